@@ -0,0 +1,93 @@
+use std::{
+    io::{self, Error, ErrorKind, Read, Write},
+    net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+// Answers a request with nothing but the address it came from.
+pub const PUBLIC_ADDRESS_SERVICE: &str = "api.ipify.org";
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// The address this machine has on its local network. Connecting a UDP socket sends nothing, but it
+// makes the system pick the interface that leads to the internet.
+pub fn lan_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    let address = socket.local_addr().ok()?.ip();
+    if address.is_unspecified() {
+        None
+    } else {
+        Some(address)
+    }
+}
+
+// What came back from an HTTP request, with its headers left out.
+pub struct HttpResponse {
+    // e.g. "HTTP/1.1 200 OK"
+    pub status_line: String,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn is_ok(&self) -> bool {
+        self.status_line.split_whitespace().nth(1) == Some("200")
+    }
+}
+
+// Sends a whole HTTP/1.0 request, whose response ends with the connection and is never chunked.
+pub fn http_request(host: &str, port: u16, request: &str) -> io::Result<HttpResponse> {
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("{} wasn't found.", host)))?;
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    stream.write_all(request.as_bytes())?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+
+    let (head, body) = response.split_once("\r\n\r\n").ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("The response from {} was cut off.", host),
+        )
+    })?;
+    Ok(HttpResponse {
+        status_line: head.lines().next().unwrap_or_default().to_string(),
+        body: body.to_string(),
+    })
+}
+
+// Asks PUBLIC_ADDRESS_SERVICE which address our requests come from, which is the router's address
+// that players outside the network connect to. It's asked over HTTPS with curl, like the EULA is
+// downloaded, so that the answer can't be changed on the way and curl deals with the HTTP.
+pub fn public_address() -> io::Result<IpAddr> {
+    let output = Command::new("curl")
+        .args([
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--max-time",
+            "5",
+            &format!("https://{}", PUBLIC_ADDRESS_SERVICE),
+        ])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|error| Error::new(error.kind(), format!("Unable to run curl: {}", error)))?;
+    let invalid = |message: &str| Error::new(ErrorKind::InvalidData, message.to_string());
+    if !output.status.success() {
+        return Err(invalid(String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|_| invalid("The service didn't answer with an address."))
+}
+
+// What players type into "Server Address", e.g. "203.0.113.7:25565".
+pub fn connection_string(address: IpAddr, port: u16) -> String {
+    SocketAddr::new(address, port).to_string()
+}
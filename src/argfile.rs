@@ -0,0 +1,28 @@
+use std::{fs, io, path::Path};
+
+// Java (9 and newer) reads its arguments from a file passed as @file, which gets around the limit
+// on the length of a command line.
+pub const ARGFILE_FILENAME: &str = "msc-java-args.txt";
+
+// Windows refuses command lines longer than 32767 characters. This leaves room for whatever it adds
+// on the way, e.g. when the path to java is expanded.
+pub const WINDOWS_COMMAND_LINE_LIMIT: usize = 30_000;
+
+// Every argument is quoted, since unquoted ones are split on whitespace and backslashes only escape
+// inside quotes, where Windows paths need them doubled.
+fn quote_for_argfile(argument: &str) -> String {
+    format!(
+        "\"{}\"",
+        argument.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+// Writes the arguments for java to the file, one per line.
+pub fn write_argfile(path: &Path, arguments: &[String]) -> io::Result<()> {
+    let mut contents = String::new();
+    for argument in arguments {
+        contents.push_str(&quote_for_argfile(argument));
+        contents.push('\n');
+    }
+    fs::write(path, contents)
+}
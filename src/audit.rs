@@ -0,0 +1,58 @@
+use std::{
+    env,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::clock::DateTime;
+
+// Kept in the server's directory, next to the configuration it records changes to.
+pub const AUDIT_LOG_FILENAME: &str = "msc-audit.log";
+
+#[cfg(unix)]
+fn account_name() -> Option<String> {
+    let account = unsafe { libc::getpwuid(libc::geteuid()) };
+    if account.is_null() {
+        return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr((*account).pw_name) };
+    Some(name.to_string_lossy().to_string())
+}
+
+#[cfg(not(unix))]
+fn account_name() -> Option<String> {
+    None
+}
+
+// Whoever is at the keyboard, which is the admin's own name rather than root's under sudo.
+fn current_user() -> String {
+    ["SUDO_USER", "USER", "USERNAME"]
+        .iter()
+        .find_map(|variable| env::var(variable).ok().filter(|name| !name.is_empty()))
+        .or_else(account_name)
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+// Appends a line for one change to an option, e.g.
+// "2021-04-20 13:37:00 alex default maxMemory: Some("2G") → Some("4G")". Lines are only ever added, so
+// the file reads as the history of the configuration.
+pub fn append_audit_entry(
+    path: &Path,
+    instance: &str,
+    property: &str,
+    old: &str,
+    new: &str,
+) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(
+        file,
+        "{} {} {} {}: {} → {}",
+        DateTime::now(),
+        current_user(),
+        instance,
+        property,
+        old,
+        new
+    )
+}
@@ -0,0 +1,284 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use crate::clock::DateTime;
+
+// Backups are kept next to the world, in a folder per world so that pruning one world's backups
+// never touches another's.
+pub const BACKUPS_DIRECTORY: &str = "msc-backups";
+
+// How far along a backup is, reported after every file that is copied.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BackupProgress {
+    pub copied_bytes: u64,
+    pub copied_files: u64,
+    // None when the world's size couldn't be read up front.
+    pub total_bytes: Option<u64>,
+}
+
+fn directory_size(path: &Path) -> Result<u64, Error> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        size += if entry.file_type()?.is_dir() {
+            directory_size(&entry.path())?
+        } else {
+            entry.metadata()?.len()
+        };
+    }
+    Ok(size)
+}
+
+fn copy_directory(
+    source: &Path,
+    destination: &Path,
+    progress: &mut BackupProgress,
+    report: &mut dyn FnMut(&BackupProgress),
+) -> Result<(), Error> {
+    fs::create_dir_all(destination)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let target = destination.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_directory(&entry.path(), &target, progress, report)?;
+        } else {
+            progress.copied_bytes += fs::copy(entry.path(), &target)?;
+            progress.copied_files += 1;
+            report(progress);
+        }
+    }
+    Ok(())
+}
+
+// The name of a backup taken now, with `suffix` after its timestamp.
+fn backup_name(suffix: &str) -> String {
+    let now = DateTime::now();
+    format!("{}_{}{}", now.date(), now.time().replace(':', "-"), suffix)
+}
+
+// Copies the world into a new backup named after the current time, e.g.
+// "msc-backups/world/2021-04-20_13-37-00", and returns where it went. The world's size is added up
+// before copying so that `report` can tell how much is left.
+pub fn back_up_world(
+    world: &Path,
+    report: &mut dyn FnMut(&BackupProgress),
+) -> Result<PathBuf, Error> {
+    let world_name = world
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "world".to_string());
+    let backup = Path::new(BACKUPS_DIRECTORY)
+        .join(world_name)
+        .join(backup_name(""));
+    let mut progress = BackupProgress {
+        total_bytes: directory_size(world).ok(),
+        ..BackupProgress::default()
+    };
+    report(&progress);
+    copy_directory(world, &backup, &mut progress, report)?;
+    Ok(backup)
+}
+
+// The world's backups, newest first.
+pub fn list_backups(world: &Path) -> Vec<PathBuf> {
+    let world_name = match world.file_name() {
+        Some(name) => name,
+        None => return Vec::new(),
+    };
+    let mut backups: Vec<PathBuf> =
+        match fs::read_dir(Path::new(BACKUPS_DIRECTORY).join(world_name)) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+    backups.sort();
+    backups.reverse();
+    backups
+}
+
+fn sibling(world: &Path, suffix: &str) -> PathBuf {
+    let mut name = world.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    world.with_file_name(name)
+}
+
+// Puts the backup in the world's place and returns where the world it replaced was kept. The backup is
+// copied next to the world first, and the world is only swapped out once that worked, so a failed
+// copy leaves it untouched. The replaced world becomes a backup of its own next to the restored one,
+// e.g. "msc-backups/world/2021-04-20_13-37-00_before-restore", or stays next to the world as
+// "world.before-restore" when it can't be moved there.
+pub fn restore_backup(backup: &Path, world: &Path) -> Result<Option<PathBuf>, Error> {
+    let staging = sibling(world, ".restoring");
+    let previous = sibling(world, ".before-restore");
+    // A restore that was interrupted between its two renames leaves the only copy of the world here.
+    if previous.exists() {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!(
+                "{} is left over from a restore that didn't finish and may be the only copy of the world. Move it back or out of the way, then try again.",
+                previous.display()
+            ),
+        ));
+    }
+    // Whatever is staged is only a copy of a backup that's still there.
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    if let Err(error) = copy_directory(
+        backup,
+        &staging,
+        &mut BackupProgress::default(),
+        &mut |_| {},
+    ) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(error);
+    }
+
+    let replaced = if world.exists() {
+        let kept = backup.with_file_name(backup_name("_before-restore"));
+        let moved = fs::rename(world, &kept);
+        // Renaming fails across file systems, e.g. when msc-backups is a mount of its own.
+        if moved.is_ok() {
+            Some(kept)
+        } else {
+            fs::rename(world, &previous)?;
+            Some(previous)
+        }
+    } else {
+        None
+    };
+    if let Err(error) = fs::rename(&staging, world) {
+        if let Some(replaced) = &replaced {
+            fs::rename(replaced, world)?;
+        }
+        let _ = fs::remove_dir_all(&staging);
+        return Err(error);
+    }
+    Ok(replaced)
+}
+
+// Removes all but the newest `keep` backups in the directory and returns how many were removed.
+// Backup names start with their timestamp, so sorting them by name sorts them by age. `confirm` is
+// shown the backups that would be removed first, and nothing is removed when it returns false.
+pub fn prune_backups(
+    dir: &Path,
+    keep: u16,
+    confirm: &mut dyn FnMut(&[PathBuf]) -> bool,
+) -> Result<usize, Error> {
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            backups.push(entry.path());
+        }
+    }
+    backups.sort();
+
+    let excess = backups.len().saturating_sub(keep as usize);
+    if excess == 0 || !confirm(&backups[..excess]) {
+        return Ok(0);
+    }
+    for backup in &backups[..excess] {
+        fs::remove_dir_all(backup)?;
+    }
+    Ok(excess)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("msc-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn make_world(path: &Path, level: &str) {
+        fs::create_dir_all(path.join("region")).unwrap();
+        fs::write(path.join("level.dat"), level).unwrap();
+        fs::write(path.join("region").join("r.0.0.mca"), level).unwrap();
+    }
+
+    #[test]
+    fn restoring_keeps_the_replaced_world_as_a_backup() {
+        let directory = scratch_directory("restore-keeps-world");
+        let world = directory.join("world");
+        let backup = directory.join("backups").join("2021-04-20_13-37-00");
+        make_world(&world, "current");
+        make_world(&backup, "old");
+
+        let kept = restore_backup(&backup, &world).unwrap().unwrap();
+
+        assert_eq!(fs::read_to_string(world.join("level.dat")).unwrap(), "old");
+        assert_eq!(
+            fs::read_to_string(world.join("region").join("r.0.0.mca")).unwrap(),
+            "old"
+        );
+        assert_eq!(kept.parent(), backup.parent());
+        assert!(kept.to_string_lossy().ends_with("_before-restore"));
+        assert_eq!(
+            fs::read_to_string(kept.join("level.dat")).unwrap(),
+            "current"
+        );
+        // The restored backup is still there to restore again.
+        assert_eq!(fs::read_to_string(backup.join("level.dat")).unwrap(), "old");
+        assert!(!sibling(&world, ".restoring").exists());
+        assert!(!sibling(&world, ".before-restore").exists());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn restoring_without_a_world_just_puts_the_backup_in_place() {
+        let directory = scratch_directory("restore-missing-world");
+        let world = directory.join("world");
+        let backup = directory.join("backups").join("2021-04-20_13-37-00");
+        make_world(&backup, "old");
+
+        assert_eq!(restore_backup(&backup, &world).unwrap(), None);
+        assert_eq!(fs::read_to_string(world.join("level.dat")).unwrap(), "old");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn restoring_refuses_to_touch_a_leftover_from_an_interrupted_restore() {
+        let directory = scratch_directory("restore-leftover");
+        let world = directory.join("world");
+        let backup = directory.join("backups").join("2021-04-20_13-37-00");
+        let leftover = sibling(&world, ".before-restore");
+        make_world(&backup, "old");
+        make_world(&leftover, "the only copy");
+
+        let error = restore_backup(&backup, &world).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(
+            fs::read_to_string(leftover.join("level.dat")).unwrap(),
+            "the only copy"
+        );
+        assert!(!world.exists());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn a_failed_copy_leaves_the_world_alone() {
+        let directory = scratch_directory("restore-failed-copy");
+        let world = directory.join("world");
+        make_world(&world, "current");
+
+        assert!(restore_backup(&directory.join("missing"), &world).is_err());
+        assert_eq!(
+            fs::read_to_string(world.join("level.dat")).unwrap(),
+            "current"
+        );
+        assert!(!sibling(&world, ".restoring").exists());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// A calendar date and time of day, in local time where the platform lets us find out the time zone
+// and in UTC otherwise.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl DateTime {
+    // Converts seconds since the Unix epoch to a UTC date, using the days-to-civil algorithm from
+    // https://howardhinnant.github.io/date_algorithms.html.
+    pub fn from_unix_utc(timestamp: i64) -> DateTime {
+        let days = timestamp.div_euclid(86400);
+        let seconds = timestamp.rem_euclid(86400) as u32;
+
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days.rem_euclid(146097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        } as u32;
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        DateTime {
+            year,
+            month,
+            day,
+            hour: seconds / 3600,
+            minute: seconds / 60 % 60,
+            second: seconds % 60,
+        }
+    }
+
+    #[cfg(unix)]
+    pub fn from_unix_local(timestamp: i64) -> DateTime {
+        let time = timestamp as libc::time_t;
+        let mut local: libc::tm = unsafe { std::mem::zeroed() };
+        if unsafe { libc::localtime_r(&time, &mut local) }.is_null() {
+            return DateTime::from_unix_utc(timestamp);
+        }
+        DateTime {
+            year: local.tm_year as i64 + 1900,
+            month: local.tm_mon as u32 + 1,
+            day: local.tm_mday as u32,
+            hour: local.tm_hour as u32,
+            minute: local.tm_min as u32,
+            second: local.tm_sec as u32,
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn from_unix_local(timestamp: i64) -> DateTime {
+        DateTime::from_unix_utc(timestamp)
+    }
+
+    pub fn now() -> DateTime {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or(0);
+        DateTime::from_unix_local(timestamp)
+    }
+
+    // e.g. "2021-04-20"
+    pub fn date(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+
+    // e.g. "13:37:00"
+    pub fn time(&self) -> String {
+        format!("{:02}:{:02}:{:02}", self.hour, self.minute, self.second)
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{} {}", self.date(), self.time())
+    }
+}
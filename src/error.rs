@@ -0,0 +1,63 @@
+use std::{error::Error, fmt, io, path::PathBuf};
+
+// Everything that can go wrong in a way the user needs to hear about. These are passed up to `main`,
+// which is the only place that reports them.
+#[derive(Debug)]
+pub enum MscError {
+    ConfigRead { path: PathBuf, source: io::Error },
+    // The description points at where in the file parsing failed (see `describe_parse_error`).
+    ConfigParse { path: PathBuf, description: String },
+    ConfigWrite { path: PathBuf, source: io::Error },
+    Launch(io::Error),
+    Validation(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for MscError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MscError::ConfigRead { path, source } => {
+                write!(formatter, "Unable to read {}: {}", path.display(), source)
+            }
+            MscError::ConfigParse { path, description } => write!(
+                formatter,
+                "Unable to successfully parse {}.\n{}",
+                path.display(),
+                description
+            ),
+            MscError::ConfigWrite { path, source } => {
+                write!(
+                    formatter,
+                    "Unable to write to {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+            MscError::Launch(source) => write!(
+                formatter,
+                "Unable to launch the server ({}). Do you have Java installed?",
+                source
+            ),
+            MscError::Validation(message) => write!(formatter, "{}", message),
+            MscError::Io(source) => write!(formatter, "{}", source),
+        }
+    }
+}
+
+impl Error for MscError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MscError::ConfigRead { source, .. }
+            | MscError::ConfigWrite { source, .. }
+            | MscError::Launch(source)
+            | MscError::Io(source) => Some(source),
+            MscError::ConfigParse { .. } | MscError::Validation(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for MscError {
+    fn from(error: io::Error) -> Self {
+        MscError::Io(error)
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    fs, io,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use crate::properties::ServerProperties;
+
+pub const EULA_FILENAME: &str = "eula.txt";
+pub const EULA_URL: &str = "https://aka.ms/MinecraftEULA";
+// Kept in the configuration directory, since the EULA is the same for every server.
+pub const EULA_CACHE_FILENAME: &str = "minecraft-eula.txt";
+
+pub fn is_eula_accepted() -> bool {
+    ServerProperties::read(Path::new(EULA_FILENAME))
+        .map(|eula| accepts_eula(&eula))
+        .unwrap_or(false)
+}
+
+// The server reads the value with Java's Boolean.parseBoolean, which ignores the case, e.g. after
+// the comment's "change the setting below to TRUE".
+fn accepts_eula(eula: &ServerProperties) -> bool {
+    eula.get("eula")
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+}
+
+// Records that the EULA was accepted, keeping the rest of an existing eula.txt (like the server's
+// comments) intact.
+pub fn accept_eula() -> io::Result<()> {
+    let mut eula = ServerProperties::read(Path::new(EULA_FILENAME)).unwrap_or_else(|_| {
+        ServerProperties::parse(&format!(
+            "#By changing the setting below to TRUE you are indicating your agreement to our EULA ({}).",
+            EULA_URL
+        ))
+    });
+    eula.set("eula", "true");
+    eula.write(Path::new(EULA_FILENAME))
+}
+
+// Turns the EULA's web page into plain text: scripts and styles are dropped, block elements end lines,
+// and the entities that the page uses are decoded.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            // A tag that's cut off is dropped along with the rest of the page.
+            None => {
+                rest = "";
+                break;
+            }
+        };
+        let tag = rest[start + 1..end].to_ascii_lowercase();
+        let name = tag
+            .trim_start_matches('/')
+            .split(|character: char| character.is_whitespace() || character == '/')
+            .next()
+            .unwrap_or("");
+        rest = &rest[end + 1..];
+        if !tag.starts_with('/') && (name == "script" || name == "style") {
+            let closing = format!("</{}", name);
+            rest = match rest.to_ascii_lowercase().find(&closing) {
+                Some(index) => &rest[index..],
+                None => "",
+            };
+            continue;
+        }
+        if matches!(
+            name,
+            "br" | "p" | "div" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+        ) {
+            text.push('\n');
+        }
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&rsquo;", "'")
+        .replace("&lsquo;", "'")
+        .replace("&rdquo;", "\"")
+        .replace("&ldquo;", "\"")
+        .replace("&mdash;", "-")
+        .replace("&ndash;", "-")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&");
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        // Keeps paragraphs apart with a single blank line.
+        if !line.is_empty() || lines.last().is_some_and(|last| !last.is_empty()) {
+            lines.push(line);
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+// The EULA's text, downloaded with curl (which also ships with Windows 10 and later) the first time
+// and read from `cache` after that.
+pub fn fetch_eula_text(cache: &Path) -> io::Result<String> {
+    if let Ok(text) = fs::read_to_string(cache) {
+        if !text.trim().is_empty() {
+            return Ok(text);
+        }
+    }
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", EULA_URL])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|error| io::Error::new(error.kind(), format!("Unable to run curl: {}", error)))?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "Unable to download the EULA: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let text = html_to_text(&String::from_utf8_lossy(&output.stdout));
+    if text.is_empty() {
+        return Err(io::Error::other("The downloaded EULA was empty."));
+    }
+    // A cache that can't be written only means downloading it again next time.
+    if let Some(directory) = cache.parent() {
+        let _ = fs::create_dir_all(directory);
+    }
+    let _ = fs::write(cache, &text);
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_eula_is_accepted_in_any_case() {
+        for text in [
+            "eula=true",
+            "eula=TRUE",
+            "eula = True ",
+            "#comment\neula=true\n",
+        ] {
+            assert!(accepts_eula(&ServerProperties::parse(text)), "{:?}", text);
+        }
+        for text in ["eula=false", "eula=", "eula=yes", ""] {
+            assert!(!accepts_eula(&ServerProperties::parse(text)), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn the_eula_page_becomes_plain_text() {
+        let html = "<html><head><style>p { color: red; }</style><script>var a = 1 < 2;</script></head>\
+            <body><h1>Minecraft EULA</h1><p>You&rsquo;re allowed to &ldquo;play&rdquo;.</p>\
+            <p>Line<br>break &amp; more &lt;rules&gt;</p><ul><li>One</li><li>Two</li></ul></body></html>";
+        assert_eq!(
+            html_to_text(html),
+            "Minecraft EULA\n\nYou're allowed to \"play\".\n\nLine\nbreak & more <rules>\n\nOne\n\nTwo"
+        );
+        assert_eq!(html_to_text("Text and <an unclosed tag"), "Text and");
+        assert_eq!(html_to_text("<SCRIPT>hidden</SCRIPT>shown"), "shown");
+    }
+
+    #[test]
+    fn a_cached_eula_is_read_without_downloading_it() {
+        let cache = std::env::temp_dir().join(format!("msc-test-eula-{}.txt", std::process::id()));
+        fs::write(&cache, "The cached EULA.").unwrap();
+        let text = fetch_eula_text(&cache);
+        fs::remove_file(&cache).unwrap();
+        assert_eq!(text.unwrap(), "The cached EULA.");
+    }
+}
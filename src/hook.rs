@@ -0,0 +1,17 @@
+use std::process::Command;
+
+// A command from the configuration, run by the platform's shell so that it can use pipes, variables,
+// and so on. It gets the server's port in MSC_PORT.
+pub fn hook_command(command_line: &str, port: u16) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", command_line]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", command_line]);
+        command
+    };
+    command.env("MSC_PORT", port.to_string());
+    command
+}
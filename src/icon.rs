@@ -0,0 +1,29 @@
+use std::{
+    fs::File,
+    io::{self, Error, ErrorKind, Read},
+    path::Path,
+};
+
+// Shown next to the server in the multiplayer list, but only when it's exactly 64×64 pixels.
+pub const SERVER_ICON_FILENAME: &str = "server-icon.png";
+pub const SERVER_ICON_SIZE: u32 = 64;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+// The width and height of a PNG, from its IHDR chunk, which always comes right after the signature.
+pub fn read_png_dimensions(path: &Path) -> io::Result<(u32, u32)> {
+    let not_png = || Error::new(ErrorKind::InvalidData, "This isn't a PNG image");
+    let mut header = [0u8; 24];
+    File::open(path)?
+        .read_exact(&mut header)
+        .map_err(|error| match error.kind() {
+            ErrorKind::UnexpectedEof => not_png(),
+            _ => error,
+        })?;
+    if header[..8] != PNG_SIGNATURE || &header[12..16] != b"IHDR" {
+        return Err(not_png());
+    }
+    let width = u32::from_be_bytes([header[16], header[17], header[18], header[19]]);
+    let height = u32::from_be_bytes([header[20], header[21], header[22], header[23]]);
+    Ok((width, height))
+}
@@ -0,0 +1,285 @@
+use std::{
+    fmt, fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{ping::json_value_after, zip::read_zip_entry};
+
+// The stable name that launcher scripts and service files can point at.
+pub const ACTIVE_JAR_FILENAME: &str = "server.jar";
+
+// Where the copy of the jar that was made for server.jar is described, outside of Unix.
+#[cfg(not(unix))]
+const ACTIVE_JAR_COPY_FILENAME: &str = "msc-server-jar.txt";
+
+// Matches a file name against a pattern where "*" matches any run of characters and "?" matches
+// exactly one character.
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            wildcard_match(&pattern[1..], name)
+                || (!name.is_empty() && wildcard_match(pattern, &name[1..]))
+        }
+        (Some('?'), Some(_)) => wildcard_match(&pattern[1..], &name[1..]),
+        (Some(expected), Some(actual)) if expected == actual => {
+            wildcard_match(&pattern[1..], &name[1..])
+        }
+        _ => false,
+    }
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+// Resolves the jar_filename argument to a single jar. Exact file names are returned unchanged, while
+// patterns like "server-*.jar" pick the most recently modified matching file.
+pub fn resolve_jar(pattern: &str) -> Result<PathBuf, Error> {
+    if !is_glob(pattern) {
+        return Ok(PathBuf::from(pattern));
+    }
+
+    let pattern_path = Path::new(pattern);
+    let directory = match pattern_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    if is_glob(&directory.to_string_lossy()) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Only the file name of the jar may contain wildcards.",
+        ));
+    }
+    let file_pattern: Vec<char> = pattern_path
+        .file_name()
+        .map(|name| name.to_string_lossy().chars().collect())
+        .unwrap_or_default();
+
+    let mut newest: Option<(SystemTime, PathBuf)> = None;
+    for entry in fs::read_dir(&directory)? {
+        let entry = entry?;
+        let name: Vec<char> = entry.file_name().to_string_lossy().chars().collect();
+        if !entry.file_type()?.is_file() || !wildcard_match(&file_pattern, &name) {
+            continue;
+        }
+        let modified = entry.metadata()?.modified()?;
+        if newest.as_ref().is_none_or(|(time, _)| modified > *time) {
+            newest = Some((modified, entry.path()));
+        }
+    }
+
+    newest.map(|(_, path)| path).ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("No server jar matches the pattern \"{}\".", pattern),
+        )
+    })
+}
+
+// Points server.jar at the given jar: a symlink on Unix, or a copy on Windows where creating symlinks
+// requires extra privileges. A server.jar that msc didn't make, a regular file on Unix or one that
+// doesn't match the recorded copy elsewhere, is never replaced since it is most likely the user's
+// actual server jar.
+pub fn set_active_jar(target: &Path) -> Result<(), Error> {
+    let active_jar = Path::new(ACTIVE_JAR_FILENAME);
+    if target == active_jar {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} is already the jar being used.", ACTIVE_JAR_FILENAME),
+        ));
+    }
+
+    #[cfg(unix)]
+    {
+        match fs::symlink_metadata(active_jar) {
+            Ok(metadata) if metadata.file_type().is_symlink() => fs::remove_file(active_jar)?,
+            Ok(_) => {
+                return Err(Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!(
+                        "{} already exists and isn't a symlink, so it was left alone.",
+                        ACTIVE_JAR_FILENAME
+                    ),
+                ))
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+        std::os::unix::fs::symlink(target, active_jar)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let record = Path::new(ACTIVE_JAR_COPY_FILENAME);
+        match fs::metadata(active_jar) {
+            Ok(metadata) if !is_recorded_copy(&metadata, record) => {
+                return Err(Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!(
+                        "{} already exists and wasn't copied there by msc, so it was left alone.",
+                        ACTIVE_JAR_FILENAME
+                    ),
+                ))
+            }
+            Ok(_) => {}
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+        fs::copy(target, active_jar)?;
+        record_copy(&fs::metadata(active_jar)?, record)
+    }
+}
+
+// A copy is told apart by its size and when it was written, which change when anything else is put
+// in its place.
+#[cfg(any(not(unix), test))]
+fn copy_fingerprint(metadata: &fs::Metadata) -> Option<String> {
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .ok()?;
+    Some(format!("{} {}", metadata.len(), modified.as_nanos()))
+}
+
+#[cfg(any(not(unix), test))]
+fn record_copy(metadata: &fs::Metadata, record: &Path) -> Result<(), Error> {
+    let fingerprint = copy_fingerprint(metadata).ok_or_else(|| {
+        Error::other(format!(
+            "{} has no modification time to recognize the copy by.",
+            ACTIVE_JAR_FILENAME
+        ))
+    })?;
+    fs::write(record, fingerprint)
+}
+
+#[cfg(any(not(unix), test))]
+fn is_recorded_copy(metadata: &fs::Metadata, record: &Path) -> bool {
+    match (fs::read_to_string(record), copy_fingerprint(metadata)) {
+        (Ok(recorded), Some(fingerprint)) => recorded.trim() == fingerprint,
+        _ => false,
+    }
+}
+
+// The Minecraft version that a server jar runs, from the version.json that Mojang puts in it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct JarVersion {
+    pub name: String,
+    // Increases with every snapshot and release, e.g. 3700 for 1.20.4.
+    pub data_version: Option<u32>,
+}
+
+impl fmt::Display for JarVersion {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.data_version {
+            Some(data_version) => {
+                write!(formatter, "{} (data version {})", self.name, data_version)
+            }
+            None => write!(formatter, "{}", self.name),
+        }
+    }
+}
+
+// Jars from modded and plugin launchers often don't have a version.json, which is a NotFound error.
+pub fn read_jar_version(path: &Path) -> Result<JarVersion, Error> {
+    let json = read_zip_entry(path, "version.json")?.ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("{} doesn't have a version.json.", path.display()),
+        )
+    })?;
+    let json = String::from_utf8_lossy(&json);
+    let name = json_value_after(&json, "name")
+        .or_else(|| json_value_after(&json, "id"))
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "version.json doesn't have a name."))?;
+    Ok(JarVersion {
+        name: name.to_string(),
+        data_version: json_value_after(&json, "world_version")
+            .and_then(|version| version.parse().ok()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_copy_that_was_recorded_may_be_replaced() {
+        let directory =
+            std::env::temp_dir().join(format!("msc-test-jar-copy-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let jar = directory.join(ACTIVE_JAR_FILENAME);
+        let record = directory.join("record.txt");
+        fs::write(&jar, "copied jar").unwrap();
+        assert!(!is_recorded_copy(&fs::metadata(&jar).unwrap(), &record));
+
+        record_copy(&fs::metadata(&jar).unwrap(), &record).unwrap();
+        assert!(is_recorded_copy(&fs::metadata(&jar).unwrap(), &record));
+
+        // e.g. the user's own server jar, downloaded over the copy.
+        fs::write(&jar, "the user's jar").unwrap();
+        assert!(!is_recorded_copy(&fs::metadata(&jar).unwrap(), &record));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn wildcards_match_any_run_or_exactly_one_character() {
+        let matches = |pattern: &str, name: &str| {
+            let pattern: Vec<char> = pattern.chars().collect();
+            let name: Vec<char> = name.chars().collect();
+            wildcard_match(&pattern, &name)
+        };
+        assert!(matches("server-*.jar", "server-1.20.4.jar"));
+        assert!(matches("server-*.jar", "server-.jar"));
+        assert!(matches("paper-1.20.?.jar", "paper-1.20.4.jar"));
+        assert!(matches("*", "server.jar"));
+        assert!(!matches("paper-1.20.?.jar", "paper-1.20.jar"));
+        assert!(!matches("server-*.jar", "server-1.20.4.jar.bak"));
+        assert!(!matches("server-*.jar", "paper-1.20.4.jar"));
+    }
+
+    #[test]
+    fn patterns_resolve_to_the_newest_matching_jar() {
+        let directory =
+            std::env::temp_dir().join(format!("msc-test-jar-glob-{}", std::process::id()));
+        fs::create_dir_all(directory.join("server-directory.jar")).unwrap();
+        let now = SystemTime::now();
+        for (name, age) in [
+            ("server-1.20.3.jar", 30),
+            ("server-1.20.4.jar", 20),
+            ("server-1.19.jar", 10),
+            ("paper-1.20.4.jar", 0),
+        ] {
+            let file = fs::File::create(directory.join(name)).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(age))
+                .unwrap();
+        }
+        let resolve = |pattern: &str| resolve_jar(&directory.join(pattern).to_string_lossy());
+
+        assert_eq!(
+            resolve("server-*.jar").unwrap(),
+            directory.join("server-1.19.jar")
+        );
+        assert_eq!(
+            resolve("server-1.20.?.jar").unwrap(),
+            directory.join("server-1.20.4.jar")
+        );
+        assert_eq!(
+            resolve("forge-*.jar").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            resolve("*/server.jar").unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+        // Exact names are left alone, whether or not they exist.
+        assert_eq!(
+            resolve_jar("missing.jar").unwrap(),
+            PathBuf::from("missing.jar")
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
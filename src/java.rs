@@ -0,0 +1,155 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+// The Java that runs the server when no javaPath is configured.
+pub const DEFAULT_JAVA: &str = "java";
+
+fn java_filename() -> String {
+    format!("{}{}", DEFAULT_JAVA, env::consts::EXE_SUFFIX)
+}
+
+// Which Java runs the server: the configured javaPath, then java from the PATH, then the one in
+// JAVA_HOME, which JDK installers often set without adding Java to the PATH. Java from the PATH is
+// left as DEFAULT_JAVA so that the command stays short.
+pub fn resolve_java(configured: Option<&str>) -> Option<PathBuf> {
+    if let Some(java) = configured {
+        return Some(PathBuf::from(java));
+    }
+    let filename = java_filename();
+    let on_path = env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|directory| directory.join(&filename).is_file()))
+        .unwrap_or(false);
+    if on_path {
+        return Some(PathBuf::from(DEFAULT_JAVA));
+    }
+    env::var_os("JAVA_HOME")
+        .map(|home| Path::new(&home).join("bin").join(&filename))
+        .filter(|java| java.is_file())
+}
+
+// Whether the server's GUI window could open. On Linux and the BSDs that needs an X11 or Wayland
+// display, which servers reached over SSH usually don't have.
+pub fn has_display() -> bool {
+    if cfg!(any(windows, target_os = "macos")) {
+        return true;
+    }
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|variable| env::var_os(variable).is_some_and(|value| !value.is_empty()))
+}
+
+// Whether this Java can be run at all, which is what launching the server needs. `java -version` is
+// the cheapest thing to ask the JVM, but it still starts one, so callers should only ask once.
+pub fn is_java_available(java: &str) -> bool {
+    Command::new(java)
+        .arg("-version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+// A JDK or JRE found on this machine.
+#[derive(Debug, PartialEq, Clone)]
+pub struct JavaInstall {
+    pub home: PathBuf,
+    pub executable: PathBuf,
+    // e.g. "17.0.2", from the install's `release` file.
+    pub version: Option<String>,
+}
+
+fn read_version(home: &Path) -> Option<String> {
+    fs::read_to_string(home.join("release"))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("JAVA_VERSION="))
+        .map(|version| version.trim().trim_matches('"').to_string())
+}
+
+fn java_install(home: &Path) -> Option<JavaInstall> {
+    let executable = home.join("bin").join("java.exe");
+    if !executable.is_file() {
+        return None;
+    }
+    Some(JavaInstall {
+        home: home.to_path_buf(),
+        executable,
+        version: read_version(home),
+    })
+}
+
+// The Java homes that installers registered, from lines like
+// "    JavaHome    REG_SZ    C:\Program Files\Java\jdk-17".
+fn registry_java_homes() -> Vec<PathBuf> {
+    let keys = [
+        r"HKLM\SOFTWARE\JavaSoft\JDK",
+        r"HKLM\SOFTWARE\JavaSoft\Java Development Kit",
+        r"HKLM\SOFTWARE\JavaSoft\Java Runtime Environment",
+    ];
+    let mut homes = Vec::new();
+    for key in keys {
+        let output = match Command::new("reg")
+            .args(["query", key, "/s", "/v", "JavaHome"])
+            .stderr(Stdio::null())
+            .output()
+        {
+            Ok(output) => output,
+            Err(_) => return homes,
+        };
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some((_, home)) = line.split_once("REG_SZ") {
+                homes.push(PathBuf::from(home.trim()));
+            }
+        }
+    }
+    homes
+}
+
+// Lists the Java installs in the folders that the common Windows installers use (Oracle, Eclipse
+// Adoptium, Microsoft, Zulu, and so on), and the ones in the registry.
+pub fn discover_java_installs() -> Vec<JavaInstall> {
+    let vendors = [
+        "Java",
+        "Eclipse Adoptium",
+        "Eclipse Foundation",
+        "AdoptOpenJDK",
+        "Microsoft",
+        "Zulu",
+        "Amazon Corretto",
+        "BellSoft",
+    ];
+    let mut homes = Vec::new();
+    for program_files in ["ProgramFiles", "ProgramFiles(x86)"]
+        .iter()
+        .filter_map(env::var_os)
+    {
+        for vendor in vendors {
+            let entries = match fs::read_dir(Path::new(&program_files).join(vendor)) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            homes.extend(
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path()),
+            );
+        }
+    }
+    homes.extend(registry_java_homes());
+
+    let mut installs: Vec<JavaInstall> = Vec::new();
+    for home in homes {
+        if let Some(install) = java_install(&home) {
+            if !installs.iter().any(|known| known.home == install.home) {
+                installs.push(install);
+            }
+        }
+    }
+    installs.sort_by(|a, b| a.home.cmp(&b.home));
+    installs
+}
@@ -0,0 +1,373 @@
+use std::{
+    convert::{TryFrom, TryInto},
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use crate::zip::gunzip;
+
+// The game rules shown in the world's info, out of the dozens that the server keeps.
+pub const SHOWN_GAME_RULES: &[&str] = &[
+    "keepInventory",
+    "doDaylightCycle",
+    "doMobSpawning",
+    "mobGriefing",
+    "doFireTick",
+    "playersSleepingPercentage",
+];
+
+// Compounds nested deeper than this are treated as corrupt, which is the limit Minecraft uses too.
+const MAX_DEPTH: usize = 512;
+
+// What level.dat says about a world. Everything is optional because old versions and other
+// programs leave out different parts of it.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct LevelInfo {
+    pub name: Option<String>,
+    // The Minecraft version that last saved the world, e.g. "1.20.4".
+    pub version: Option<String>,
+    pub seed: Option<i64>,
+    // e.g. "default", "flat", or "amplified".
+    pub generator: Option<String>,
+    // The game rules in SHOWN_GAME_RULES that the world has, in that order.
+    pub game_rules: Vec<(String, String)>,
+}
+
+// The parts of the NBT format that level.dat uses. Arrays are skipped over since nothing here needs
+// them.
+#[derive(Debug, PartialEq, Clone)]
+enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    List(Vec<Tag>),
+    Compound(Vec<(String, Tag)>),
+    Array,
+}
+
+impl Tag {
+    fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(entries) => entries
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, tag)| tag),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Tag::Byte(value) => Some(value as i64),
+            Tag::Short(value) => Some(value as i64),
+            Tag::Int(value) => Some(value as i64),
+            Tag::Long(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+struct NbtReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> NbtReader<'a> {
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .data
+            .get(self.position..self.position.saturating_add(length))
+            .ok_or_else(|| invalid("level.dat ended early."))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn length(&mut self) -> Result<usize, Error> {
+        let length = i32::from_be_bytes(self.array()?);
+        usize::try_from(length).map_err(|_| invalid("level.dat has a negative length."))
+    }
+
+    // Java writes strings in its "modified UTF-8", which only differs from UTF-8 for characters that
+    // don't show up in names and game rules.
+    fn string(&mut self) -> Result<String, Error> {
+        let length = u16::from_be_bytes(self.array()?) as usize;
+        Ok(String::from_utf8_lossy(self.bytes(length)?).to_string())
+    }
+
+    fn tag(&mut self, kind: u8, depth: usize) -> Result<Tag, Error> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("level.dat is nested too deeply."));
+        }
+        Ok(match kind {
+            1 => Tag::Byte(i8::from_be_bytes(self.array()?)),
+            2 => Tag::Short(i16::from_be_bytes(self.array()?)),
+            3 => Tag::Int(i32::from_be_bytes(self.array()?)),
+            4 => Tag::Long(i64::from_be_bytes(self.array()?)),
+            5 => Tag::Float(f32::from_be_bytes(self.array()?)),
+            6 => Tag::Double(f64::from_be_bytes(self.array()?)),
+            // Byte, int, and long arrays.
+            7 | 11 | 12 => {
+                let width = match kind {
+                    7 => 1,
+                    11 => 4,
+                    _ => 8,
+                };
+                let length = self.length()?;
+                self.bytes(length.saturating_mul(width))?;
+                Tag::Array
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let element_kind = self.array::<1>()?[0];
+                let length = self.length()?;
+                let mut elements = Vec::new();
+                for _ in 0..length {
+                    elements.push(self.tag(element_kind, depth + 1)?);
+                }
+                Tag::List(elements)
+            }
+            10 => {
+                let mut entries = Vec::new();
+                loop {
+                    let kind = self.array::<1>()?[0];
+                    if kind == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    entries.push((name, self.tag(kind, depth + 1)?));
+                }
+                Tag::Compound(entries)
+            }
+            _ => return Err(invalid("level.dat has an unknown kind of tag.")),
+        })
+    }
+
+    fn root(&mut self) -> Result<Tag, Error> {
+        let kind = self.array::<1>()?[0];
+        if kind != 10 {
+            return Err(invalid("level.dat doesn't start with a compound tag."));
+        }
+        self.string()?;
+        self.tag(kind, 0)
+    }
+}
+
+// Names the world's generator the way the world creation screen does. Since 1.16 the generator is
+// stored per dimension, and before that it was a single name.
+fn generator_name(data: &Tag) -> Option<String> {
+    if let Some(generator) = data
+        .get("WorldGenSettings")
+        .and_then(|settings| settings.get("dimensions"))
+        .and_then(|dimensions| dimensions.get("minecraft:overworld"))
+        .and_then(|overworld| overworld.get("generator"))
+    {
+        let kind = generator.get("type")?.as_str()?;
+        let kind = kind.strip_prefix("minecraft:").unwrap_or(kind);
+        // Flat worlds keep their settings in a compound, while noise worlds name a preset.
+        return Some(
+            match generator.get("settings").and_then(Tag::as_str) {
+                Some("minecraft:overworld") => "default",
+                Some(preset) if kind == "noise" => {
+                    preset.strip_prefix("minecraft:").unwrap_or(preset)
+                }
+                _ => kind,
+            }
+            .to_string(),
+        );
+    }
+    data.get("generatorName")
+        .and_then(Tag::as_str)
+        .map(str::to_string)
+}
+
+// Reads the world's level.dat, which is gzipped NBT.
+pub fn read_level_dat(path: &Path) -> Result<LevelInfo, Error> {
+    parse_level_dat(fs::read(path)?)
+}
+
+fn parse_level_dat(bytes: Vec<u8>) -> Result<LevelInfo, Error> {
+    let bytes = if bytes.starts_with(&[0x1f, 0x8b]) {
+        gunzip(&bytes)?
+    } else {
+        bytes
+    };
+    let root = NbtReader {
+        data: &bytes,
+        position: 0,
+    }
+    .root()?;
+    let data = root
+        .get("Data")
+        .ok_or_else(|| invalid("level.dat doesn't have any world data."))?;
+
+    let game_rules = data.get("GameRules");
+    Ok(LevelInfo {
+        name: data
+            .get("LevelName")
+            .and_then(Tag::as_str)
+            .map(str::to_string),
+        version: data
+            .get("Version")
+            .and_then(|version| version.get("Name"))
+            .and_then(Tag::as_str)
+            .map(str::to_string),
+        seed: data
+            .get("WorldGenSettings")
+            .and_then(|settings| settings.get("seed"))
+            .or_else(|| data.get("RandomSeed"))
+            .and_then(Tag::as_i64),
+        generator: generator_name(data),
+        game_rules: SHOWN_GAME_RULES
+            .iter()
+            .filter_map(|rule| {
+                let value = game_rules?.get(rule)?.as_str()?;
+                Some((rule.to_string(), value.to_string()))
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(kind: u8, name: &str, payload: &[u8]) -> Vec<u8> {
+        let mut tag = vec![kind];
+        tag.extend_from_slice(&string(name));
+        tag.extend_from_slice(payload);
+        tag
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        let mut payload = (value.len() as u16).to_be_bytes().to_vec();
+        payload.extend_from_slice(value.as_bytes());
+        payload
+    }
+
+    fn compound(entries: &[Vec<u8>]) -> Vec<u8> {
+        let mut payload = entries.concat();
+        payload.push(0);
+        payload
+    }
+
+    // A pre-1.16 level.dat, uncompressed.
+    fn level_dat() -> Vec<u8> {
+        let data = compound(&[
+            named(8, "LevelName", &string("My World")),
+            named(
+                10,
+                "Version",
+                &compound(&[named(8, "Name", &string("1.15.2"))]),
+            ),
+            named(4, "RandomSeed", &(-42i64).to_be_bytes()),
+            named(8, "generatorName", &string("flat")),
+            // An int array, which is skipped over.
+            named(11, "WanderingTraderId", &[0, 0, 0, 1, 0, 0, 0, 7]),
+            named(
+                10,
+                "GameRules",
+                &compound(&[
+                    named(8, "keepInventory", &string("true")),
+                    named(8, "doFireTick", &string("false")),
+                ]),
+            ),
+        ]);
+        named(10, "", &compound(&[named(10, "Data", &data)]))
+    }
+
+    #[test]
+    fn reads_plain_and_gzipped_level_dat() {
+        let expected = LevelInfo {
+            name: Some("My World".to_string()),
+            version: Some("1.15.2".to_string()),
+            seed: Some(-42),
+            generator: Some("flat".to_string()),
+            game_rules: vec![
+                ("keepInventory".to_string(), "true".to_string()),
+                ("doFireTick".to_string(), "false".to_string()),
+            ],
+        };
+        assert_eq!(parse_level_dat(level_dat()).unwrap(), expected);
+
+        // A gzip header and a single stored deflate block.
+        let nbt = level_dat();
+        let length = nbt.len() as u16;
+        let mut gzipped = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3, 1];
+        gzipped.extend_from_slice(&length.to_le_bytes());
+        gzipped.extend_from_slice(&(!length).to_le_bytes());
+        gzipped.extend_from_slice(&nbt);
+        gzipped.extend_from_slice(&[0; 8]);
+        assert_eq!(parse_level_dat(gzipped.clone()).unwrap(), expected);
+
+        gzipped[10] = 0x07;
+        assert!(parse_level_dat(gzipped).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_level_dat() {
+        let data = level_dat();
+        for length in 0..data.len() {
+            assert!(parse_level_dat(data[..length].to_vec()).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_corrupt_level_dat() {
+        // The root isn't a compound.
+        assert!(parse_level_dat(named(8, "", &string("Data"))).is_err());
+        // There's no Data compound.
+        assert!(parse_level_dat(named(10, "", &compound(&[]))).is_err());
+        // An unknown kind of tag.
+        assert!(parse_level_dat(named(10, "", &compound(&[named(13, "Data", &[])]))).is_err());
+        // Arrays and lists with negative or huge lengths.
+        for length in [-1i32, i32::MAX] {
+            for kind in [7, 11, 12] {
+                let array = named(kind, "Array", &length.to_be_bytes());
+                let data = named(10, "", &compound(&[named(10, "Data", &compound(&[array]))]));
+                assert!(parse_level_dat(data).is_err());
+            }
+            let mut list = vec![1];
+            list.extend_from_slice(&length.to_be_bytes());
+            let data = named(10, "", &compound(&[named(9, "Data", &list)]));
+            assert!(parse_level_dat(data).is_err());
+        }
+        // Compounds nested deeper than Minecraft allows.
+        let mut nested = named(10, "", &[]);
+        for _ in 0..=MAX_DEPTH {
+            nested.extend_from_slice(&named(10, "a", &[]));
+        }
+        nested.extend(std::iter::repeat_n(0, MAX_DEPTH + 2));
+        let error = parse_level_dat(nested).unwrap_err();
+        assert_eq!(error.to_string(), "level.dat is nested too deeply.");
+    }
+
+    #[test]
+    fn flipped_bits_never_panic() {
+        let data = level_dat();
+        for bit in 0..data.len() * 8 {
+            let mut data = data.clone();
+            data[bit / 8] ^= 1 << (bit % 8);
+            let _ = parse_level_dat(data);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+// A lockfile next to the configuration file that holds the process ID of the tool that's using it,
+// so that two copies of the tool don't take turns overwriting each other's changes. The lock is
+// released when the guard is dropped, which also happens while unwinding from a panic.
+pub struct ConfigurationLock {
+    path: PathBuf,
+}
+
+// What was found when trying to take the lock.
+pub enum LockOutcome {
+    Acquired(ConfigurationLock),
+    // Another copy of the tool holds the lock, with its process ID when it could be read.
+    Held(Option<u32>),
+}
+
+fn lock_path(configuration_path: &Path) -> PathBuf {
+    let mut path = configuration_path.as_os_str().to_owned();
+    path.push(".lock");
+    PathBuf::from(path)
+}
+
+#[cfg(unix)]
+fn is_process_running(pid: u32) -> bool {
+    // Signal 0 only checks whether the process exists. EPERM means it exists but belongs to
+    // another user.
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+// Without a way to check, assume that whoever took the lock is still running.
+#[cfg(not(unix))]
+fn is_process_running(_pid: u32) -> bool {
+    true
+}
+
+pub fn lock_configuration(configuration_path: &Path) -> std::io::Result<LockOutcome> {
+    let path = lock_path(configuration_path);
+    // A lock from a copy that was killed, or that exited without unwinding, is taken over.
+    for _ in 0..2 {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                write!(file, "{}", std::process::id())?;
+                return Ok(LockOutcome::Acquired(ConfigurationLock { path }));
+            }
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+                let pid = fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|pid| pid.trim().parse::<u32>().ok());
+                match pid {
+                    Some(pid) if !is_process_running(pid) => fs::remove_file(&path)?,
+                    pid => return Ok(LockOutcome::Held(pid)),
+                }
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(LockOutcome::Held(None))
+}
+
+impl Drop for ConfigurationLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,505 @@
+use std::collections::BTreeSet;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PlayerEvent {
+    Joined(String),
+    Left(String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChatMessage {
+    pub sender: String,
+    pub message: String,
+}
+
+// Tick timing as reported by `/tps` and `/mspt` on Paper/Spigot, `/forge tps`, or `/spark tps`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TpsReport {
+    // Ticks per second over the periods that the command reports, most recent first, e.g. the last
+    // 1, 5, and 15 minutes for Paper's /tps. Empty when the line only has tick times.
+    pub tps: Vec<f32>,
+    // Milliseconds per tick for the most recent period, when the line has them.
+    pub mspt: Option<f32>,
+}
+
+// How the server is keeping up, using the thresholds that Paper colors its own reports with.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum TickHealth {
+    Good,
+    Slow,
+    Lagging,
+}
+
+impl TpsReport {
+    pub fn health(&self) -> TickHealth {
+        let tps_health = match self.tps.first() {
+            Some(tps) if *tps <= 16.0 => TickHealth::Lagging,
+            Some(tps) if *tps <= 18.0 => TickHealth::Slow,
+            _ => TickHealth::Good,
+        };
+        // A tick has 50ms before the server falls behind.
+        let mspt_health = match self.mspt {
+            Some(mspt) if mspt >= 50.0 => TickHealth::Lagging,
+            Some(mspt) if mspt >= 40.0 => TickHealth::Slow,
+            _ => TickHealth::Good,
+        };
+        tps_health.max(mspt_health)
+    }
+
+    // e.g. "TPS 20.0 / 19.9 / 19.8, MSPT 12.3".
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.tps.is_empty() {
+            let tps: Vec<String> = self.tps.iter().map(|tps| format!("{:.1}", tps)).collect();
+            parts.push(format!("TPS {}", tps.join(" / ")));
+        }
+        if let Some(mspt) = self.mspt {
+            parts.push(format!("MSPT {:.1}", mspt));
+        }
+        parts.join(", ")
+    }
+}
+
+// Returns the message portion of a server log line, stripping the "[12:34:56] [Server thread/INFO]: "
+// (vanilla) or "[12:34:56 INFO]: " (Paper/Spigot) prefix. Lines without a prefix are returned as-is.
+pub fn log_message(line: &str) -> &str {
+    let line = line.trim_end();
+    match line.find("]: ") {
+        Some(index) if line.starts_with('[') => &line[index + 3..],
+        _ => line,
+    }
+}
+
+pub fn parse_player_event(line: &str) -> Option<PlayerEvent> {
+    let message = log_message(line);
+    // Chat messages look like "<Steve> hello" and must never be mistaken for a join/leave message.
+    if message.starts_with('<') {
+        return None;
+    }
+
+    let is_player_name = |name: &str| !name.is_empty() && !name.contains(' ');
+    if let Some(name) = message.strip_suffix(" joined the game") {
+        if is_player_name(name) {
+            return Some(PlayerEvent::Joined(name.to_string()));
+        }
+    } else if let Some(name) = message.strip_suffix(" left the game") {
+        if is_player_name(name) {
+            return Some(PlayerEvent::Left(name.to_string()));
+        }
+    }
+    None
+}
+
+// Who /say is logged as when it's typed into the console or sent through RCON. Any other name in
+// brackets is a plugin's output, e.g. "[WorldEdit] Enabling WorldEdit v7".
+const CONSOLE_SENDERS: [&str; 2] = ["Server", "Rcon"];
+
+// Finds chat like "<Steve> hello", which 1.19+ servers log as "[Not Secure] <Steve> hello" when the
+// message isn't signed, and messages sent with /say, like "[Server] hello".
+pub fn parse_chat_message(line: &str) -> Option<ChatMessage> {
+    let message = log_message(line);
+    let message = message.strip_prefix("[Not Secure] ").unwrap_or(message);
+    let (sender, message) = if let Some(rest) = message.strip_prefix('<') {
+        rest.split_once("> ")?
+    } else if let Some(rest) = message.strip_prefix('[') {
+        let (sender, message) = rest.split_once("] ")?;
+        if !CONSOLE_SENDERS.contains(&sender) {
+            return None;
+        }
+        (sender, message)
+    } else {
+        return None;
+    };
+    // Player names can't contain spaces, which tells chat apart from other bracketed output.
+    if sender.is_empty() || sender.contains(' ') {
+        return None;
+    }
+    Some(ChatMessage {
+        sender: sender.to_string(),
+        message: message.to_string(),
+    })
+}
+
+// Paper and spark pad values with "*" when they are capped at 20 (e.g. "*20.0").
+fn parse_tps_values(values: &str) -> Option<Vec<f32>> {
+    values
+        .split(',')
+        .map(|value| value.trim().trim_start_matches('*').parse::<f32>().ok())
+        .collect()
+}
+
+// "Overall: Mean tick time: 0.633 ms. Mean TPS: 20.000" from older Forge, or
+// "Overall: 20.000 TPS (0.633 ms/tick)" from newer. The lines for each dimension are left out.
+fn parse_forge_tps(message: &str) -> Option<TpsReport> {
+    let rest = message.trim_end().strip_prefix("Overall: ")?;
+    let (tps, mspt) = match rest.strip_prefix("Mean tick time: ") {
+        Some(rest) => {
+            let (mspt, tps) = rest.split_once(" ms. Mean TPS: ")?;
+            (tps, mspt)
+        }
+        None => rest.strip_suffix(" ms/tick)")?.split_once(" TPS (")?,
+    };
+    Some(TpsReport {
+        tps: vec![tps.parse().ok()?],
+        mspt: Some(mspt.parse().ok()?),
+    })
+}
+
+// Paper's /mspt and spark print a header line and then their values on the next one, so the values
+// are recognized by their shape: "◴ 1.2/0.5/3.4, 1.3/0.5/4.0, 1.1/0.4/5.0" (average/minimum/maximum
+// for the last 5s, 10s, and 1m) from /mspt, "*20.0, *20.0, 19.9, 19.9, 19.8" (TPS for the last 5s,
+// 10s, 1m, 5m, and 15m) and "0.5/1.0/2.0/3.0; 0.5/1.1/2.4/8.0" (minimum/median/95th
+// percentile/maximum for the last 10s and 1m) from spark.
+fn parse_tick_values(message: &str) -> Option<TpsReport> {
+    let message = message.trim();
+    if let Some(values) = message.strip_prefix("◴ ") {
+        let average = values.split(',').next()?.split('/').next()?;
+        return Some(TpsReport {
+            tps: Vec::new(),
+            mspt: Some(average.trim().parse().ok()?),
+        });
+    }
+    // spark starts each line that it prints to the console with "[⚡]", which keeps e.g. a plugin's
+    // list of numbers from passing for its values.
+    let message = message.strip_prefix("[⚡]")?.trim();
+    if message.contains(';') {
+        let durations: Vec<f32> = message
+            .split(';')
+            .next()?
+            .split('/')
+            .map(|value| value.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        return match durations.as_slice() {
+            [_, median, _, _] => Some(TpsReport {
+                tps: Vec::new(),
+                mspt: Some(*median),
+            }),
+            _ => None,
+        };
+    }
+    let tps = parse_tps_values(message)?;
+    Some(TpsReport { tps, mspt: None }).filter(|report| report.tps.len() == 5)
+}
+
+pub fn parse_tps_report(line: &str) -> Option<TpsReport> {
+    let message = log_message(line);
+    if let Some(values) = message.split("TPS from last 1m, 5m, 15m:").nth(1) {
+        let tps = parse_tps_values(values)?;
+        return Some(TpsReport { tps, mspt: None }).filter(|report| report.tps.len() == 3);
+    }
+    parse_forge_tps(message).or_else(|| parse_tick_values(message))
+}
+
+// The jar was built for a newer Java than the one running it, which the JVM reports as e.g.
+// "java.lang.UnsupportedClassVersionError: net/minecraft/bundler/Main has been compiled by a more
+// recent version of the Java Runtime (class file version 65.0), this version of the Java Runtime
+// only recognizes class file versions up to 61.0".
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct JavaVersionMismatch {
+    // Java releases, e.g. 21, converted from class file versions.
+    pub required: u32,
+    // Java 8 and older only say which version the class needs, as "Unsupported major.minor version
+    // 52.0".
+    pub current: Option<u32>,
+}
+
+// Class file version 49 was Java 5, and every release since has added one.
+fn java_release(class_file_version: u32) -> u32 {
+    class_file_version.saturating_sub(44)
+}
+
+fn class_file_version_after(message: &str, marker: &str) -> Option<u32> {
+    let rest = &message[message.find(marker)? + marker.len()..];
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+pub fn parse_unsupported_class_version(line: &str) -> Option<JavaVersionMismatch> {
+    if !line.contains("UnsupportedClassVersionError") {
+        return None;
+    }
+    let required = class_file_version_after(line, "class file version ")
+        .or_else(|| class_file_version_after(line, "Unsupported major.minor version "))?;
+    Some(JavaVersionMismatch {
+        required: java_release(required),
+        current: class_file_version_after(line, "class file versions up to ").map(java_release),
+    })
+}
+
+// What a line of the server's log means to us, if anything.
+#[derive(Debug, PartialEq, Clone)]
+pub enum LogEvent {
+    Player(PlayerEvent),
+    Chat(ChatMessage),
+    Tps(TpsReport),
+    DemoExpired,
+    UnsupportedJava(JavaVersionMismatch),
+    // The server finished starting and is accepting players.
+    Ready,
+}
+
+// The server doesn't log the demo's expiry itself, but the message players receive at that point shows
+// up in the log when it is echoed to the console (e.g. by Paper). This is "demo.reminder" in the
+// server's en_us.json as of 1.20.4, which it sends whenever a player tries to act after the demo ended.
+const DEMO_EXPIRED_MESSAGE: &str =
+    "The demo time has expired. Buy the game to continue or start a new world!";
+
+fn is_demo_expired_message(message: &str) -> bool {
+    message.contains(DEMO_EXPIRED_MESSAGE)
+}
+
+// e.g. "Done (12.345s)! For help, type "help"" on both vanilla and Paper/Spigot.
+fn is_ready_message(message: &str) -> bool {
+    message.starts_with("Done (") && message.contains("For help")
+}
+
+pub fn classify_line(line: &str) -> Option<LogEvent> {
+    if let Some(event) = parse_player_event(line) {
+        Some(LogEvent::Player(event))
+    } else if let Some(chat) = parse_chat_message(line) {
+        Some(LogEvent::Chat(chat))
+    } else if let Some(mismatch) = parse_unsupported_class_version(line) {
+        Some(LogEvent::UnsupportedJava(mismatch))
+    } else if let Some(report) = parse_tps_report(line) {
+        Some(LogEvent::Tps(report))
+    } else if is_demo_expired_message(log_message(line)) {
+        Some(LogEvent::DemoExpired)
+    } else if is_ready_message(log_message(line)) {
+        Some(LogEvent::Ready)
+    } else {
+        None
+    }
+}
+
+// An in-memory view of the running server that is built up purely by reading its log.
+#[derive(Debug, Default, Clone)]
+pub struct LiveStatus {
+    pub players: BTreeSet<String>,
+    pub tps: Option<TpsReport>,
+    pub demo_expired: bool,
+}
+
+impl LiveStatus {
+    // Updates the status from a classified log line, returning whether anything changed.
+    pub fn apply(&mut self, event: &LogEvent) -> bool {
+        match event {
+            LogEvent::Player(PlayerEvent::Joined(name)) => self.players.insert(name.clone()),
+            LogEvent::Player(PlayerEvent::Left(name)) => self.players.remove(name),
+            // /mspt and spark report tick times on a line of their own, so they're kept next to the
+            // last TPS rather than replacing it.
+            LogEvent::Tps(report) => {
+                let tps = self.tps.get_or_insert_with(TpsReport::default);
+                if !report.tps.is_empty() {
+                    tps.tps = report.tps.clone();
+                }
+                if report.mspt.is_some() {
+                    tps.mspt = report.mspt;
+                }
+                true
+            }
+            LogEvent::DemoExpired => !std::mem::replace(&mut self.demo_expired, true),
+            LogEvent::Chat(_) | LogEvent::UnsupportedJava(_) | LogEvent::Ready => false,
+        }
+    }
+
+    pub fn status_line(&self) -> String {
+        let mut line = format!("Server status: {} online", self.players.len());
+        if !self.players.is_empty() {
+            let names: Vec<&str> = self.players.iter().map(String::as_str).collect();
+            line = format!("{} ({})", line, names.join(", "));
+        }
+        if self.demo_expired {
+            line = format!("{}, demo expired", line);
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chat(sender: &str, message: &str) -> Option<ChatMessage> {
+        Some(ChatMessage {
+            sender: sender.to_string(),
+            message: message.to_string(),
+        })
+    }
+
+    #[test]
+    fn player_chat_is_chat() {
+        assert_eq!(
+            parse_chat_message("[12:00:00] [Server thread/INFO]: <Steve> hello there"),
+            chat("Steve", "hello there")
+        );
+        assert_eq!(
+            parse_chat_message("[12:00:00] [Server thread/INFO]: [Not Secure] <Alex> hi"),
+            chat("Alex", "hi")
+        );
+    }
+
+    #[test]
+    fn say_from_the_console_or_rcon_is_chat() {
+        assert_eq!(
+            parse_chat_message("[12:00:00] [Server thread/INFO]: [Server] restarting soon"),
+            chat("Server", "restarting soon")
+        );
+        assert_eq!(
+            parse_chat_message("[12:00:00 INFO]: [Rcon] backup done"),
+            chat("Rcon", "backup done")
+        );
+    }
+
+    fn tps(tps: &[f32], mspt: Option<f32>) -> Option<TpsReport> {
+        Some(TpsReport {
+            tps: tps.to_vec(),
+            mspt,
+        })
+    }
+
+    #[test]
+    fn paper_tps_and_mspt_are_recognized() {
+        assert_eq!(
+            parse_tps_report("[12:00:00 INFO]: TPS from last 1m, 5m, 15m: *20.0, 19.9, 19.8"),
+            tps(&[20.0, 19.9, 19.8], None)
+        );
+        assert_eq!(
+            parse_tps_report("[12:00:00 INFO]: ◴ 45.2/0.5/60.4, 1.3/0.5/4.0, 1.1/0.4/5.0"),
+            tps(&[], Some(45.2))
+        );
+    }
+
+    #[test]
+    fn forge_tps_is_recognized() {
+        assert_eq!(
+            parse_tps_report(
+                "[12:00:00] [Server thread/INFO]: Overall: Mean tick time: 0.633 ms. Mean TPS: 20.000"
+            ),
+            tps(&[20.0], Some(0.633))
+        );
+        assert_eq!(
+            parse_tps_report(
+                "[12:00:00] [Server thread/INFO]: Overall: 15.500 TPS (64.516 ms/tick)"
+            ),
+            tps(&[15.5], Some(64.516))
+        );
+        assert_eq!(
+            parse_tps_report(
+                "[12:00:00] [Server thread/INFO]: Dim minecraft:overworld (minecraft:overworld): Mean tick time: 0.500 ms. Mean TPS: 20.000"
+            ),
+            None
+        );
+        assert_eq!(
+            parse_tps_report("[12:00:00] [Server thread/INFO]: Overall: 3 players, 20 chunks"),
+            None
+        );
+        assert_eq!(
+            parse_tps_report("[12:00:00] [Server thread/INFO]: Overall: 12.5 (of 20)"),
+            None
+        );
+    }
+
+    #[test]
+    fn spark_tps_is_recognized() {
+        assert_eq!(
+            classify_line("[12:00:00 INFO]: [⚡]  *20.0, *20.0, 19.9, 19.9, 19.8"),
+            Some(LogEvent::Tps(TpsReport {
+                tps: vec![20.0, 20.0, 19.9, 19.9, 19.8],
+                mspt: None,
+            }))
+        );
+        assert_eq!(
+            classify_line("[12:00:00 INFO]: [⚡]  0.5/1.0/2.0/3.0;  0.5/1.1/2.4/8.0"),
+            Some(LogEvent::Tps(TpsReport {
+                tps: Vec::new(),
+                mspt: Some(1.0),
+            }))
+        );
+        assert_eq!(
+            parse_tps_report("[12:00:00 INFO]: [⚡] TPS from last 5s, 10s, 1m, 5m, 15m:"),
+            None
+        );
+        // The same shapes without spark's prefix, e.g. from another plugin.
+        assert_eq!(
+            parse_tps_report("[12:00:00 INFO]: 1.0, 2.0, 3.0, 4.0, 5.0"),
+            None
+        );
+        assert_eq!(parse_tps_report("[12:00:00 INFO]: 1/2/3/4; 5/6/7/8"), None);
+    }
+
+    #[test]
+    fn tick_health_uses_the_worse_of_tps_and_mspt() {
+        assert_eq!(tps(&[20.0], Some(10.0)).unwrap().health(), TickHealth::Good);
+        assert_eq!(tps(&[17.0], Some(10.0)).unwrap().health(), TickHealth::Slow);
+        assert_eq!(
+            tps(&[20.0], Some(55.0)).unwrap().health(),
+            TickHealth::Lagging
+        );
+        assert_eq!(tps(&[], None).unwrap().health(), TickHealth::Good);
+    }
+
+    #[test]
+    fn plugin_output_isnt_chat() {
+        for line in &[
+            "[12:00:00 INFO]: [WorldEdit] Enabling WorldEdit v7.2.15",
+            "[12:00:00 INFO]: [LuckPerms] Loading configuration...",
+            "[12:00:00 INFO]: [⚡] TPS from last 5s, 10s, 1m, 5m, 15m:",
+            "[12:00:00 INFO]: [Not Secure] [Essentials] Loaded 1000 items",
+        ] {
+            assert_eq!(parse_chat_message(line), None, "{}", line);
+            assert!(
+                !matches!(classify_line(line), Some(LogEvent::Chat(_))),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported_class_versions_name_the_java_releases() {
+        let line = "Error: LinkageError occurred while loading main class net.minecraft.bundler.Main\n\
+            java.lang.UnsupportedClassVersionError: net/minecraft/bundler/Main has been compiled by a more recent version of the Java Runtime (class file version 65.0), this version of the Java Runtime only recognizes class file versions up to 61.0";
+        assert_eq!(
+            parse_unsupported_class_version(line),
+            Some(JavaVersionMismatch {
+                required: 21,
+                current: Some(17),
+            })
+        );
+        // Java 8 and older.
+        assert_eq!(
+            parse_unsupported_class_version(
+                "Exception in thread \"main\" java.lang.UnsupportedClassVersionError: net/minecraft/server/Main : Unsupported major.minor version 52.0"
+            ),
+            Some(JavaVersionMismatch {
+                required: 8,
+                current: None,
+            })
+        );
+        assert_eq!(
+            parse_unsupported_class_version(
+                "java.lang.UnsupportedClassVersionError: Preview features are not enabled"
+            ),
+            None
+        );
+        assert_eq!(
+            parse_unsupported_class_version("[12:00:00 INFO]: <Steve> class file version 65.0"),
+            None
+        );
+    }
+
+    #[test]
+    fn only_the_demo_reminder_means_the_demo_expired() {
+        assert_eq!(
+            classify_line("[12:00:00 INFO]: The demo time has expired. Buy the game to continue or start a new world!"),
+            Some(LogEvent::DemoExpired)
+        );
+        for line in [
+            "[12:00:00 INFO]: demo.reminder",
+            "[12:00:00 INFO]: The demo time has expired",
+            "[12:00:00 INFO]: Demo time's up!",
+        ] {
+            assert_eq!(classify_line(line), None, "{}", line);
+        }
+    }
+}
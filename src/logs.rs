@@ -0,0 +1,131 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{clock::DateTime, machine::DEFAULT_INSTANCE};
+
+pub const LOGS_DIRECTORY: &str = "msc-logs";
+
+pub const LOG_FILENAME: &str = "latest.log";
+
+// Where the server writes its crash reports, relative to its directory.
+const CRASH_REPORTS_DIRECTORY: &str = "crash-reports";
+
+const TIMESTAMP_SLACK: Duration = Duration::from_secs(1);
+
+// Instance names are free text, but they end up as a folder name here. Letters, digits, and "-" are
+// kept, and every other byte becomes "_" and its hex code, so that no two names share a folder.
+fn folder_name(name: &str) -> String {
+    let mut folder = String::new();
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' {
+            folder.push(byte as char);
+        } else {
+            folder.push_str(&format!("_{:02x}", byte));
+        }
+    }
+    folder
+}
+
+// "msc-logs" for the default instance of msc-configuration.ron, and e.g.
+// "msc-logs/instances/creative" or "msc-logs/profiles/modded/instances/survival" for the others, so
+// that profiles and instances sharing a directory never write over each other's logs.
+pub fn instance_log_directory(profile: Option<&str>, instance: &str) -> PathBuf {
+    let mut directory = PathBuf::from(LOGS_DIRECTORY);
+    if let Some(profile) = profile {
+        directory.push("profiles");
+        directory.push(folder_name(profile));
+    }
+    if instance != DEFAULT_INSTANCE {
+        directory.push("instances");
+        directory.push(folder_name(instance));
+    }
+    directory
+}
+
+// Opens the log for appending, marking where this launch begins since every launch shares the file.
+pub fn open_log_file(directory: &Path) -> io::Result<File> {
+    fs::create_dir_all(directory)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(directory.join(LOG_FILENAME))?;
+    let now = DateTime::now();
+    writeln!(file, "--- Started on {} at {} ---", now.date(), now.time())?;
+    Ok(file)
+}
+
+// The newest crash report that the server wrote since `since`. Reports from before then belong to
+// another launch, possibly of another instance that shares the directory.
+pub fn find_crash_report(since: SystemTime) -> Option<PathBuf> {
+    // File times come from a coarser clock, so they can be slightly behind a launch they followed.
+    let since = since.checked_sub(TIMESTAMP_SLACK).unwrap_or(since);
+    fs::read_dir(CRASH_REPORTS_DIRECTORY)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .filter(|(modified, path)| *modified >= since && path.is_file())
+        .max()
+        .map(|(_, path)| path)
+}
+
+// The one-line summary at the top of a crash report, e.g. "Exception in server tick loop".
+pub fn crash_report_description(report: &Path) -> Option<String> {
+    fs::read_to_string(report)
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("Description: "))
+        .map(|description| description.trim().to_string())
+}
+
+// Copies the crash report next to the instance's log, where it can't be mistaken for another
+// instance's, and returns where it went.
+pub fn keep_crash_report(report: &Path, log_directory: &Path) -> io::Result<PathBuf> {
+    let directory = log_directory.join(CRASH_REPORTS_DIRECTORY);
+    fs::create_dir_all(&directory)?;
+    let copy = directory.join(report.file_name().unwrap_or_default());
+    fs::copy(report, &copy)?;
+    Ok(copy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_and_instances_never_share_a_folder() {
+        let directories = [
+            instance_log_directory(None, DEFAULT_INSTANCE),
+            instance_log_directory(Some("creative"), DEFAULT_INSTANCE),
+            instance_log_directory(None, "creative"),
+            instance_log_directory(Some("creative"), "creative"),
+            instance_log_directory(None, "a.b"),
+            instance_log_directory(None, "a_b"),
+            instance_log_directory(None, "a_2eb"),
+            instance_log_directory(None, "profiles"),
+            instance_log_directory(None, "crash-reports"),
+        ];
+        for (index, directory) in directories.iter().enumerate() {
+            for other in &directories[index + 1..] {
+                assert_ne!(directory, other);
+            }
+        }
+        assert_eq!(directories[0], PathBuf::from("msc-logs"));
+        assert_eq!(
+            directories[3],
+            Path::new("msc-logs/profiles/creative/instances/creative")
+        );
+    }
+
+    #[test]
+    fn folder_names_keep_plain_names_readable() {
+        assert_eq!(folder_name("survival-2"), "survival-2");
+        assert_eq!(folder_name("a.b"), "a_2eb");
+        assert_eq!(folder_name("a_b"), "a_5fb");
+        assert_eq!(folder_name("../x"), "_2e_2e_2fx");
+        assert_eq!(folder_name("é"), "_c3_a9");
+    }
+}
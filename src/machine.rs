@@ -1,10 +1,25 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
 use enumflags2::{bitflags, BitFlags};
 use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+
+use crate::{
+    memory::MemorySize, schedule::parse_time_of_day, server_type::ServerType,
+    share::redact_secrets, theme::PromptTheme,
+};
 
 // Configuration data structure. This is what we edit and persist to the disk.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+// Missing fields fall back to their defaults so that older configuration files keep working.
+#[derive(Serialize, Deserialize, Debug, Clone, SmartDefault)]
+#[serde(default)]
 #[allow(non_snake_case)]
 pub struct MinecraftServerConfiguration {
+    pub serverType: ServerType,
+    // The Java that runs the server, e.g. "C:\Program Files\Java\jdk-17\bin\java.exe", instead of
+    // the one in the PATH.
+    pub javaPath: Option<String>,
+    #[default = true]
     pub bonusChest: bool,
     pub demo: bool,
     pub eraseCache: bool,
@@ -14,16 +29,93 @@ pub struct MinecraftServerConfiguration {
     pub port: Option<u16>, // u16 is the equivalent of 2^16-1 (0–65535). All ports are 1–65535
     pub safeMode: bool,
     pub singleplayer: bool,
+    pub jfrProfile: bool,
     pub universe: Option<String>,
     pub world: Option<String>,
+    pub maxMemory: Option<MemorySize>,
+    pub minMemory: Option<MemorySize>,
+    // Copies the world into msc-backups before every start.
+    pub backupBeforeStart: bool,
+    // How many backups of each world to keep, or all of them when unset.
+    pub backupRetention: Option<u16>,
+    // Run by the shell before the server starts, which is called off when the command fails.
+    pub preStartHook: Option<String>,
+    // Run by the shell once the server has finished starting, e.g. to send a notification.
+    pub postStartHook: Option<String>,
+    // A file holding the RCON password, so that the password itself stays out of the configuration.
+    pub rconPasswordFile: Option<String>,
+    // A 24-hour time like "04:30" at which the server is stopped and started again every day.
+    pub dailyRestartTime: Option<String>,
+    // How many minutes before the daily restart the players are warned.
+    pub dailyRestartWarningMinutes: Option<u16>,
+    // Stops the server once nobody has been online for this many minutes.
+    pub idleShutdownMinutes: Option<u16>,
+    // Caps the whole server process, heap and all, through a systemd scope on Linux.
+    pub memoryLimit: Option<MemorySize>,
+    // The share of one CPU core the server may use, in percent, e.g. 200 for two cores.
+    pub cpuQuota: Option<u16>,
+    // Lowers the server's CPU and IO priority, from 0 (normal) to 19 (lowest).
+    pub niceness: Option<u16>,
+    // Turns off the server's JLine console when its output is captured, which would otherwise fill
+    // the log with terminal control codes.
+    #[default = true]
+    pub plainLogOutput: bool,
+    // Looks for truncated region files in the world before every start.
+    #[default = true]
+    pub checkRegionFiles: bool,
+    // Records every change made to an option in msc-audit.log.
+    pub auditLog: bool,
+    // Java system properties, passed to the JVM as `-Dkey=value`.
+    pub systemProperties: Vec<(String, String)>,
+    // Broadcast to the players before a planned stop or restart, as (seconds before it, message).
+    // Only edited by hand in the configuration file.
+    pub shutdownWarnings: Vec<(u32, String)>,
+    // Named lists of console commands that are run one after another through RCON from the menu.
+    // Only edited by hand in the configuration file.
+    pub macros: Vec<(String, Vec<String>)>,
+    // Console commands typed into the server once it has finished starting, in order.
+    // Only edited by hand in the configuration file.
+    pub startupCommands: Vec<String>,
+    // Not a server option, so it is only edited by hand in the configuration file.
+    pub theme: Option<PromptTheme>,
+}
+
+// The name of the instance used when the configuration file only holds a single configuration.
+pub const DEFAULT_INSTANCE: &str = "default";
+
+// The configuration file when it holds several server instances (e.g. worlds on different ports).
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ConfigurationFile {
+    pub instances: BTreeMap<String, MinecraftServerConfiguration>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum ConfigurationOptionType {
     Bool(bool),
     OptionU16(Option<u16>),
     OptionString(Option<String>),
+    OptionMemory(Option<MemorySize>),
+    KeyValues(Vec<(String, String)>),
+    ServerType(ServerType),
+    // One of the names that the option's OptionValidator::OneOf allows.
+    Choice(String),
+}
+impl ConfigurationOptionType {
+    // The value as it's written in the configuration file.
+    pub fn to_ron(&self) -> String {
+        match self {
+            ConfigurationOptionType::Bool(value) => ron::to_string(value),
+            ConfigurationOptionType::OptionU16(value) => ron::to_string(value),
+            ConfigurationOptionType::OptionString(value) => ron::to_string(value),
+            ConfigurationOptionType::OptionMemory(value) => ron::to_string(value),
+            ConfigurationOptionType::KeyValues(value) => ron::to_string(value),
+            ConfigurationOptionType::ServerType(value) => ron::to_string(value),
+            ConfigurationOptionType::Choice(value) => ron::to_string(value),
+        }
+        .unwrap()
+    }
 }
+
 impl MinecraftServerConfiguration {
     fn set(&mut self, property: String, value: ConfigurationOptionType) {
         let property = property.as_str();
@@ -37,25 +129,62 @@ impl MinecraftServerConfiguration {
                 "gui" => self.gui = value,
                 "safeMode" => self.safeMode = value,
                 "singleplayer" => self.singleplayer = value,
+                "jfrProfile" => self.jfrProfile = value,
+                "backupBeforeStart" => self.backupBeforeStart = value,
+                "plainLogOutput" => self.plainLogOutput = value,
+                "checkRegionFiles" => self.checkRegionFiles = value,
+                "auditLog" => self.auditLog = value,
                 _ => {}
             }
         } else if let ConfigurationOptionType::OptionU16(value) = value {
             match property {
                 "port" => self.port = value,
+                "backupRetention" => self.backupRetention = value,
+                "dailyRestartWarningMinutes" => self.dailyRestartWarningMinutes = value,
+                "idleShutdownMinutes" => self.idleShutdownMinutes = value,
+                "cpuQuota" => self.cpuQuota = value,
+                "niceness" => self.niceness = value,
                 _ => {}
             }
         } else if let ConfigurationOptionType::OptionString(value) = value {
             match property {
+                "javaPath" => self.javaPath = value,
                 "universe" => self.universe = value,
                 "world" => self.world = value,
+                "preStartHook" => self.preStartHook = value,
+                "postStartHook" => self.postStartHook = value,
+                "rconPasswordFile" => self.rconPasswordFile = value,
+                "dailyRestartTime" => self.dailyRestartTime = value,
+                _ => {}
+            }
+        } else if let ConfigurationOptionType::OptionMemory(value) = value {
+            match property {
+                "maxMemory" => self.maxMemory = value,
+                "minMemory" => self.minMemory = value,
+                "memoryLimit" => self.memoryLimit = value,
                 _ => {}
             }
+        } else if let ConfigurationOptionType::KeyValues(value) = value {
+            if property == "systemProperties" {
+                self.systemProperties = value
+            }
+        } else if let ConfigurationOptionType::ServerType(value) = value {
+            if property == "serverType" {
+                self.serverType = value
+            }
+        } else if let ConfigurationOptionType::Choice(value) = value {
+            if property == "serverType" {
+                if let Some(value) = ServerType::from_name(&value) {
+                    self.serverType = value
+                }
+            }
         }
     }
 
     pub fn get(&self, property: String) -> ConfigurationOptionType {
         let property = property.as_str();
         match property {
+            "serverType" => ConfigurationOptionType::ServerType(self.serverType),
             "bonusChest" => ConfigurationOptionType::Bool(self.bonusChest),
             "demo" => ConfigurationOptionType::Bool(self.demo),
             "eraseCache" => ConfigurationOptionType::Bool(self.eraseCache),
@@ -65,8 +194,33 @@ impl MinecraftServerConfiguration {
             "port" => ConfigurationOptionType::OptionU16(self.port),
             "safeMode" => ConfigurationOptionType::Bool(self.safeMode),
             "singleplayer" => ConfigurationOptionType::Bool(self.singleplayer),
+            "jfrProfile" => ConfigurationOptionType::Bool(self.jfrProfile),
+            "javaPath" => ConfigurationOptionType::OptionString(self.javaPath.clone()),
             "universe" => ConfigurationOptionType::OptionString(self.universe.clone()),
             "world" => ConfigurationOptionType::OptionString(self.world.clone()),
+            "preStartHook" => ConfigurationOptionType::OptionString(self.preStartHook.clone()),
+            "postStartHook" => ConfigurationOptionType::OptionString(self.postStartHook.clone()),
+            "rconPasswordFile" => {
+                ConfigurationOptionType::OptionString(self.rconPasswordFile.clone())
+            }
+            "dailyRestartTime" => {
+                ConfigurationOptionType::OptionString(self.dailyRestartTime.clone())
+            }
+            "dailyRestartWarningMinutes" => {
+                ConfigurationOptionType::OptionU16(self.dailyRestartWarningMinutes)
+            }
+            "idleShutdownMinutes" => ConfigurationOptionType::OptionU16(self.idleShutdownMinutes),
+            "memoryLimit" => ConfigurationOptionType::OptionMemory(self.memoryLimit),
+            "cpuQuota" => ConfigurationOptionType::OptionU16(self.cpuQuota),
+            "niceness" => ConfigurationOptionType::OptionU16(self.niceness),
+            "plainLogOutput" => ConfigurationOptionType::Bool(self.plainLogOutput),
+            "checkRegionFiles" => ConfigurationOptionType::Bool(self.checkRegionFiles),
+            "auditLog" => ConfigurationOptionType::Bool(self.auditLog),
+            "maxMemory" => ConfigurationOptionType::OptionMemory(self.maxMemory),
+            "minMemory" => ConfigurationOptionType::OptionMemory(self.minMemory),
+            "backupBeforeStart" => ConfigurationOptionType::Bool(self.backupBeforeStart),
+            "backupRetention" => ConfigurationOptionType::OptionU16(self.backupRetention),
+            "systemProperties" => ConfigurationOptionType::KeyValues(self.systemProperties.clone()),
             _ => ConfigurationOptionType::OptionString(None),
         }
     }
@@ -91,13 +245,18 @@ pub enum EditorState {
     SelectOnOff,
     NumberInput,
     TextInput,
+    MemoryInput,
     SelectValueOrNone,
+    KeyValueList,
+    SelectChoice,
 }
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum EditorEvent {
     SubmitValue,
     SelectedValue,
     SelectedNone,
+    ResetToDefault,
+    Cancel,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -114,6 +273,64 @@ pub enum ConfigurationOptionTypeFlag {
     U16,
     String,
     Option,
+    Memory,
+    KeyValues,
+    Choice,
+    // The value is kept in server.properties under the option's property, where the editor writes
+    // it, rather than in the configuration.
+    ServerProperty,
+}
+
+// The rules a value typed into the editor has to follow before it's accepted. Failing one re-prompts
+// with the message from `check`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum OptionValidator {
+    // A whole number in this inclusive range.
+    Range(u16, u16),
+    // A name that can be used as a single folder on every platform, e.g. for the world.
+    FolderName,
+    // A 24-hour time of day like "04:30".
+    TimeOfDay,
+    // Exactly one of these names, which the Choice editor lists.
+    OneOf(Vec<String>),
+}
+
+impl OptionValidator {
+    pub fn check(&self, input: &str) -> Result<(), String> {
+        match self {
+            OptionValidator::Range(min, max) => match input.trim().parse::<u16>() {
+                Ok(value) if (*min..=*max).contains(&value) => Ok(()),
+                _ => Err(format!("Enter a whole number from {} to {}.", min, max)),
+            },
+            OptionValidator::FolderName => {
+                let name = input.trim();
+                if name.is_empty() || name == "." || name == ".." {
+                    Err("Enter a folder name.".to_string())
+                } else if let Some(character) = name
+                    .chars()
+                    .find(|character| "/\\:*?\"<>|".contains(*character))
+                {
+                    Err(format!("Folder names can't contain \"{}\".", character))
+                } else if name.ends_with('.') || name.ends_with(' ') {
+                    // Windows drops these, so the server would end up using a different folder.
+                    Err("Folder names can't end with a period or a space.".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+            OptionValidator::TimeOfDay => match parse_time_of_day(input) {
+                Some(_) => Ok(()),
+                None => Err("Enter a 24-hour time like 04:30.".to_string()),
+            },
+            OptionValidator::OneOf(choices) => {
+                if choices.iter().any(|choice| choice == input.trim()) {
+                    Ok(())
+                } else {
+                    Err(format!("Choose one of: {}.", choices.join(", ")))
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -122,6 +339,7 @@ pub struct ConfigurationOption {
     pub name: String,
     pub description: String,
     pub r#type: BitFlags<ConfigurationOptionTypeFlag>,
+    pub validator: Option<OptionValidator>,
 }
 
 #[derive(Debug, Clone)]
@@ -135,35 +353,145 @@ pub struct Machine {
     pub editor_state: Option<EditorState>,
     pub selected_configuration_option: Option<ConfigurationOption>,
     pub configuration: MinecraftServerConfiguration,
+    // The name of the server instance that `configuration` belongs to.
+    pub instance: String,
+    // The configuration file that the instance is read from and saved to.
+    pub configuration_path: PathBuf,
+    // Whether the configuration has changed since it was last saved.
+    pub dirty: bool,
+    // Earlier versions of the configuration, most recent last, for undoing changes.
+    pub history: Vec<MinecraftServerConfiguration>,
+    // Writes every transition to stderr, for --debug-state.
+    pub trace_transitions: bool,
+    // Changes to options that are waiting to be written to the audit log, oldest first.
+    pub audit_entries: Vec<AuditEntry>,
+}
+
+// One change to an option for the audit log, with its values as RON and secrets redacted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub property: String,
+    pub old: String,
+    pub new: String,
+}
+
+// The state and editor as they appear in the transition trace, e.g. "EditingConfiguration
+// (NumberInput)".
+fn describe_state(state: AppState, editor_state: Option<EditorState>) -> String {
+    match editor_state {
+        Some(editor_state) => format!("{:?} ({:?})", state, editor_state),
+        None => format!("{:?}", state),
+    }
 }
 
+fn describe_payload(payload: &Payload) -> String {
+    match payload {
+        Payload::ConfigurationOption(option) => option.property.clone(),
+        Payload::ConfigurationOptionType(value) => value.to_ron(),
+    }
+}
+
+// How many changes can be undone.
+const HISTORY_LIMIT: usize = 20;
+
 impl Machine {
     fn set_option_value(&mut self, payload: ConfigurationOptionType) {
-        let property = self
-            .selected_configuration_option
-            .clone()
-            .expect("A configuration option was never selected before attempting to set its value.")
-            .property;
-        self.configuration.set(property, payload);
-        self.selected_configuration_option = None;
+        let option = self.selected_configuration_option.take().expect(
+            "A configuration option was never selected before attempting to set its value.",
+        );
+        if option
+            .r#type
+            .contains(ConfigurationOptionTypeFlag::ServerProperty)
+        {
+            return;
+        }
+        let property = option.property;
+        let mut configuration = self.configuration.clone();
+        configuration.set(property.clone(), payload);
+        // A choice is stored as its own type, e.g. a ServerType. Turning the audit log on or off is
+        // recorded too.
+        let changed = self.configuration.get(property.clone()).to_ron()
+            != configuration.get(property.clone()).to_ron();
+        if (self.configuration.auditLog || configuration.auditLog) && changed {
+            self.audit_entries.push(AuditEntry {
+                old: redact_secrets(&self.configuration)
+                    .get(property.clone())
+                    .to_ron(),
+                new: redact_secrets(&configuration)
+                    .get(property.clone())
+                    .to_ron(),
+                property,
+            });
+        }
+        self.replace_configuration(configuration);
     }
 
-    pub fn dispatch(&mut self, event: Event, payload: Option<Payload>) -> () {
-        let state = self.state.clone();
+    // Changes the whole configuration at once while keeping the previous one to undo to.
+    pub fn replace_configuration(&mut self, configuration: MinecraftServerConfiguration) {
+        if self.history.len() == HISTORY_LIMIT {
+            self.history.remove(0);
+        }
+        self.history
+            .push(std::mem::replace(&mut self.configuration, configuration));
+        self.dirty = true;
+    }
+
+    // Restores the configuration from before the last change, returning whether there was one.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(configuration) => {
+                self.configuration = configuration;
+                self.dirty = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn dispatch(&mut self, event: Event, payload: Option<Payload>) {
+        if !self.trace_transitions {
+            return self.transition(event, payload);
+        }
+        let before = describe_state(self.state, self.editor_state);
+        let described_payload = payload.as_ref().map(describe_payload);
+        self.transition(event, payload);
+        let after = describe_state(self.state, self.editor_state);
+        let event = match described_payload {
+            Some(payload) => format!("{:?} with {}", event, payload),
+            None => format!("{:?}", event),
+        };
+        if before == after {
+            eprintln!("[state] {} --{}--> unchanged", before, event);
+        } else {
+            eprintln!("[state] {} --{}--> {}", before, event, after);
+        }
+    }
+
+    fn transition(&mut self, event: Event, payload: Option<Payload>) {
+        let state = self.state;
         match event {
             Event::AppEvent(event) => {
                 self.state = match (state, event) {
-                    (AppState::ChoiceMenu, AppEvent::StartServer) => AppState::Running,
+                    (AppState::ChoiceMenu, AppEvent::StartServer) => {
+                        self.history.clear();
+                        AppState::Running
+                    }
                     (AppState::ChoiceMenu, AppEvent::Exit) => AppState::Exited,
                     (AppState::ChoiceMenu, AppEvent::SelectedOption) => {
                         if let Payload::ConfigurationOption(payload) = payload.expect("A ConfigurationOption payload was not provided when the SelectedOption event was dispatched from the ChoiceMenu state.") {
 						self.selected_configuration_option = Some(payload.clone());
-						self.editor_state = Some(if payload.r#type.contains(ConfigurationOptionTypeFlag::Option) {
+						self.editor_state = Some(if payload.r#type.contains(ConfigurationOptionTypeFlag::KeyValues) {
+							EditorState::KeyValueList
+						} else if payload.r#type.contains(ConfigurationOptionTypeFlag::Choice) {
+							EditorState::SelectChoice
+						} else if payload.r#type.contains(ConfigurationOptionTypeFlag::Option) {
 							EditorState::SelectValueOrNone
 						} else if payload.r#type.contains(ConfigurationOptionTypeFlag::Bool) {
 							EditorState::SelectOnOff
 						} else if payload.r#type.contains(ConfigurationOptionTypeFlag::U16) {
 							EditorState::NumberInput
+						} else if payload.r#type.contains(ConfigurationOptionTypeFlag::Memory) {
+							EditorState::MemoryInput
 						} else {
 							EditorState::TextInput
 						});
@@ -175,7 +503,7 @@ impl Machine {
                 }
             }
             Event::EditorEvent(event) => {
-                let option_editor_state = self.editor_state.clone();
+                let option_editor_state = self.editor_state;
                 let editor_state = option_editor_state
                     .expect("An EditorEvent has been dispatched while not in the EditorState");
                 let none: Option<EditorState> = None;
@@ -213,6 +541,44 @@ impl Machine {
                             option_editor_state
                         }
                     }
+                    (EditorState::MemoryInput, EditorEvent::SubmitValue) => {
+                        if let Payload::ConfigurationOptionType(value) =
+                            payload.expect("Expected an amount of memory (MemorySize) or None.")
+                        {
+                            self.set_option_value(value);
+                            self.state = AppState::ChoiceMenu;
+                            none
+                        } else {
+                            option_editor_state
+                        }
+                    }
+                    (EditorState::SelectChoice, EditorEvent::SubmitValue) => {
+                        if let Payload::ConfigurationOptionType(value) =
+                            payload.expect("Expected one of the choices.")
+                        {
+                            self.set_option_value(value);
+                            self.state = AppState::ChoiceMenu;
+                            none
+                        } else {
+                            option_editor_state
+                        }
+                    }
+                    (EditorState::KeyValueList, EditorEvent::SubmitValue) => {
+                        if let Payload::ConfigurationOptionType(value) =
+                            payload.expect("Expected a list of keys and values.")
+                        {
+                            self.set_option_value(value);
+                            self.state = AppState::ChoiceMenu;
+                            none
+                        } else {
+                            option_editor_state
+                        }
+                    }
+                    (_, EditorEvent::Cancel) => {
+                        self.selected_configuration_option = None;
+                        self.state = AppState::ChoiceMenu;
+                        None
+                    }
                     (EditorState::SelectValueOrNone, EditorEvent::SelectedValue) => {
                         let selected = self.selected_configuration_option.clone().expect("");
                         if selected.r#type.contains(ConfigurationOptionTypeFlag::U16) {
@@ -222,6 +588,11 @@ impl Machine {
                             .contains(ConfigurationOptionTypeFlag::String)
                         {
                             Some(EditorState::TextInput)
+                        } else if selected
+                            .r#type
+                            .contains(ConfigurationOptionTypeFlag::Memory)
+                        {
+                            Some(EditorState::MemoryInput)
                         } else {
                             option_editor_state
                         }
@@ -237,10 +608,30 @@ impl Machine {
                         {
                             let none = ConfigurationOptionType::OptionString(None);
                             self.set_option_value(none);
+                        } else if selected
+                            .r#type
+                            .contains(ConfigurationOptionTypeFlag::Memory)
+                        {
+                            let none = ConfigurationOptionType::OptionMemory(None);
+                            self.set_option_value(none);
                         }
                         self.state = AppState::ChoiceMenu;
                         None
                     }
+                    (EditorState::SelectOnOff, EditorEvent::ResetToDefault)
+                    | (EditorState::SelectChoice, EditorEvent::ResetToDefault)
+                    | (EditorState::KeyValueList, EditorEvent::ResetToDefault)
+                    | (EditorState::SelectValueOrNone, EditorEvent::ResetToDefault) => {
+                        let property = self
+                            .selected_configuration_option
+                            .clone()
+                            .expect("A configuration option was never selected before attempting to reset it.")
+                            .property;
+                        let default = MinecraftServerConfiguration::default().get(property);
+                        self.set_option_value(default);
+                        self.state = AppState::ChoiceMenu;
+                        None
+                    }
                     _ => option_editor_state,
                 }
             }
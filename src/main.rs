@@ -1,299 +1,5334 @@
-use std::{env::set_current_dir, fs, process::Stdio};
+use std::{
+    collections::BTreeMap,
+    env::{self, set_current_dir},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime},
+};
 
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use console::{style, Term};
-use dialoguer;
-use execute::command;
-use ron;
 
+mod address;
+mod argfile;
+mod audit;
+mod backup;
+mod clock;
+mod error;
+mod eula;
+mod hook;
+mod icon;
+mod jar;
+mod java;
+mod level;
+mod lock;
+mod log;
+mod logs;
 mod machine;
+mod memory;
+mod menu;
+mod output;
+mod ping;
+mod properties;
+mod rcon;
+mod repair;
+mod report;
+mod schedule;
+mod secret;
+mod server_type;
+mod session;
+mod share;
+mod state;
+mod systemd;
+mod theme;
+mod upnp;
+mod validate;
+mod whitelist;
+mod world;
+mod zip;
+use address::*;
+use argfile::*;
+use audit::*;
+use backup::*;
 use enumflags2::make_bitflags;
+use error::*;
+use eula::*;
+use hook::*;
+use jar::*;
+use java::*;
+use level::*;
+use lock::*;
+use log::*;
+use logs::*;
 use machine::*;
+use memory::*;
+use menu::*;
+use output::*;
+use ping::*;
+use properties::*;
+use rcon::*;
+use repair::*;
+use report::*;
+use schedule::*;
+use secret::*;
+use server_type::*;
+use session::*;
+use share::*;
+use state::*;
+use systemd::*;
+use theme::*;
+use upnp::*;
+use validate::*;
+use whitelist::*;
+use world::*;
+
+const CONFIGURATION_FILENAME: &str = "msc-configuration.ron";
 
 fn get_default_configuration_path() -> String {
     if cfg!(windows) {
         let appdata = std::env::var("APPDATA").expect("No path to the Minecraft server folder was specified and the APPDATA environment variable does not exist.");
-        return format!("{}\\.minecraft\\server\\", appdata);
+        format!("{}\\.minecraft\\server\\", appdata)
     } else {
-        return "~/.minecraft/server/".to_string();
+        "~/.minecraft/server/".to_string()
     }
 }
 
-fn save_configuration(configuration: MinecraftServerConfiguration) -> MinecraftServerConfiguration {
-    let configuration_ron = ron::to_string(&configuration).unwrap();
-    fs::write("msc-configuration.ron", configuration_ron)
-        .expect("Unable to write to msc-configuration.ron");
-    configuration
+// Where --config-dir keeps the configuration when it isn't given a directory, following each
+// platform's convention, e.g. ~/.config/minecraft-server-cli on Linux.
+fn get_default_configuration_directory() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        PathBuf::from(std::env::var_os("APPDATA")?)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(std::env::var_os("HOME")?).join("Library/Application Support")
+    } else {
+        match std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from) {
+            Some(directory) if directory.is_absolute() => directory,
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        }
+    };
+    Some(base.join("minecraft-server-cli"))
 }
 
-fn get_configuration() -> MinecraftServerConfiguration {
-    let default_configuration = MinecraftServerConfiguration {
-        bonusChest: true,
-        demo: false,
-        eraseCache: false,
-        forceUpgrade: false,
-        initSettings: false,
-        gui: false,
-        port: None,
-        safeMode: false,
-        singleplayer: false,
-        universe: None,
-        world: None,
+// Each server gets its own folder in --config-dir, so that its msc-configuration.ron and profiles are
+// laid out the same way they would be in the server's directory. Folders are named after the server's
+// directory and a hash of its whole path, e.g. "survival-1a2b3c4d" for /srv/survival.
+fn server_configuration_directory(
+    configuration_directory: &Path,
+    server_directory: &Path,
+) -> PathBuf {
+    let name: String = server_directory
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() || character == '-' || character == '_' {
+                character
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let hash = fnv1a(server_directory.to_string_lossy().as_bytes()) as u32;
+    let folder = if name.is_empty() {
+        format!("{:08x}", hash)
+    } else {
+        format!("{}-{:08x}", name, hash)
     };
+    configuration_directory.join(folder)
+}
 
-    let result = fs::read_to_string("msc-configuration.ron");
-    if let Ok(configuration_string) = result {
-        if let Ok(configuration) =
-            ron::from_str::<MinecraftServerConfiguration>(&configuration_string)
-        {
-            return configuration;
-        } else {
-            println!("Unable to successfully parse msc-configuration.ron, falling back to default configuration.");
+// Expands a leading "~" to the home directory and strips any trailing separators so that
+// directories can be given with or without them.
+fn normalize_directory(directory: &str) -> PathBuf {
+    let directory = match (directory.strip_prefix('~'), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            format!("{}{}", home, rest)
         }
+        _ => directory.to_string(),
+    };
+    let trimmed = directory.trim_end_matches(['/', '\\']);
+    if trimmed.is_empty() || trimmed.ends_with(':') {
+        // The directory was the filesystem root, e.g. "/" or "C:\".
+        PathBuf::from(directory)
     } else {
-        println!(
-            "Unable to read the msc-configuration.ron file, falling back to default configuration."
+        PathBuf::from(trimmed)
+    }
+}
+
+// Profiles are separate configuration files that live next to msc-configuration.ron, e.g.
+// msc-modded.ron for the "modded" profile.
+fn profile_filename(name: &str) -> String {
+    format!("msc-{}.ron", name)
+}
+
+// The name of the profile that the configuration file belongs to, e.g. "creative" for
+// msc-creative.ron, or None for msc-configuration.ron.
+fn profile_name(configuration_path: &Path) -> Option<String> {
+    let filename = configuration_path.file_name()?.to_str()?;
+    if filename == CONFIGURATION_FILENAME {
+        return None;
+    }
+    let name = filename.strip_suffix(".ron").unwrap_or(filename);
+    Some(name.strip_prefix("msc-").unwrap_or(name).to_string())
+}
+
+fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().all(|character| {
+            character.is_ascii_alphanumeric() || character == '-' || character == '_'
+        })
+}
+
+// Looks for a configuration file in the directory and each of its parents, the same way git finds
+// `.git`, so that the tool can be run from anywhere inside a server's folder.
+fn find_configuration_upwards(directory: &Path) -> Option<PathBuf> {
+    directory
+        .ancestors()
+        .map(|ancestor| ancestor.join(CONFIGURATION_FILENAME))
+        .find(|path| path.is_file())
+}
+
+// A file can either hold several named instances or, like older versions of this tool wrote, a single
+// configuration which is treated as the default instance.
+fn parse_configuration_file(configuration_string: &str) -> ron::Result<ConfigurationFile> {
+    ron::from_str::<ConfigurationFile>(configuration_string).or_else(|error| {
+        // Report the error from the format the file was evidently written in.
+        let is_instances_file = configuration_string
+            .trim_start()
+            .trim_start_matches('(')
+            .trim_start()
+            .starts_with("instances");
+        if is_instances_file {
+            return Err(error);
+        }
+
+        let configuration = ron::from_str::<MinecraftServerConfiguration>(configuration_string)?;
+        let mut instances = BTreeMap::new();
+        instances.insert(DEFAULT_INSTANCE.to_string(), configuration);
+        Ok(ConfigurationFile { instances })
+    })
+}
+
+// Writes the file next to its destination first and then renames it into place, which replaces the
+// file in one step on the same file system. Being killed halfway through therefore leaves either the
+// old or the new file behind, never a truncated one.
+fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+    let temporary_path = PathBuf::from(temporary_path);
+
+    let mut file = fs::File::create(&temporary_path)?;
+    let written = file
+        .write_all(contents.as_bytes())
+        // Without syncing, a power loss right after the rename can still leave an empty file.
+        .and_then(|()| file.sync_all());
+    drop(file);
+    if let Err(error) = written.and_then(|()| fs::rename(&temporary_path, path)) {
+        let _ = fs::remove_file(&temporary_path);
+        return Err(error);
+    }
+    Ok(())
+}
+
+fn save_configuration(
+    configuration_path: &Path,
+    instance: &str,
+    configuration: MinecraftServerConfiguration,
+) -> Result<MinecraftServerConfiguration, MscError> {
+    // Anything but a missing file would lose the other instances when it's written back.
+    let mut configuration_file = match read_configuration_file(configuration_path) {
+        Ok(configuration_file) => configuration_file,
+        Err(MscError::ConfigRead { source, .. }) if source.kind() == ErrorKind::NotFound => {
+            ConfigurationFile::default()
+        }
+        Err(error) => return Err(error),
+    };
+    configuration_file
+        .instances
+        .insert(instance.to_string(), configuration.clone());
+
+    // Keep writing the single-configuration format until there is more than the default instance.
+    let configuration_ron =
+        if configuration_file.instances.len() == 1 && instance == DEFAULT_INSTANCE {
+            ron::to_string(&configuration).unwrap()
+        } else {
+            ron::to_string(&configuration_file).unwrap()
+        };
+    write_atomically(configuration_path, &configuration_ron).map_err(|source| {
+        MscError::ConfigWrite {
+            path: configuration_path.to_path_buf(),
+            source,
+        }
+    })?;
+    Ok(configuration)
+}
+
+// Writes a profile with only the default instance, replacing the file and any other instances in it,
+// e.g. when a profile is duplicated over an existing one.
+fn replace_profile(
+    configuration_path: &Path,
+    configuration: MinecraftServerConfiguration,
+) -> Result<MinecraftServerConfiguration, MscError> {
+    write_atomically(configuration_path, &ron::to_string(&configuration).unwrap()).map_err(
+        |source| MscError::ConfigWrite {
+            path: configuration_path.to_path_buf(),
+            source,
+        },
+    )?;
+    Ok(configuration)
+}
+
+// Loads the configuration file, keeping every valid value, and writes it back pretty-printed with
+// every option present and nothing else, reporting which keys that removed or added.
+fn repair_configuration(configuration_path: &Path, terminal: &Term) -> Result<(), MscError> {
+    let configuration_string =
+        fs::read_to_string(configuration_path).map_err(|source| MscError::ConfigRead {
+            path: configuration_path.to_path_buf(),
+            source,
+        })?;
+    let configuration_file = read_configuration_file(configuration_path)?;
+    let changes = find_key_changes(&configuration_string);
+
+    let pretty = ron::ser::PrettyConfig::new();
+    let repaired = match configuration_file.instances.get(DEFAULT_INSTANCE) {
+        Some(configuration) if configuration_file.instances.len() == 1 => {
+            ron::ser::to_string_pretty(configuration, pretty)
+        }
+        _ => ron::ser::to_string_pretty(&configuration_file, pretty),
+    }
+    .unwrap();
+    if repaired == configuration_string.trim_end() {
+        terminal
+            .write_line(
+                format!(
+                    "{} is already in canonical form.",
+                    configuration_path.display()
+                )
+                .as_str(),
+            )
+            .unwrap();
+        return Ok(());
+    }
+    write_atomically(configuration_path, &format!("{}\n", repaired)).map_err(|source| {
+        MscError::ConfigWrite {
+            path: configuration_path.to_path_buf(),
+            source,
+        }
+    })?;
+
+    let describe = |instance: &str, key: &str| {
+        if configuration_file.instances.len() == 1 {
+            key.to_string()
+        } else {
+            format!("{}.{}", instance, key)
+        }
+    };
+    let dropped: Vec<String> = changes
+        .iter()
+        .filter_map(|change| match change {
+            KeyChange::Dropped { instance, key } => Some(describe(instance, key)),
+            _ => None,
+        })
+        .collect();
+    let filled: Vec<String> = changes
+        .iter()
+        .filter_map(|change| match change {
+            KeyChange::Filled { instance, key } => Some(describe(instance, key)),
+            _ => None,
+        })
+        .collect();
+    terminal
+        .write_line(
+            style(format!("Rewrote {}.", configuration_path.display()))
+                .green()
+                .to_string()
+                .as_str(),
+        )
+        .unwrap();
+    if !dropped.is_empty() {
+        terminal
+            .write_line(format!("Removed unknown keys: {}", dropped.join(", ")).as_str())
+            .unwrap();
+    }
+    if !filled.is_empty() {
+        terminal
+            .write_line(
+                format!(
+                    "Added missing keys with their defaults: {}",
+                    filled.join(", ")
+                )
+                .as_str(),
+            )
+            .unwrap();
+    }
+    if dropped.is_empty() && filled.is_empty() {
+        terminal.write_line("Only the formatting changed.").unwrap();
+    }
+    Ok(())
+}
+
+// Formats a RON error like a compiler would, pointing at the line and column it occurred on.
+fn describe_parse_error(
+    configuration_path: &Path,
+    configuration_string: &str,
+    error: &ron::Error,
+) -> String {
+    let ron::error::Position { line, col } = error.position;
+    let mut description = format!(
+        "{}:{}:{}: {}",
+        configuration_path.display(),
+        line,
+        col,
+        error.code
+    );
+    if let Some(source_line) = configuration_string.lines().nth(line.saturating_sub(1)) {
+        let gutter = line.to_string();
+        description = format!(
+            "{}\n{} | {}\n{} | {}^",
+            description,
+            gutter,
+            source_line,
+            " ".repeat(gutter.len()),
+            " ".repeat(col.saturating_sub(1))
         );
     }
+    description
+}
+
+// A configuration file that can't be parsed was most likely edited by hand. Rather than silently
+// replacing it with the defaults, make sure that's what the user wants and keep a copy of it.
+fn confirm_default_configuration(configuration_path: &Path) -> Result<(), MscError> {
+    // The theme is set from the configuration, which is what couldn't be read.
+    let start_over = dialoguer::Confirm::with_theme(&dialoguer::theme::SimpleTheme)
+        .with_prompt(
+            "Continue with the default configuration? Choose \"no\" to exit and fix the file",
+        )
+        .default(false)
+        .interact()?;
+    if !start_over {
+        return Err(MscError::Validation(format!(
+            "{} was left as it is. Fix the problem above and run the tool again.",
+            configuration_path.display()
+        )));
+    }
+
+    let mut broken_path = configuration_path.as_os_str().to_owned();
+    broken_path.push(".broken");
+    let broken_path = PathBuf::from(broken_path);
+    // Moving it aside keeps the only version of the file, and lets the defaults be saved in its place.
+    fs::rename(configuration_path, &broken_path).map_err(|source| MscError::ConfigWrite {
+        path: broken_path.clone(),
+        source,
+    })?;
+    println!(
+        "The unparsable file was moved to {}.",
+        broken_path.display()
+    );
+    Ok(())
+}
+
+// Locks the configuration file for as long as the returned guard is kept. A lock that can't be
+// created at all, e.g. on a read-only file system, doesn't stop the tool, since there's nothing to
+// protect when nothing can be written.
+fn acquire_configuration_lock(configuration_path: &Path) -> Option<ConfigurationLock> {
+    match lock_configuration(configuration_path) {
+        Ok(LockOutcome::Acquired(lock)) => Some(lock),
+        Ok(LockOutcome::Held(pid)) => {
+            let holder = match pid {
+                Some(pid) => format!(" (process {})", pid),
+                None => String::new(),
+            };
+            println!(
+                "{}",
+                style(format!(
+                    "{} is locked, so another msc instance may be running{}. Changes made in both will overwrite each other.",
+                    configuration_path.display(),
+                    holder
+                ))
+                .yellow()
+            );
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+fn read_configuration_file(configuration_path: &Path) -> Result<ConfigurationFile, MscError> {
+    let configuration_string =
+        fs::read_to_string(configuration_path).map_err(|source| MscError::ConfigRead {
+            path: configuration_path.to_path_buf(),
+            source,
+        })?;
+    parse_configuration_file(&configuration_string).map_err(|error| MscError::ConfigParse {
+        path: configuration_path.to_path_buf(),
+        description: describe_parse_error(configuration_path, &configuration_string, &error),
+    })
+}
+
+fn get_instances(
+    configuration_path: &Path,
+    interactive: bool,
+) -> Result<BTreeMap<String, MinecraftServerConfiguration>, MscError> {
+    let mut default_instances = BTreeMap::new();
+    default_instances.insert(
+        DEFAULT_INSTANCE.to_string(),
+        MinecraftServerConfiguration::default(),
+    );
+
+    match read_configuration_file(configuration_path) {
+        Ok(configuration_file) => {
+            if !configuration_file.instances.is_empty() {
+                return Ok(configuration_file.instances);
+            }
+        }
+        Err(MscError::ConfigRead { source, .. }) if source.kind() == ErrorKind::NotFound => {
+            println!(
+                "Unable to read the {} file, falling back to default configuration.",
+                configuration_path.display()
+            );
+        }
+        // Without a terminal to ask on, continuing with the defaults would go unnoticed.
+        Err(error @ MscError::ConfigParse { .. }) if !interactive => return Err(error),
+        Err(error @ MscError::ConfigParse { .. }) => {
+            println!("{}", style(error).red());
+            confirm_default_configuration(configuration_path)?;
+        }
+        Err(error) => return Err(error),
+    }
+
+    Ok(default_instances)
+}
+
+// Lets the user pick one of the instances in the configuration file or create a new one. Nothing is
+// picked when they press Escape.
+fn select_instance(
+    configuration_path: &Path,
+    mut instances: BTreeMap<String, MinecraftServerConfiguration>,
+    terminal: &Term,
+) -> Result<Option<(String, MinecraftServerConfiguration)>, MscError> {
+    let names: Vec<String> = instances.keys().cloned().collect();
+    let items: Vec<&str> = names
+        .iter()
+        .map(String::as_str)
+        .chain(std::iter::once("Create a new instance"))
+        .collect();
+    let result = dialoguer::Select::with_theme(theme())
+        .with_prompt("Please select the server instance you wish to use")
+        .items(&items)
+        .default(0)
+        .interact_on_opt(terminal)?;
+    let result = match result {
+        Some(result) => result,
+        None => return Ok(None),
+    };
+
+    if let Some(name) = names.get(result) {
+        let configuration = instances.remove(name).unwrap();
+        return Ok(Some((name.clone(), configuration)));
+    }
 
-    return default_configuration;
+    loop {
+        let name: String = dialoguer::Input::with_theme(theme())
+            .with_prompt("Name of the new instance")
+            .interact_text_on(terminal)
+            .unwrap();
+        let name = name.trim().to_string();
+        if name.is_empty() || instances.contains_key(&name) {
+            terminal
+                .write_line("That name is empty or already taken. Please try again.")
+                .unwrap();
+            continue;
+        }
+        let configuration = save_configuration(
+            configuration_path,
+            &name,
+            MinecraftServerConfiguration::default(),
+        )?;
+        return Ok(Some((name, configuration)));
+    }
 }
 
 fn get_config_option_info() -> Vec<ConfigurationOption> {
     vec![
+		ConfigurationOption {
+			property: "serverType".to_string(),
+			name: "Server type".to_string(),
+			description: "What kind of server the jar runs (Vanilla, Forge, Fabric, or Paper). Options that the server type doesn't support are left out when launching.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Choice}),
+			validator: Some(OptionValidator::OneOf(ServerType::ALL.iter().map(|server_type| server_type.name().to_string()).collect())),
+		},
+		ConfigurationOption {
+			property: "javaPath".to_string(),
+			name: "Java executable".to_string(),
+			description: "The java executable that runs the server, for when the one in your PATH is the wrong version. When disabled, \"java\" from your PATH is used.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | String}),
+			validator: None,
+		},
 		ConfigurationOption {
 			property: "bonusChest".to_string(),
 			name: "Bonus chest".to_string(),
 			description: "Whether or not to add the bonus chest when creating a new world.".to_string(),
 			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Bool}),
+			validator: None,
 		},
 		ConfigurationOption {
 			property: "demo".to_string(),
 			name: "Demo mode".to_string(),
 			description: "Shows the players a demo pop-up, players can't place/break/eat once the demo expires.".to_string(),
 			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Bool}),
+			validator: None,
 		},
 		ConfigurationOption {
 			property: "eraseCache".to_string(),
 			name: "Erase the cache".to_string(),
 			description: "Erases the lighting caches, etc.".to_string(),
 			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Bool}),
+			validator: None,
 		},
 		ConfigurationOption {
 			property: "forceUpgrade".to_string(),
 			name: "Force an upgrade".to_string(),
 			description: "Forces an upgrade on all the chunks.".to_string(),
 			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Bool}),
+			validator: None,
 		},
 		ConfigurationOption {
 			property: "initSettings".to_string(),
 			name: "Initialize server settings".to_string(),
 			description: "Initializes 'server.properties' and 'eula.txt', then quits.".to_string(),
 			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Bool}),
+			validator: None,
 		},
 		ConfigurationOption {
 			property: "gui".to_string(),
 			name: "GUI mode".to_string(),
-			description: "When enabled, opens the GUI upon launch of the server.".to_string(),
+			description: "When enabled, opens the GUI upon launch of the server. Ignored when there is no display to open it on or with --headless.".to_string(),
 			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Bool}),
+			validator: None,
 		},
 		ConfigurationOption {
 			property: "port".to_string(),
 			name: "Port".to_string(),
 			description: "Which port to listen on, overrides the server.properties value.".to_string(),
 			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | U16}),
+			validator: Some(OptionValidator::Range(1, u16::MAX)),
 		},
 		ConfigurationOption {
 			property: "safeMode".to_string(),
 			name: "Safe mode".to_string(),
 			description: "Loads level with vanilla data pack only.".to_string(),
 			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Bool}),
+			validator: None,
 		},
 		ConfigurationOption {
 			property: "singleplayer".to_string(),
 			name: "Single-player mode".to_string(),
 			description: "Runs the server in offline mode without authentication. This is insecure, do not use this when online.".to_string(),
 			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Bool}),
+			validator: None,
+		},
+		ConfigurationOption {
+			property: "jfrProfile".to_string(),
+			name: "Flight recorder profiling".to_string(),
+			description: "Records a Java Flight Recorder profile while the server runs (--jfrProfile), for diagnosing lag. Open the recording with JDK Mission Control.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Bool}),
+			validator: None,
 		},
 		ConfigurationOption {
 			property: "universe".to_string(),
 			name: "Universe name".to_string(),
 			description: "The folder name of the universe that you want to use that contains all of your worlds.".to_string(),
 			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | String}),
+			validator: None,
 		},
 		ConfigurationOption {
 			property: "world".to_string(),
 			name: "World name".to_string(),
 			description: "The folder name for the world you want to run.".to_string(),
 			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | String}),
+			validator: Some(OptionValidator::FolderName),
+		},
+		ConfigurationOption {
+			property: "maxMemory".to_string(),
+			name: "Maximum memory".to_string(),
+			description: "The most memory the server may use (-Xmx), e.g. 4G or 512M. Numbers without a unit are in megabytes. When disabled, half of this machine's memory is used, up to 8G.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | Memory}),
+			validator: None,
+		},
+		ConfigurationOption {
+			property: "minMemory".to_string(),
+			name: "Initial memory".to_string(),
+			description: "The memory the server starts with (-Xms), e.g. 1G or 512M. Numbers without a unit are in megabytes.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | Memory}),
+			validator: None,
+		},
+		ConfigurationOption {
+			property: "backupBeforeStart".to_string(),
+			name: "Back up before starting".to_string(),
+			description: "Copies the world into the msc-backups folder every time before the server starts.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Bool}),
+			validator: None,
+		},
+		ConfigurationOption {
+			property: "backupRetention".to_string(),
+			name: "Backups to keep".to_string(),
+			description: "How many backups of each world to keep. The oldest ones are removed after every backup. When disabled, every backup is kept.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | U16}),
+			validator: Some(OptionValidator::Range(1, u16::MAX)),
+		},
+		ConfigurationOption {
+			property: "preStartHook".to_string(),
+			name: "Pre-start hook".to_string(),
+			description: "A shell command that runs before the server starts, e.g. to mount a volume. The server isn't started when the command fails. The server's port is in the MSC_PORT environment variable.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | String}),
+			validator: None,
+		},
+		ConfigurationOption {
+			property: "postStartHook".to_string(),
+			name: "Post-start hook".to_string(),
+			description: "A shell command that runs once the server has finished starting, e.g. to send a notification. The server's port is in the MSC_PORT environment variable. Not run when the server is started in a --session.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | String}),
+			validator: None,
+		},
+		ConfigurationOption {
+			property: "rconPasswordFile".to_string(),
+			name: "RCON password file".to_string(),
+			description: "A file holding the RCON password, used by the stop subcommand instead of rcon.password in server.properties. The RCON_PASSWORD environment variable and --rcon-password-file take precedence.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | String}),
+			validator: None,
+		},
+		ConfigurationOption {
+			property: "dailyRestartTime".to_string(),
+			name: "Daily restart time".to_string(),
+			description: "A 24-hour time like 04:30 at which the server warns its players, stops, and starts again every day. Not used by smoke tests.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | String}),
+			validator: Some(OptionValidator::TimeOfDay),
+		},
+		ConfigurationOption {
+			property: "dailyRestartWarningMinutes".to_string(),
+			name: "Daily restart warning".to_string(),
+			description: "How many minutes before the daily restart the players are told about it. Defaults to 5. Not used when shutdownWarnings are set.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | U16}),
+			validator: Some(OptionValidator::Range(0, 720)),
+		},
+		ConfigurationOption {
+			property: "idleShutdownMinutes".to_string(),
+			name: "Idle shutdown".to_string(),
+			description: "Stops the server once nobody has been online for this many minutes since it started or the last player left, e.g. for servers that are only started on demand.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | U16}),
+			validator: Some(OptionValidator::Range(1, 1440)),
+		},
+		ConfigurationOption {
+			property: "memoryLimit".to_string(),
+			name: "Memory limit".to_string(),
+			description: "The most memory the whole server process may use, e.g. 6G, enforced by running it in a systemd scope (MemoryMax). Leave room above the maximum memory for Java itself. Needs Linux with systemd; ignored elsewhere.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | Memory}),
+			validator: None,
+		},
+		ConfigurationOption {
+			property: "cpuQuota".to_string(),
+			name: "CPU quota".to_string(),
+			description: "The share of one CPU core the server may use, in percent, e.g. 200 for two cores, enforced by running it in a systemd scope (CPUQuota). Needs Linux with systemd; ignored elsewhere.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | U16}),
+			validator: Some(OptionValidator::Range(1, 10000)),
+		},
+		ConfigurationOption {
+			property: "niceness".to_string(),
+			name: "Niceness".to_string(),
+			description: "Lowers the server's CPU and IO priority so that it yields to everything else on the machine, from 0 (normal) to 19 (lowest). Set through a systemd scope (Nice). Needs Linux with systemd; ignored elsewhere.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Option | U16}),
+			validator: Some(OptionValidator::Range(0, 19)),
+		},
+		ConfigurationOption {
+			property: "auditLog".to_string(),
+			name: "Audit log".to_string(),
+			description: "Records every change made to an option in msc-audit.log, with when it was made, by which user, and the old and new values, e.g. for servers with several admins.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Bool}),
+			validator: None,
+		},
+		ConfigurationOption {
+			property: "checkRegionFiles".to_string(),
+			name: "Check region files".to_string(),
+			description: "Before starting, looks for region files in the world that were cut short, e.g. by a crash while saving, which make the server crash when it loads their chunks. Only their sizes and headers are read.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Bool}),
+			validator: None,
+		},
+		ConfigurationOption {
+			property: "plainLogOutput".to_string(),
+			name: "Plain log output".to_string(),
+			description: "When the output goes to a file (--log-to-file) or a pipe, passes -Dterminal.jline=false -Dterminal.ansi=false so that the log has no terminal control codes. Java system properties with the same keys take precedence.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Bool}),
+			validator: None,
+		},
+		ConfigurationOption {
+			property: "systemProperties".to_string(),
+			name: "Java system properties".to_string(),
+			description: "Properties passed to Java as -Dkey=value, e.g. file.encoding=UTF-8.".to_string(),
+			r#type: make_bitflags!(ConfigurationOptionTypeFlag::{KeyValues}),
+			validator: None,
 		}
 	]
 }
 
-fn get_names(
-    config_option_info: Vec<ConfigurationOption>,
-    configuration: MinecraftServerConfiguration,
-) -> Vec<String> {
-    let configuration_option_names: Vec<String> = config_option_info
-        .into_iter()
-        .map(|option_information| {
-            let value: String = match configuration.get(option_information.property) {
-                ConfigurationOptionType::Bool(value) => {
-                    if value {
-                        "Enabled".to_string()
-                    } else {
-                        "Disabled".to_string()
-                    }
-                }
-                ConfigurationOptionType::OptionString(value) => {
-                    value.unwrap_or("default".to_string())
-                }
-                ConfigurationOptionType::OptionU16(value) => match value {
-                    Some(value) => value.to_string(),
-                    None => "default".to_string(),
-                },
-            };
-            format!("{} ({})", option_information.name, value)
-        })
-        .collect();
-    vec![
-        vec!["Start server now".to_string(), "Exit".to_string()],
-        configuration_option_names,
-    ]
-    .concat()
+// The server.properties settings that "Edit server.properties" and --set can change. They are edited
+// like the options above but written to server.properties.
+fn get_property_option_info() -> Vec<ConfigurationOption> {
+    let tunables = TUNABLE_PROPERTIES
+        .iter()
+        .map(|property| ConfigurationOption {
+            property: property.key.to_string(),
+            name: property.name.to_string(),
+            description: property.description.to_string(),
+            r#type: make_bitflags!(ConfigurationOptionTypeFlag::{U16 | ServerProperty}),
+            validator: Some(OptionValidator::Range(property.min, property.max)),
+        });
+    let choices = CHOICE_PROPERTIES
+        .iter()
+        .map(|property| ConfigurationOption {
+            property: property.key.to_string(),
+            name: property.name.to_string(),
+            description: property.description.to_string(),
+            r#type: make_bitflags!(ConfigurationOptionTypeFlag::{Choice | ServerProperty}),
+            validator: Some(OptionValidator::OneOf(
+                property
+                    .choices
+                    .iter()
+                    .map(|choice| choice.to_string())
+                    .collect(),
+            )),
+        });
+    tunables.chain(choices).collect()
 }
 
-fn run_server(
-    configuration: MinecraftServerConfiguration,
-    jar_filename: String,
-    terminal: Term,
-) -> (MinecraftServerConfiguration, Term) {
-    let mut command_string = format!("java -jar {}", jar_filename);
-
-    for option in get_config_option_info() {
-        let value = configuration.get(option.property.clone());
-        let cli_flag = match (option.property.clone().as_str(), value) {
-            ("gui", ConfigurationOptionType::Bool(value)) => {
-                if value {
-                    None
-                } else {
-                    Some("--nogui".to_string())
-                }
-            }
-            (property, ConfigurationOptionType::Bool(value)) => {
-                if value {
-                    Some(format!("--{}", property))
-                } else {
-                    None
-                }
-            }
-            (property, ConfigurationOptionType::OptionU16(value)) => match value {
-                Some(value) => Some(format!("--{} {}", property, value.to_string())),
-                None => None,
-            },
-            (property, ConfigurationOptionType::OptionString(value)) => match value {
-                Some(value) => Some(format!("--{} {}", property, value)),
-                None => None,
-            },
-        };
+// The value that the server uses for a setting from get_property_option_info, which is its default
+// when the file doesn't set it.
+fn server_property_value(property: &str, properties: &ServerProperties) -> String {
+    if let Some(tunable) = find_tunable_property(property) {
+        tunable.current(properties).to_string()
+    } else if let Some(choice) = find_choice_property(property) {
+        choice.current(properties).to_string()
+    } else {
+        properties.get(property).unwrap_or_default().to_string()
+    }
+}
 
-        if let Some(cli_flag) = cli_flag {
-            command_string = format!("{} {}", command_string, cli_flag);
-        }
+fn server_property_default(property: &str) -> String {
+    if let Some(tunable) = find_tunable_property(property) {
+        tunable.default.to_string()
+    } else {
+        find_choice_property(property)
+            .map(|choice| choice.default.to_string())
+            .unwrap_or_default()
     }
+}
 
+// Writes the value of an option from get_property_option_info to server.properties, which the
+// server only reads when it starts.
+fn write_server_property(
+    machine: &mut Machine,
+    option: &ConfigurationOption,
+    value: &str,
+    terminal: &Term,
+) -> Result<(), MscError> {
+    let path = Path::new("server.properties");
+    let mut properties = ServerProperties::read_or_default(path).map_err(|error| {
+        MscError::Io(io::Error::new(
+            error.kind(),
+            format!("Unable to read server.properties: {}", error),
+        ))
+    })?;
+    let old = server_property_value(&option.property, &properties);
+    if machine.configuration.auditLog && old != value {
+        machine.audit_entries.push(AuditEntry {
+            property: option.property.clone(),
+            old: redact_property_value(&option.property, &old),
+            new: redact_property_value(&option.property, value),
+        });
+    }
+    properties.set(&option.property, value);
+    properties.write(path).map_err(|error| {
+        MscError::Io(io::Error::new(
+            error.kind(),
+            format!("Unable to write to server.properties: {}", error),
+        ))
+    })?;
     terminal
         .write_line(
-            style("Starting your Minecraft Server")
-                .green()
+            style(format!(
+                "{} is now {} ({} in server.properties). Restart the server for it to take effect.",
+                option.name, value, option.property
+            ))
+            .green()
+            .to_string()
+            .as_str(),
+        )
+        .unwrap();
+    terminal
+        .write_line(
+            style("Press any key to go back to the menu.")
+                .dim()
                 .to_string()
                 .as_str(),
         )
         .unwrap();
-    terminal.write_line(command_string.as_str()).unwrap();
+    terminal.read_key()?;
+    Ok(())
+}
+
+// Longer explanations for options that are commonly misunderstood, shown in their editor.
+fn get_option_details(property: &str) -> Option<&'static str> {
+    match property {
+        "gamemode" => Some(
+            "Players who have joined before keep their own game mode unless force-gamemode is on.",
+        ),
+        "demo" => Some(
+            "Demo mode gives every player the same time-limited experience as the Minecraft demo. \
+             Players get a demo pop-up when they join and the demo lasts for five in-game days \
+             (about 100 minutes of play). Once it has expired, players can still move around, but \
+             they can no longer place or break blocks, or eat. This applies to everyone, including \
+             players who own the game.",
+        ),
+        _ => None,
+    }
+}
 
-    let mut command = command(command_string);
-    command.stdin(Stdio::inherit());
-    command.stdout(Stdio::inherit());
+const DEMO_BANNER: &str = "\
+*******************************************************************************
+* DEMO MODE IS ENABLED                                                        *
+* Players can't place or break blocks, or eat, once the demo expires after    *
+* five in-game days. Disable \"Demo mode\" unless this is what you want.        *
+*******************************************************************************";
 
-    let output = command.output();
-    match output {
-        Ok(output) => {
-            let code = output.status.code().unwrap();
-            if code == 0 {
-                terminal.write_line("It was a success!").unwrap();
+fn format_option_value(property: &str, value: ConfigurationOptionType) -> String {
+    match value {
+        ConfigurationOptionType::Bool(value) => {
+            if value {
+                "Enabled".to_string()
             } else {
-                terminal
-                    .write_line("Something went wrong! Do you have Java installed?")
-                    .unwrap();
+                "Disabled".to_string()
             }
         }
-        Err(error) => {
-            terminal
-                .write_line(style("An error ocurred!").red().to_string().as_str())
-                .unwrap();
-            terminal.write_line(error.to_string().as_str()).unwrap();
-        }
+        ConfigurationOptionType::OptionString(value) => value.unwrap_or("default".to_string()),
+        ConfigurationOptionType::OptionU16(value) => match value {
+            Some(value) => value.to_string(),
+            None => "default".to_string(),
+        },
+        ConfigurationOptionType::OptionMemory(value) => match value {
+            Some(value) => value.to_string(),
+            None if property == "maxMemory" => format!("default, {} suggested", suggested_heap()),
+            None => "default".to_string(),
+        },
+        ConfigurationOptionType::KeyValues(values) => match values.len() {
+            0 => "none".to_string(),
+            1 => "1 entry".to_string(),
+            count => format!("{} entries", count),
+        },
+        ConfigurationOptionType::ServerType(value) => value.name().to_string(),
+        ConfigurationOptionType::Choice(value) => value,
     }
-    return (configuration, terminal);
 }
 
-fn main() {
-    let matches = App::new("minecraft-server-cli")
-		.version("0.1.0")
-    .author("Andria Brown <andria_girl@pm.me>")
-    .about("A command-line interface used to edit and persist your Minecraft server command-line settings and start your Minecraft server.")
-		.arg(Arg::with_name("jar_filename")
-			.index(1)
-			.help("The name you saved your Minecraft server .jar file under.")
-			.required(true)
-    	.takes_value(true)
-		).arg(Arg::with_name("server_directory")
-			.index(2)
-			.help("The relative or absolute path to the directory containing your Minecraft server. Defaults to %AppData%\\.minecraft\\server\\ on Windows and ~/.minecraft/server/ on Unix-based systems. Please end your value with a forward slash (Unix-based /) or backslash (Windows \\).")
-		).get_matches();
+fn format_option_type(value: &ConfigurationOptionType) -> &'static str {
+    match value {
+        ConfigurationOptionType::Bool(_) => "on/off",
+        ConfigurationOptionType::OptionString(_) => "optional text",
+        ConfigurationOptionType::OptionU16(_) => "optional number",
+        ConfigurationOptionType::OptionMemory(_) => "optional memory size",
+        ConfigurationOptionType::KeyValues(_) => "list of key=value",
+        ConfigurationOptionType::ServerType(_) | ConfigurationOptionType::Choice(_) => "choice",
+    }
+}
 
-    let jar_filename = matches.value_of("jar_filename").unwrap().to_string();
-    let server_directory = matches
-        .value_of("server_directory")
-        .unwrap_or(get_default_configuration_path().as_str())
-        .to_string();
-    set_current_dir(server_directory).expect(
-        "Expected to be able to change the current directory to your Minecraft Server's directory.",
+fn get_names(
+    config_option_info: Vec<ConfigurationOption>,
+    configuration: MinecraftServerConfiguration,
+) -> Vec<String> {
+    let configuration_option_names: Vec<String> = config_option_info
+        .into_iter()
+        .map(|option_information| {
+            let value = format_option_value(
+                &option_information.property,
+                configuration.get(option_information.property.clone()),
+            );
+            format!("{} ({})", option_information.name, value)
+        })
+        .collect();
+    configuration_option_names
+}
+
+// A configuration file with every option at its default value and its description as a comment.
+// The text is put together by hand because ron can't write comments.
+fn default_configuration_template() -> String {
+    let defaults = MinecraftServerConfiguration::default();
+    let mut template = format!(
+        "// A {} with every option set to its default value.\n(\n",
+        CONFIGURATION_FILENAME
+    );
+    for option in get_config_option_info() {
+        let value = defaults.get(option.property.clone()).to_ron();
+        template.push_str(&format!(
+            "    // {}: {}\n    {}: {},\n",
+            option.name, option.description, option.property, value
+        ));
+    }
+    template.push_str(
+        "    // Broadcast to the players before the stop subcommand or a daily restart, as (seconds before it, message), e.g. [(300, \"Restarting in 5 minutes\"), (10, \"Restarting in 10 seconds\")]. Not a server option.\n    shutdownWarnings: [],\n",
+    );
+    template.push_str(
+        "    // Console commands to run one after another through RCON from the menu, as (name, commands), e.g. [(\"Prepare for backup\", [\"save-off\", \"save-all\"])]. Not a server option.\n    macros: [],\n",
+    );
+    template.push_str(
+        "    // Console commands typed into the server once it has finished starting, in order, e.g. [\"whitelist reload\"]. Not a server option.\n    startupCommands: [],\n",
     );
+    template.push_str(
+        "    // The colors of the prompts, e.g. Some((colorful: true, promptColor: Some(\"cyan\"))). Not a server option.\n    theme: None,\n)\n",
+    );
+    template
+}
 
-    let mut machine = Machine {
-        state: AppState::ChoiceMenu,
+// Every option with its current value, for a quick look without the menu.
+fn option_rows(
+    config_option_info: &[ConfigurationOption],
+    configuration: &MinecraftServerConfiguration,
+) -> Vec<(ConfigurationOption, ConfigurationOptionType)> {
+    config_option_info
+        .iter()
+        .map(|option| (option.clone(), configuration.get(option.property.clone())))
+        .collect()
+}
+
+fn option_value_json(value: &ConfigurationOptionType) -> Json {
+    match value {
+        ConfigurationOptionType::Bool(value) => Json::Bool(*value),
+        ConfigurationOptionType::OptionU16(value) => {
+            Json::optional(*value, |value| Json::Number(value as i64))
+        }
+        ConfigurationOptionType::OptionString(value) => Json::optional(value.clone(), Json::String),
+        ConfigurationOptionType::OptionMemory(value) => {
+            Json::optional(*value, |value| Json::string(value.to_string()))
+        }
+        ConfigurationOptionType::KeyValues(values) => Json::Object(
+            values
+                .iter()
+                .map(|(key, value)| (key.clone(), Json::string(value.as_str())))
+                .collect(),
+        ),
+        ConfigurationOptionType::ServerType(value) => Json::string(value.name()),
+        ConfigurationOptionType::Choice(value) => Json::string(value.as_str()),
+    }
+}
+
+struct OptionsReport {
+    rows: Vec<(ConfigurationOption, ConfigurationOptionType)>,
+}
+
+impl Report for OptionsReport {
+    fn human(&self) -> Vec<String> {
+        let mut rows = vec![[
+            "Property".to_string(),
+            "Name".to_string(),
+            "Value".to_string(),
+            "Type".to_string(),
+        ]];
+        for (option, value) in &self.rows {
+            rows.push([
+                option.property.clone(),
+                option.name.clone(),
+                format_option_value(&option.property, value.clone()),
+                format_option_type(value).to_string(),
+            ]);
+        }
+        table_lines(&rows)
+    }
+
+    // Unset options are null rather than "default", so that scripts can tell them apart.
+    fn json(&self) -> Json {
+        Json::Array(
+            self.rows
+                .iter()
+                .map(|(option, value)| {
+                    Json::object(vec![
+                        ("property", Json::string(option.property.as_str())),
+                        ("name", Json::string(option.name.as_str())),
+                        ("value", option_value_json(value)),
+                        ("type", Json::string(format_option_type(value))),
+                    ])
+                })
+                .collect(),
+        )
+    }
+}
+
+fn format_diagnostic(diagnostic: &Diagnostic) -> String {
+    match diagnostic.severity {
+        Severity::Warning => style(diagnostic.to_string()).yellow(),
+        Severity::Error => style(diagnostic.to_string()).red(),
+    }
+    .to_string()
+}
+
+struct ValidationReport {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+}
+
+impl Report for ValidationReport {
+    fn human(&self) -> Vec<String> {
+        if self.diagnostics.is_empty() {
+            return vec![style("The configuration has no problems.")
+                .green()
+                .to_string()];
+        }
+        self.diagnostics.iter().map(format_diagnostic).collect()
+    }
+
+    fn json(&self) -> Json {
+        Json::object(vec![
+            ("valid", Json::Bool(!self.has_errors())),
+            ("diagnostics", diagnostics_json(&self.diagnostics)),
+        ])
+    }
+}
+
+fn diagnostics_json(diagnostics: &[Diagnostic]) -> Json {
+    Json::Array(
+        diagnostics
+            .iter()
+            .map(|diagnostic| {
+                Json::object(vec![
+                    (
+                        "severity",
+                        Json::string(match diagnostic.severity {
+                            Severity::Warning => "warning",
+                            Severity::Error => "error",
+                        }),
+                    ),
+                    ("message", Json::string(diagnostic.message.as_str())),
+                ])
+            })
+            .collect(),
+    )
+}
+
+// One of the things --check looks at, e.g. whether Java can be run.
+struct Check {
+    name: &'static str,
+    passed: bool,
+    detail: String,
+}
+
+// Everything that has to be in place for the server to start, without starting it.
+struct CheckReport {
+    checks: Vec<Check>,
+    diagnostics: Vec<Diagnostic>,
+    // The command that --start would run.
+    command: Vec<String>,
+}
+
+impl CheckReport {
+    fn is_ready(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+}
+
+impl Report for CheckReport {
+    fn human(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for check in &self.checks {
+            let label = if check.passed {
+                style("ok").green()
+            } else {
+                style("failed").red()
+            };
+            lines.push(format!("{:>6} {}: {}", label, check.name, check.detail));
+            if check.name == "Configuration" {
+                lines.extend(self.diagnostics.iter().map(format_diagnostic));
+            }
+        }
+        lines.push(format!("Would run: {}", format_command(&self.command)));
+        let failed = self.checks.iter().filter(|check| !check.passed).count();
+        lines.push(if failed == 0 {
+            style("Ready to start.").green().to_string()
+        } else {
+            style(format!(
+                "Not ready: {} check{} failed.",
+                failed,
+                if failed == 1 { "" } else { "s" }
+            ))
+            .red()
+            .to_string()
+        });
+        lines
+    }
+
+    fn json(&self) -> Json {
+        Json::object(vec![
+            ("ready", Json::Bool(self.is_ready())),
+            (
+                "checks",
+                Json::Array(
+                    self.checks
+                        .iter()
+                        .map(|check| {
+                            Json::object(vec![
+                                ("name", Json::string(check.name)),
+                                ("passed", Json::Bool(check.passed)),
+                                ("detail", Json::string(check.detail.as_str())),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            ("diagnostics", diagnostics_json(&self.diagnostics)),
+            (
+                "command",
+                Json::Array(
+                    self.command
+                        .iter()
+                        .map(|argument| Json::string(argument.as_str()))
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+}
+
+// Validates the configuration and makes sure the jar, Java, and the EULA are ready, reporting what
+// would be run. Nothing is launched, not even the hooks.
+fn check_readiness(
+    instance: &str,
+    configuration: &MinecraftServerConfiguration,
+    jar_filename: &str,
+    // Why the jar's pattern didn't match anything.
+    jar_error: Option<&io::Error>,
+    launch_options: &LaunchOptions,
+) -> CheckReport {
+    let mut diagnostics = validate_configuration(configuration, !launch_options.skip_port_check);
+    diagnostics.extend(session_diagnostics(configuration, launch_options));
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    let plural = |count: usize, noun: &str| {
+        format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+    };
+    let mut checks = vec![Check {
+        name: "Configuration",
+        passed: errors == 0,
+        detail: match (errors, warnings) {
+            (0, 0) => "no problems".to_string(),
+            (0, warnings) => plural(warnings, "warning"),
+            (errors, 0) => plural(errors, "error"),
+            (errors, warnings) => format!(
+                "{}, {}",
+                plural(errors, "error"),
+                plural(warnings, "warning")
+            ),
+        },
+    }];
+
+    let jar = jar_filename;
+    checks.push(match jar_error {
+        Some(error) => Check {
+            name: "Jar",
+            passed: false,
+            detail: error.to_string().trim_end_matches('.').to_string(),
+        },
+        None if !Path::new(jar).is_file() => Check {
+            name: "Jar",
+            passed: false,
+            detail: format!("{} doesn't exist", jar),
+        },
+        None => match read_jar_version(Path::new(jar)) {
+            Ok(version) => Check {
+                name: "Jar",
+                passed: true,
+                detail: format!("{} (Minecraft {})", jar, version),
+            },
+            // Modded and plugin launchers often don't say which version they run.
+            Err(error) if error.kind() == ErrorKind::NotFound => Check {
+                name: "Jar",
+                passed: true,
+                detail: jar.to_string(),
+            },
+            Err(error) => Check {
+                name: "Jar",
+                passed: false,
+                detail: format!(
+                    "{} can't be read: {}",
+                    jar,
+                    error.to_string().trim_end_matches('.')
+                ),
+            },
+        },
+    });
+
+    let java = configured_java(configuration);
+    let java_available = is_java_available(&java);
+    checks.push(Check {
+        name: "Java",
+        passed: java_available,
+        detail: if java_available {
+            java
+        } else {
+            format!("{} can't be run", java)
+        },
+    });
+
+    let eula_accepted = is_eula_accepted();
+    checks.push(Check {
+        name: "EULA",
+        passed: eula_accepted,
+        detail: if eula_accepted {
+            "accepted".to_string()
+        } else {
+            format!("not accepted in {}, pass --accept-eula", EULA_FILENAME)
+        },
+    });
+
+    CheckReport {
+        checks,
+        diagnostics,
+        command: build_command(instance, configuration, jar_filename, launch_options),
+    }
+}
+
+// The world's info as rows for print_table, leaving out whatever level.dat didn't have.
+fn level_info_rows(info: &LevelInfo) -> Vec<[String; 2]> {
+    let mut rows = Vec::new();
+    let fields = [
+        ("Name", info.name.clone()),
+        (
+            "Saved by",
+            info.version
+                .as_ref()
+                .map(|version| format!("Minecraft {}", version)),
+        ),
+        ("Seed", info.seed.map(|seed| seed.to_string())),
+        ("Generator", info.generator.clone()),
+    ];
+    for (label, value) in fields {
+        if let Some(value) = value {
+            rows.push([label.to_string(), value]);
+        }
+    }
+    for (rule, value) in &info.game_rules {
+        rows.push([rule.clone(), value.clone()]);
+    }
+    rows
+}
+
+// Backups are named after when they were made, e.g. "2021-04-20_13-37-00", which reads better as
+// "2021-04-20 13:37:00".
+fn backup_label(backup: &Path) -> String {
+    let name = backup
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    match name.split_once('_') {
+        Some((date, time)) => format!("{} {}", date, time.replace('-', ":")),
+        None => name,
+    }
+}
+
+// What's been added to the server that changes how it behaves, e.g. "Plugins (2): EssentialsX.jar,
+// WorldEdit.jar", leaving out whatever there's none of.
+fn add_on_lines(world: &Path) -> Vec<String> {
+    format_add_ons(vec![
+        ("Plugins", list_plugins()),
+        ("Datapacks", list_datapacks(world)),
+    ])
+}
+
+fn format_add_ons(add_ons: Vec<(&str, Vec<String>)>) -> Vec<String> {
+    add_ons
+        .into_iter()
+        .filter(|(_, names)| !names.is_empty())
+        .map(|(label, names)| format!("{} ({}): {}", label, names.len(), names.join(", ")))
+        .collect()
+}
+
+// Lays out rows with every column padded to its widest cell.
+fn table_lines<const COLUMNS: usize>(rows: &[[String; COLUMNS]]) -> Vec<String> {
+    let mut widths = [0; COLUMNS];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    rows.iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect();
+            cells.join("  ").trim_end().to_string()
+        })
+        .collect()
+}
+
+fn print_table<const COLUMNS: usize>(rows: &[[String; COLUMNS]]) {
+    for line in table_lines(rows) {
+        println!("{}", line);
+    }
+}
+
+// The options that are set differently in the two configurations, in menu order.
+fn diff_configurations<'a>(
+    config_option_info: &'a [ConfigurationOption],
+    left: &MinecraftServerConfiguration,
+    right: &MinecraftServerConfiguration,
+) -> Vec<&'a ConfigurationOption> {
+    config_option_info
+        .iter()
+        .filter(|option| left.get(option.property.clone()) != right.get(option.property.clone()))
+        .collect()
+}
+
+// Every configuration in the profiles next to the configuration file, labeled with the profile's file
+// name and, for files with several instances, the instance.
+fn list_profile_configurations(
+    configuration_path: &Path,
+) -> Vec<(String, MinecraftServerConfiguration)> {
+    let directory = match configuration_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut filenames: Vec<String> = fs::read_dir(directory)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .filter(|filename| {
+                    filename
+                        .strip_prefix("msc-")
+                        .and_then(|name| name.strip_suffix(".ron"))
+                        .is_some_and(is_valid_profile_name)
+                        && filename != SHAREABLE_CONFIGURATION_FILENAME
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    filenames.sort();
+
+    let mut configurations = Vec::new();
+    for filename in filenames {
+        let instances = match read_configuration_file(&directory.join(&filename)) {
+            Ok(configuration_file) => configuration_file.instances,
+            Err(_) => continue,
+        };
+        let has_several_instances = instances.len() > 1;
+        for (instance, configuration) in instances {
+            let label = if has_several_instances {
+                format!("{} ({})", filename, instance)
+            } else {
+                filename.clone()
+            };
+            configurations.push((label, configuration));
+        }
+    }
+    configurations
+}
+
+// The two most common reasons that the server won't start, shown above the choice menu.
+fn format_readiness(eula_accepted: bool, java_available: bool) -> String {
+    let indicator = |ready: bool, ready_label: &str, blocked_label: &str| {
+        if ready {
+            style(format!("✔ {}", ready_label)).green().to_string()
+        } else {
+            style(format!("✘ {}", blocked_label)).red().to_string()
+        }
+    };
+    format!(
+        "{}   {}",
+        indicator(
+            eula_accepted,
+            "EULA accepted",
+            "EULA not accepted (see eula.txt or pass --accept-eula)"
+        ),
+        indicator(java_available, "Java found", "Java not found")
+    )
+}
+
+// The keys that toggle an on/off option right from the choice menu, in the order of the options.
+const QUICK_TOGGLE_KEYS: [char; 10] = ['1', '2', '3', '4', '5', '6', '7', '8', '9', '0'];
+
+fn get_quick_toggles(
+    config_option_info: &[ConfigurationOption],
+) -> Vec<(char, &ConfigurationOption)> {
+    QUICK_TOGGLE_KEYS
+        .iter()
+        .copied()
+        .zip(
+            config_option_info
+                .iter()
+                .filter(|option| option.r#type.contains(ConfigurationOptionTypeFlag::Bool)),
+        )
+        .collect()
+}
+
+// The actions listed above the configuration options in the choice menu.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum MenuAction {
+    StartServer,
+    QuickSetup,
+    SaveNow,
+    Undo,
+    ImportServerProperties,
+    SetActiveJar,
+    ChooseJava,
+    EnableWhitelist,
+    DisableWhitelist,
+    EnableRcon,
+    DisableRcon,
+    ConnectionInfo,
+    EditServerProperties,
+    RunMacro,
+    ExportShareable,
+    WorldInfo,
+    RenameWorld,
+    RestoreBackup,
+    DuplicateProfile,
+    CompareProfiles,
+    SwitchInstance,
+    Exit,
+}
+
+impl MenuAction {
+    fn label(&self) -> &'static str {
+        match self {
+            MenuAction::StartServer => "Start server now",
+            MenuAction::QuickSetup => "Quick setup",
+            MenuAction::SaveNow => "Save now",
+            MenuAction::Undo => "Undo last change",
+            MenuAction::ImportServerProperties => "Import from server.properties",
+            MenuAction::SetActiveJar => "Set active jar",
+            MenuAction::ChooseJava => "Choose a Java installation",
+            MenuAction::EnableWhitelist => "Enable whitelist",
+            MenuAction::DisableWhitelist => "Disable whitelist",
+            MenuAction::EnableRcon => "Enable RCON",
+            MenuAction::DisableRcon => "Disable RCON",
+            MenuAction::ConnectionInfo => "How to connect",
+            MenuAction::EditServerProperties => "Edit server.properties",
+            MenuAction::RunMacro => "Run a macro",
+            MenuAction::ExportShareable => "Export sanitized config",
+            MenuAction::WorldInfo => "World info",
+            MenuAction::RenameWorld => "Rename world",
+            MenuAction::RestoreBackup => "Restore from backup",
+            MenuAction::DuplicateProfile => "Duplicate this profile",
+            MenuAction::CompareProfiles => "Compare two profiles",
+            MenuAction::SwitchInstance => "Switch server instance",
+            MenuAction::Exit => "Exit",
+        }
+    }
+}
+
+// The options that "Quick setup" goes through, in order.
+const QUICK_SETUP_OPTIONS: [&str; 4] = ["port", "world", "maxMemory", "gui"];
+
+fn get_menu_actions(machine: &Machine, defer_save: bool, jar_filename: &str) -> Vec<MenuAction> {
+    let mut actions = vec![MenuAction::StartServer, MenuAction::QuickSetup];
+    if defer_save && machine.dirty {
+        actions.push(MenuAction::SaveNow);
+    }
+    if !machine.history.is_empty() {
+        actions.push(MenuAction::Undo);
+    }
+    if Path::new("server.properties").is_file() {
+        actions.push(MenuAction::ImportServerProperties);
+    }
+    if Path::new(jar_filename) != Path::new(ACTIVE_JAR_FILENAME) {
+        actions.push(MenuAction::SetActiveJar);
+    }
+    // Windows doesn't have a standard place for java, so installs are often missing from the PATH or
+    // several versions compete for it.
+    if cfg!(windows) {
+        actions.push(MenuAction::ChooseJava);
+    }
+    if is_whitelist_enabled() {
+        actions.push(MenuAction::DisableWhitelist);
+    } else {
+        actions.push(MenuAction::EnableWhitelist);
+    }
+    let properties = ServerProperties::read(Path::new("server.properties")).unwrap_or_default();
+    if rcon_port(&properties).is_some() {
+        actions.push(MenuAction::DisableRcon);
+    } else {
+        actions.push(MenuAction::EnableRcon);
+    }
+    actions.push(MenuAction::ConnectionInfo);
+    actions.push(MenuAction::EditServerProperties);
+    if !machine.configuration.macros.is_empty() {
+        actions.push(MenuAction::RunMacro);
+    }
+    actions.push(MenuAction::ExportShareable);
+    if resolve_world_path(&machine.configuration).is_dir() {
+        actions.push(MenuAction::WorldInfo);
+        actions.push(MenuAction::RenameWorld);
+    }
+    // A world that's gone missing can still be restored.
+    if !list_backups(&resolve_world_path(&machine.configuration)).is_empty() {
+        actions.push(MenuAction::RestoreBackup);
+    }
+    actions.push(MenuAction::DuplicateProfile);
+    if list_profile_configurations(&machine.configuration_path).len() > 1 {
+        actions.push(MenuAction::CompareProfiles);
+    }
+    actions.push(MenuAction::SwitchInstance);
+    actions.push(MenuAction::Exit);
+    actions
+}
+
+// Copies the settings that have an equivalent option from an existing server.properties, returning
+// the new configuration and the names of the options that were changed.
+fn import_server_properties(
+    configuration: &MinecraftServerConfiguration,
+    properties: &ServerProperties,
+) -> (MinecraftServerConfiguration, Vec<&'static str>) {
+    let mut imported = configuration.clone();
+    let mut changed = Vec::new();
+    if let Some(port) = properties
+        .get("server-port")
+        .and_then(|port| port.trim().parse::<u16>().ok())
+        .filter(|port| *port != 0)
+    {
+        if imported.port != Some(port) {
+            imported.port = Some(port);
+            changed.push("Port");
+        }
+    }
+    if let Some(world) = properties
+        .get("level-name")
+        .map(str::trim)
+        .filter(|world| !world.is_empty())
+    {
+        if imported.world.as_deref() != Some(world) {
+            imported.world = Some(world.to_string());
+            changed.push("World name");
+        }
+    }
+    (imported, changed)
+}
+
+// Persists the configuration unless saving has been deferred until exiting or choosing "Save now".
+// Changes are recorded in the audit log as they're made, even when saving them is deferred.
+fn autosave(machine: &mut Machine, defer_save: bool) -> Result<(), MscError> {
+    write_audit_entries(machine);
+    if !defer_save {
+        save_machine_configuration(machine)?;
+    }
+    Ok(())
+}
+
+// A failure is only reported, since the change has already been made.
+fn write_audit_entries(machine: &mut Machine) {
+    for entry in machine.audit_entries.drain(..) {
+        if let Err(error) = append_audit_entry(
+            Path::new(AUDIT_LOG_FILENAME),
+            &machine.instance,
+            &entry.property,
+            &entry.old,
+            &entry.new,
+        ) {
+            Term::stderr()
+                .write_line(
+                    style(format!(
+                        "Unable to record the change to {} in {}: {}",
+                        entry.property, AUDIT_LOG_FILENAME, error
+                    ))
+                    .yellow()
+                    .to_string()
+                    .as_str(),
+                )
+                .unwrap();
+        }
+    }
+}
+
+fn save_machine_configuration(machine: &mut Machine) -> Result<(), MscError> {
+    machine.configuration = save_configuration(
+        &machine.configuration_path,
+        &machine.instance,
+        machine.configuration.clone(),
+    )?;
+    machine.dirty = false;
+    Ok(())
+}
+
+// What became of the changes that were still waiting to be saved (see --defer-save).
+#[derive(Debug, PartialEq)]
+enum PendingChanges {
+    None,
+    Saved,
+    Discarded,
+}
+
+// Saves the changes that are still waiting to be saved, both right before the server starts and when
+// the menu exits. Once the server has been started, --no-save-on-start says that nothing may be
+// written, which makes this and the editors' autosave the only places that write the configuration.
+fn settle_pending_changes(
+    machine: &mut Machine,
+    no_save_on_start: bool,
+    server_started: bool,
+) -> Result<PendingChanges, MscError> {
+    if !machine.dirty {
+        return Ok(PendingChanges::None);
+    }
+    if no_save_on_start && server_started {
+        return Ok(PendingChanges::Discarded);
+    }
+    save_machine_configuration(machine)?;
+    Ok(PendingChanges::Saved)
+}
+
+// Formats a size as e.g. "512 B", "1.5 MB", or "2.3 GB".
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+// Formats a duration as e.g. "1h 2m 3s", leaving off the leading units that are zero.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h {}m {}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+// Echoes the server's output to the terminal (and the console mirror) while following along with what
+// its log says.
+struct OutputEcho<'a> {
+    terminal: &'a Term,
+    mirror: Option<ConsoleMirror>,
+    prefix: Option<&'a OutputPrefix>,
+    status: LiveStatus,
+    // Run the first time that the server reports it has finished starting, with the port it uses.
+    post_start_hook: Option<(&'a str, u16)>,
+    // Sent to the console the first time that the server reports it has finished starting, since
+    // it ignores what's typed while it boots.
+    startup_commands: Option<&'a [String]>,
+    // When the server was last seen empty, for the idle shutdown. None while someone is online and
+    // until the server has finished starting.
+    empty_since: Option<Arc<Mutex<Option<Instant>>>>,
+    // Only chat and players joining or leaving are shown, while the mirror still gets everything.
+    chat_only: bool,
+    // Set when the server couldn't start because it needs a newer Java.
+    java_mismatch: Option<JavaVersionMismatch>,
+    // The instance's log file, with --log-to-file.
+    log: Option<File>,
+}
+
+fn format_java_mismatch(mismatch: &JavaVersionMismatch) -> String {
+    let current = match mismatch.current {
+        Some(current) => format!("you have Java {}, but it needs", current),
+        None => "it needs".to_string(),
+    };
+    format!(
+        "This server requires a newer Java version: {} Java {}. Install Java {} or newer, or set the Java executable option to one.",
+        current, mismatch.required, mismatch.required
+    )
+}
+
+fn format_chat_message(chat: &ChatMessage) -> String {
+    if chat.sender == "Server" {
+        style(format!("[Server] {}", chat.message))
+            .magenta()
+            .to_string()
+    } else {
+        format!(
+            "{} {}",
+            style(format!("<{}>", chat.sender)).cyan().bold(),
+            chat.message
+        )
+    }
+}
+
+impl OutputEcho<'_> {
+    fn handle_line(&mut self, line: &str) -> Option<LogEvent> {
+        let prefixed_line = self.prefix.map(|prefix| prefix.apply(line));
+        if self.chat_only {
+            match classify_line(line) {
+                Some(LogEvent::Chat(chat)) => self
+                    .terminal
+                    .write_line(format_chat_message(&chat).as_str())
+                    .unwrap(),
+                Some(LogEvent::Player(PlayerEvent::Joined(name))) => self
+                    .terminal
+                    .write_line(style(format!("{} joined", name)).yellow().to_string().as_str())
+                    .unwrap(),
+                Some(LogEvent::Player(PlayerEvent::Left(name))) => self
+                    .terminal
+                    .write_line(style(format!("{} left", name)).yellow().to_string().as_str())
+                    .unwrap(),
+                Some(LogEvent::Ready) => self
+                    .terminal
+                    .write_line(
+                        style("The server is ready. Type to chat as [Server], or start a line with / to run a command.")
+                            .green()
+                            .to_string()
+                            .as_str(),
+                    )
+                    .unwrap(),
+                _ => {}
+            }
+        } else {
+            self.terminal
+                .write_line(prefixed_line.as_deref().unwrap_or(line))
+                .unwrap();
+        }
+        if let Some(log) = self.log.as_mut() {
+            // A full disk shouldn't take the server down with it.
+            let _ = writeln!(log, "{}", line);
+        }
+        if let Some(mirror) = self.mirror.as_mut() {
+            match (&prefixed_line, self.prefix) {
+                (Some(prefixed_line), Some(prefix)) if prefix.mirror => {
+                    mirror.write_line(prefixed_line)
+                }
+                _ => mirror.write_line(line),
+            }
+        }
+        let event = classify_line(line)?;
+        if event == LogEvent::DemoExpired {
+            self.terminal
+                .write_line(
+                    style("The demo has expired: players can no longer place or break blocks, or eat.")
+                        .yellow()
+                        .bold()
+                        .to_string()
+                        .as_str(),
+                )
+                .unwrap();
+        }
+        if let LogEvent::UnsupportedJava(mismatch) = event {
+            self.java_mismatch = Some(mismatch);
+        }
+        if event == LogEvent::Ready {
+            if let Some((hook, port)) = self.post_start_hook.take() {
+                run_post_start_hook(hook, port, self.terminal);
+            }
+            for command in self.startup_commands.take().unwrap_or_default() {
+                send_to_console(command);
+            }
+        }
+        let changed = self.status.apply(&event);
+        if let Some(empty_since) = &self.empty_since {
+            if matches!(event, LogEvent::Ready | LogEvent::Player(_)) {
+                let mut empty_since = empty_since.lock().unwrap();
+                if self.status.players.is_empty() {
+                    empty_since.get_or_insert_with(Instant::now);
+                } else {
+                    *empty_since = None;
+                }
+            }
+        }
+        if changed {
+            let mut line = style(self.status.status_line()).cyan().to_string();
+            if let Some(tps) = &self.status.tps {
+                let summary = style(tps.summary());
+                let summary = match tps.health() {
+                    TickHealth::Good => summary.green(),
+                    TickHealth::Slow => summary.yellow(),
+                    TickHealth::Lagging => summary.red(),
+                };
+                line = format!("{}{} {}", line, style(",").cyan(), summary);
+            }
+            self.terminal.write_line(line.as_str()).unwrap();
+        }
+        Some(event)
+    }
+}
+
+// Starts the hook without waiting for it, so that the server's output keeps flowing. A hook that
+// fails is only reported, since the server itself is fine.
+fn run_post_start_hook(hook: &str, port: u16, terminal: &Term) {
+    let mut child = match hook_command(hook, port).spawn() {
+        Ok(child) => child,
+        Err(error) => {
+            terminal
+                .write_line(
+                    style(format!("Unable to run the post-start hook: {}", error))
+                        .red()
+                        .to_string()
+                        .as_str(),
+                )
+                .unwrap();
+            return;
+        }
+    };
+    std::thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => println!(
+            "{}",
+            style(format!("The post-start hook exited with {}.", status)).red()
+        ),
+        Ok(_) => {}
+        Err(error) => println!(
+            "{}",
+            style(format!("Unable to wait for the post-start hook: {}", error)).red()
+        ),
+    });
+}
+
+// Turns what was typed in the chat view into a console command: "/op Steve" runs "op Steve", and
+// anything else is said to everyone.
+fn chat_input_command(input: &str) -> Option<String> {
+    let input = input.trim();
+    match input.strip_prefix('/') {
+        Some(command) if !command.is_empty() => Some(command.to_string()),
+        Some(_) => None,
+        None if input.is_empty() => None,
+        None => Some(format!("say {}", input)),
+    }
+}
+
+// The console of the server that's running, when we write to it ourselves instead of letting it
+// read the terminal.
+static SERVER_CONSOLE: Mutex<Option<ChildStdin>> = Mutex::new(None);
+
+fn send_to_console(command: &str) {
+    if let Some(stdin) = SERVER_CONSOLE.lock().unwrap().as_mut() {
+        // The server may already be on its way out, in which case there's nothing to send it.
+        let _ = writeln!(stdin, "{}", command).and_then(|_| stdin.flush());
+    }
+}
+
+// The lines that are read until the end, with bytes that aren't UTF-8 replaced instead of ending
+// the reading, since the server's output and what's typed can be in another encoding, e.g. a
+// Windows code page.
+fn lossy_lines<R: BufRead>(mut reader: R) -> impl Iterator<Item = String> {
+    std::iter::from_fn(move || {
+        let mut line = Vec::new();
+        loop {
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) if line.is_empty() => return None,
+                Ok(_) => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => return None,
+            }
+        }
+        if line.ends_with(b"\n") {
+            line.pop();
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+        }
+        Some(String::from_utf8_lossy(&line).into_owned())
+    })
+}
+
+// Whether forward_console_input has taken over the terminal's input.
+static CONSOLE_INPUT_FORWARDED: AtomicBool = AtomicBool::new(false);
+
+// Forwards what's typed to the server's console, turning it into commands first in the chat view.
+// The terminal is read for the rest of the process, so this is only started once even when the
+// server is restarted.
+fn forward_console_input(chat: bool) {
+    if CONSOLE_INPUT_FORWARDED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    std::thread::spawn(move || {
+        for input in lossy_lines(io::stdin().lock()) {
+            let command = if chat {
+                chat_input_command(&input)
+            } else {
+                Some(input)
+            };
+            if let Some(command) = command {
+                send_to_console(&command);
+            }
+        }
+    });
+}
+
+// Whether there's someone to answer a prompt. Once the console's input is forwarded, a prompt would
+// compete with it for what's typed.
+fn can_prompt(terminal: &Term) -> bool {
+    terminal.is_term() && !CONSOLE_INPUT_FORWARDED.load(Ordering::SeqCst)
+}
+
+// Echoes the server's errors as they come, looking out for a JVM that's too old to run the jar,
+// which is only ever reported here.
+fn echo_server_errors(stderr: ChildStderr) -> JoinHandle<Option<JavaVersionMismatch>> {
+    std::thread::spawn(move || {
+        let terminal = Term::stderr();
+        let mut mismatch = None;
+        for line in lossy_lines(BufReader::new(stderr)) {
+            terminal.write_line(&line).unwrap();
+            if let Some(found) = parse_unsupported_class_version(&line) {
+                mismatch = Some(found);
+            }
+        }
+        mismatch
+    })
+}
+
+fn echo_server_output(stdout: ChildStdout, echo: &mut OutputEcho) {
+    for line in lossy_lines(BufReader::new(stdout)) {
+        echo.handle_line(&line);
+    }
+}
+
+// Waits for the server to finish starting and then asks it to stop, killing it if it's still running
+// once the timeout is up. Returns how long the server took to finish starting, if it did in time.
+fn run_smoke_test(
+    child: &mut Child,
+    stdout: ChildStdout,
+    echo: &mut OutputEcho,
+    timeout: Duration,
+) -> io::Result<Option<Duration>> {
+    let started_at = Instant::now();
+    // Lines are read on another thread so that waiting for them can time out.
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in lossy_lines(BufReader::new(stdout)) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let deadline = started_at + timeout;
+    let mut ready = None;
+    loop {
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(line) => {
+                if echo.handle_line(&line) == Some(LogEvent::Ready) && ready.is_none() {
+                    ready = Some(started_at.elapsed());
+                    if let Some(stdin) = child.stdin.as_mut() {
+                        // The server may already be on its way out, in which case there's nothing
+                        // to stop.
+                        let _ = writeln!(stdin, "stop").and_then(|_| stdin.flush());
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                let _ = child.kill();
+                break;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    Ok(ready)
+}
+
+struct HealthReport {
+    address: SocketAddr,
+    status: io::Result<ServerStatus>,
+}
+
+impl Report for HealthReport {
+    fn human(&self) -> Vec<String> {
+        let line = match &self.status {
+            Ok(status) => {
+                let mut message = format!(
+                    "The server on {} responded in {}ms.",
+                    self.address,
+                    status.latency.as_millis()
+                );
+                if let Some(version) = &status.version {
+                    message = format!("{} Version: {}.", message, version);
+                }
+                if let (Some(online), Some(max)) = (status.online_players, status.max_players) {
+                    message = format!("{} Players: {}/{}.", message, online, max);
+                }
+                style(message).green()
+            }
+            Err(error) => style(format!(
+                "The server on {} didn't respond: {}",
+                self.address, error
+            ))
+            .red(),
+        };
+        vec![line.to_string()]
+    }
+
+    fn json(&self) -> Json {
+        let address = Json::string(self.address.to_string());
+        match &self.status {
+            Ok(status) => Json::object(vec![
+                ("address", address),
+                ("responding", Json::Bool(true)),
+                ("latencyMs", Json::Number(status.latency.as_millis() as i64)),
+                (
+                    "version",
+                    Json::optional(status.version.clone(), Json::String),
+                ),
+                (
+                    "onlinePlayers",
+                    Json::optional(status.online_players, |online| Json::Number(online as i64)),
+                ),
+                (
+                    "maxPlayers",
+                    Json::optional(status.max_players, |max| Json::Number(max as i64)),
+                ),
+            ]),
+            Err(error) => Json::object(vec![
+                ("address", address),
+                ("responding", Json::Bool(false)),
+                ("error", Json::string(error.to_string())),
+            ]),
+        }
+    }
+}
+
+// Pings the server on this machine, returning the process exit code: 0 if it responded.
+fn health_check(
+    configuration: &MinecraftServerConfiguration,
+    format: OutputFormat,
+    terminal: &Term,
+) -> i32 {
+    let address = SocketAddr::from(([127, 0, 0, 1], server_port(configuration)));
+    let report = HealthReport {
+        address,
+        status: ping_server(address),
+    };
+    emit(&report, format, terminal);
+    if report.status.is_ok() {
+        0
+    } else {
+        1
+    }
+}
+
+// Connects to the server's RCON, using the port and password in server.properties. `purpose` says
+// what the connection is for in the errors, e.g. "stop the server".
+fn connect_rcon(
+    configuration: &MinecraftServerConfiguration,
+    password_file: Option<&str>,
+    purpose: &str,
+) -> Result<(RconClient, SocketAddr), MscError> {
+    let properties = ServerProperties::read(Path::new("server.properties")).unwrap_or_default();
+    let port = rcon_port(&properties).ok_or_else(|| {
+        MscError::Validation(format!(
+            "RCON isn't enabled, so there's no way to {} from here. Set enable-rcon=true and an rcon.password in server.properties, then restart the server.",
+            purpose
+        ))
+    })?;
+    let password_source = rcon_password_source(
+        password_file,
+        configuration.rconPasswordFile.as_deref(),
+        &properties,
+    )
+    .ok_or_else(|| {
+        MscError::Validation(format!(
+            "There's no RCON password. Set rcon.password in server.properties, and pass it with {} or --rcon-password-file to keep it out of your files.",
+            RCON_PASSWORD_VARIABLE
+        ))
+    })?;
+    let password = resolve_secret(&password_source).map_err(|error| {
+        MscError::Validation(format!("Unable to get the RCON password: {}", error))
+    })?;
+    let address = SocketAddr::from(([127, 0, 0, 1], port));
+    let client = RconClient::connect(address, &password).map_err(|error| {
+        MscError::Validation(format!(
+            "Unable to {} through RCON on {}: {}",
+            purpose, address, error
+        ))
+    })?;
+    Ok((client, address))
+}
+
+// Asks the server to stop through RCON.
+fn stop_server(
+    configuration: &MinecraftServerConfiguration,
+    password_file: Option<&str>,
+    warn_players: bool,
+    terminal: &Term,
+) -> Result<(), MscError> {
+    let (mut client, address) = connect_rcon(configuration, password_file, "stop the server")?;
+    let unreachable = |error: io::Error| {
+        MscError::Validation(format!(
+            "Unable to stop the server through RCON on {}: {}",
+            address, error
+        ))
+    };
+    if warn_players {
+        count_down(&configuration.shutdownWarnings, &mut |message| {
+            client.command(&format!("say {}", message))?;
+            terminal.write_line(format!("Told the players: {}", message).as_str())
+        })
+        .map_err(unreachable)?;
+    }
+    match client.command("stop") {
+        // The server may close the connection as it shuts down instead of answering.
+        Ok(_) => {}
+        Err(error)
+            if matches!(
+                error.kind(),
+                io::ErrorKind::UnexpectedEof | io::ErrorKind::ConnectionReset
+            ) => {}
+        Err(error) => return Err(unreachable(error)),
+    }
+    terminal
+        .write_line(
+            style("The server is stopping.")
+                .green()
+                .to_string()
+                .as_str(),
+        )
+        .unwrap();
+    Ok(())
+}
+
+// How long to wait before each attempt to reconnect to RCON. Together that's about a minute, which
+// is long enough for most servers to restart.
+const RCON_RECONNECT_DELAYS: [u64; 6] = [1, 2, 4, 8, 16, 30];
+
+fn is_disconnect(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+    )
+}
+
+// Connects to RCON again after the connection dropped, waiting longer after every failed attempt.
+fn reconnect_rcon(
+    configuration: &MinecraftServerConfiguration,
+    password_file: Option<&str>,
+    terminal: &Term,
+) -> Option<RconClient> {
+    for (attempt, delay) in RCON_RECONNECT_DELAYS.iter().enumerate() {
+        terminal
+            .write_line(
+                style(format!(
+                    "Trying again in {}s (attempt {} of {}).",
+                    delay,
+                    attempt + 1,
+                    RCON_RECONNECT_DELAYS.len()
+                ))
+                .dim()
+                .to_string()
+                .as_str(),
+            )
+            .unwrap();
+        std::thread::sleep(Duration::from_secs(*delay));
+        if let Ok((client, _)) = connect_rcon(configuration, password_file, "open its console") {
+            return Some(client);
+        }
+    }
+    None
+}
+
+// Gets back to a server that's running in the background: into its screen or tmux session when it was
+// started with --session, or else into a console over RCON.
+fn attach_to_server(
+    configuration: &MinecraftServerConfiguration,
+    profile: Option<&str>,
+    instance: &str,
+    password_file: Option<&str>,
+    terminal: &Term,
+) -> Result<(), MscError> {
+    let session = session_name(&env::current_dir()?, profile, instance);
+    if let Some(multiplexer) = find_session(&session)? {
+        multiplexer.attach(&session)?;
+        return Ok(());
+    }
+    let (mut client, address) = connect_rcon(configuration, password_file, "open its console")
+        .map_err(|error| {
+            MscError::Validation(format!(
+                "There's no screen or tmux session named \"{}\" to attach to, and the server's console can't be reached through RCON either. {}",
+                session, error
+            ))
+        })?;
+    terminal
+        .write_line(
+            style(format!(
+                "Connected to the server's console through RCON on {}. Type a command to run it, or press Ctrl-D to leave.",
+                address
+            ))
+            .green()
+            .to_string()
+            .as_str(),
+        )
+        .unwrap();
+    for command in lossy_lines(io::stdin().lock()) {
+        let command = command.trim().trim_start_matches('/');
+        if command.is_empty() {
+            continue;
+        }
+        // The server closes the connection as it stops, so there's nothing to reconnect to.
+        let stopping = command
+            .split_whitespace()
+            .next()
+            .is_some_and(|name| name.eq_ignore_ascii_case("stop"));
+        match client.command(command) {
+            Ok(response) => {
+                let response = response.trim_end();
+                if !response.is_empty() {
+                    terminal.write_line(response).unwrap();
+                }
+                if stopping {
+                    report_server_stopping(terminal);
+                    return Ok(());
+                }
+            }
+            Err(error) if stopping && is_disconnect(&error) => {
+                report_server_stopping(terminal);
+                return Ok(());
+            }
+            // The connection also ends when the server restarts, e.g. for its daily restart.
+            Err(error) if is_disconnect(&error) => {
+                terminal
+                    .write_line(
+                        style("The server closed the connection, e.g. because it's restarting. Reconnecting, or press Ctrl-C to give up.")
+                            .yellow()
+                            .to_string()
+                            .as_str(),
+                    )
+                    .unwrap();
+                // A server that doesn't come back was stopped some other way, e.g. from its own
+                // console, which ends the console here as well.
+                client = match reconnect_rcon(configuration, password_file, terminal) {
+                    Some(client) => client,
+                    None => {
+                        terminal
+                            .write_line(
+                                style(format!(
+                                    "The server on {} didn't come back within about a minute, so it has likely stopped. Run attach again once it's back up.",
+                                    address
+                                ))
+                                .yellow()
+                                .to_string()
+                                .as_str(),
+                            )
+                            .unwrap();
+                        return Ok(());
+                    }
+                };
+                terminal
+                    .write_line(
+                        style(format!(
+                            "Reconnected. \"{}\" may not have run, so enter it again if it still needs to.",
+                            command
+                        ))
+                        .green()
+                        .to_string()
+                        .as_str(),
+                    )
+                    .unwrap();
+            }
+            Err(error) => {
+                return Err(MscError::Validation(format!(
+                    "Unable to run \"{}\" through RCON on {}: {}",
+                    command, address, error
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+fn report_server_stopping(terminal: &Term) {
+    terminal
+        .write_line(
+            style("The server is stopping, so its console was left.")
+                .green()
+                .to_string()
+                .as_str(),
+        )
+        .unwrap();
+}
+
+// Runs the macro's commands in order through RCON, printing what the server answers to each. The
+// rest are skipped once one fails, since later commands usually depend on the earlier ones.
+fn run_macro(
+    configuration: &MinecraftServerConfiguration,
+    commands: &[String],
+    password_file: Option<&str>,
+    terminal: &Term,
+) -> Result<(), MscError> {
+    let (mut client, address) = connect_rcon(configuration, password_file, "run the macro")?;
+    for command in commands {
+        let command = command.trim().trim_start_matches('/');
+        terminal
+            .write_line(style(format!("> {}", command)).bold().to_string().as_str())
+            .unwrap();
+        let response = client.command(command).map_err(|error| {
+            MscError::Validation(format!(
+                "Unable to run \"{}\" through RCON on {}, so the rest of the macro was skipped: {}",
+                command, address, error
+            ))
+        })?;
+        let response = response.trim_end();
+        if !response.is_empty() {
+            terminal.write_line(response).unwrap();
+        }
+    }
+    Ok(())
+}
+
+// Command-line settings that affect how the server is launched but aren't persisted.
+#[derive(Debug, Default, Clone)]
+struct LaunchOptions {
+    console_fifo: Option<PathBuf>,
+    session: Option<Multiplexer>,
+    skip_port_check: bool,
+    // Removes old backups past the retention without asking first.
+    no_confirm: bool,
+    // Starts the server with --nogui whatever the gui option says.
+    headless: bool,
+    // Passes the arguments to java in a file, which Windows also does for commands that are too long.
+    argfile: bool,
+    // The format of the prefix added to each line of output, when prefixing is on.
+    prefix_format: Option<String>,
+    prefix_mirror: bool,
+    // Stops the server once it has started, failing if that takes longer than this.
+    timeout: Option<Duration>,
+    // Sizes the heap as a percentage of the container's memory limit instead of with -Xmx.
+    max_ram_percentage: Option<u8>,
+    // Shows only the server's chat, and says what's typed instead of passing it to the console.
+    chat: bool,
+    // Appends the server's output to the instance's log in msc-logs.
+    log_to_file: bool,
+    // Asks the router to forward the server's port while it runs.
+    upnp: bool,
+    // Enables assertions and logs garbage collection to gc_log_filename.
+    debug_jvm: bool,
+}
+
+// Forwards the port through the router for as long as the server runs, saying how players outside
+// the network can connect. Failing to is only reported, since players may not need it.
+fn forward_port(port: u16, terminal: &Term) -> Option<RenewedMapping> {
+    terminal
+        .write_line(
+            style(format!("Asking the router to forward port {}...", port))
+                .dim()
+                .to_string()
+                .as_str(),
+        )
+        .unwrap();
+    match try_upnp_map(port) {
+        Ok(mapping) => {
+            let message = match &mapping.external_address {
+                Some(address) => format!(
+                    "Port {} is forwarded, so players outside your network can connect to {}:{}.",
+                    port, address, port
+                ),
+                None => format!("Port {} is forwarded to this machine.", port),
+            };
+            terminal
+                .write_line(style(message).green().to_string().as_str())
+                .unwrap();
+            Some(RenewedMapping::new(mapping))
+        }
+        Err(error) => {
+            terminal
+                .write_line(
+                    style(format!(
+                        "Unable to forward port {} through UPnP ({}). Forward it in your router's settings for players outside your network.",
+                        port, error
+                    ))
+                    .yellow()
+                    .to_string()
+                    .as_str(),
+                )
+                .unwrap();
+            None
+        }
+    }
+}
+
+fn open_instance_log(
+    launch_options: &LaunchOptions,
+    log_directory: &Path,
+    terminal: &Term,
+) -> Option<File> {
+    if !launch_options.log_to_file {
+        return None;
+    }
+    match open_log_file(log_directory) {
+        Ok(file) => {
+            terminal
+                .write_line(
+                    format!(
+                        "Logging the console to {}",
+                        log_directory.join(LOG_FILENAME).display()
+                    )
+                    .as_str(),
+                )
+                .unwrap();
+            Some(file)
+        }
+        Err(error) => {
+            terminal
+                .write_line(
+                    style(format!(
+                        "Unable to log the console to {}: {}",
+                        log_directory.join(LOG_FILENAME).display(),
+                        error
+                    ))
+                    .red()
+                    .to_string()
+                    .as_str(),
+                )
+                .unwrap();
+            None
+        }
+    }
+}
+
+// Points out the crash report that the server wrote while it ran, keeping a copy with the instance's
+// logs.
+fn report_crash(launched_at: SystemTime, log_directory: &Path, terminal: &Term) {
+    let report = match find_crash_report(launched_at) {
+        Some(report) => report,
+        None => return,
+    };
+    let report = keep_crash_report(&report, log_directory).unwrap_or(report);
+    let message = match crash_report_description(&report) {
+        Some(description) => format!(
+            "The server crashed ({}). The crash report is in {}.",
+            description,
+            report.display()
+        ),
+        None => format!("The server wrote a crash report to {}.", report.display()),
+    };
+    terminal
+        .write_line(style(message).red().to_string().as_str())
+        .unwrap();
+}
+
+fn open_console_mirror(launch_options: &LaunchOptions, terminal: &Term) -> Option<ConsoleMirror> {
+    let path = launch_options.console_fifo.as_ref()?;
+    if cfg!(windows) {
+        terminal
+            .write_line(
+                style("Named pipes aren't supported here, the console will be written to a regular file instead.")
+                    .yellow()
+                    .to_string()
+                    .as_str(),
+            )
+            .unwrap();
+    }
+    match ConsoleMirror::create(path) {
+        Ok(mirror) => {
+            terminal
+                .write_line(format!("Mirroring the console to {}", path.display()).as_str())
+                .unwrap();
+            Some(mirror)
+        }
+        Err(error) => {
+            terminal
+                .write_line(
+                    style(format!(
+                        "Unable to mirror the console to {}: {}",
+                        path.display(),
+                        error
+                    ))
+                    .red()
+                    .to_string()
+                    .as_str(),
+                )
+                .unwrap();
+            None
+        }
+    }
+}
+
+// Quotes an argument for `sh`, which runs the command in a session, when it contains anything but
+// the usual characters, e.g. a system property with a path in it. This also makes the command that
+// is shown before launching safe to copy into a shell.
+fn quote_argument(argument: &str) -> String {
+    let is_plain = !argument.is_empty()
+        && argument
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || "-_=.,:/+@%".contains(character));
+    if is_plain {
+        argument.to_string()
+    } else {
+        format!("'{}'", argument.replace('\'', "'\\''"))
+    }
+}
+
+fn format_command(arguments: &[String]) -> String {
+    arguments
+        .iter()
+        .map(|argument| quote_argument(argument))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// The command that's run when Java reads its arguments from ARGFILE_FILENAME.
+struct ArgfileCommand {
+    reason: &'static str,
+    // What's run, which ends with "@msc-java-args.txt".
+    command: Vec<String>,
+    // What's written to the file.
+    java_arguments: Vec<String>,
+}
+
+// Splits the command for --argfile, or when it's too long for Windows, which many system properties
+// can make it. None when the command is run as it is.
+fn argfile_command(
+    configuration: &MinecraftServerConfiguration,
+    arguments: &[String],
+    launch_options: &LaunchOptions,
+) -> Option<ArgfileCommand> {
+    let too_long = cfg!(windows) && format_command(arguments).len() > WINDOWS_COMMAND_LINE_LIMIT;
+    if !launch_options.argfile && !too_long {
+        return None;
+    }
+    let java_index = arguments
+        .iter()
+        .position(|argument| *argument == configured_java(configuration))
+        .unwrap_or(0);
+    let mut command = arguments[..=java_index].to_vec();
+    command.push(format!("@{}", ARGFILE_FILENAME));
+    Some(ArgfileCommand {
+        reason: if too_long {
+            "The command is too long for Windows"
+        } else {
+            "--argfile was passed"
+        },
+        command,
+        java_arguments: arguments[java_index + 1..].to_vec(),
+    })
+}
+
+// What the server is started with, as headings and their numbered arguments. In argfile mode that's
+// the command that's run, then the arguments that Java reads from the file.
+fn launch_argument_sections(
+    configuration: &MinecraftServerConfiguration,
+    arguments: &[String],
+    launch_options: &LaunchOptions,
+) -> Vec<(String, Vec<String>)> {
+    let heading = "The server will be started with these arguments:".to_string();
+    match argfile_command(configuration, arguments, launch_options) {
+        Some(argfile) => vec![
+            (heading, numbered_argument_lines(&argfile.command)),
+            (
+                format!(
+                    "{}, so Java reads these arguments from {}:",
+                    argfile.reason, ARGFILE_FILENAME
+                ),
+                numbered_argument_lines(&argfile.java_arguments),
+            ),
+        ],
+        None => vec![(heading, numbered_argument_lines(arguments))],
+    }
+}
+
+// The arguments one per line and numbered, exactly as the process gets them, without the quoting
+// that format_command adds.
+fn numbered_argument_lines(arguments: &[String]) -> Vec<String> {
+    let width = arguments.len().to_string().len();
+    arguments
+        .iter()
+        .enumerate()
+        .map(|(index, argument)| format!("{:>width$}  {}", index, argument, width = width))
+        .collect()
+}
+
+const JFR_RECORDING_FILENAME: &str = "msc-recording.jfr";
+
+// Where --debug-jvm has Java log garbage collection, next to the server's own logs. Instances can
+// share the server's folder, so each gets its own.
+fn gc_log_filename(instance: &str) -> String {
+    format!("logs/gc-{}.log", instance)
+}
+
+// The properties that stop the server from treating its output as an interactive terminal.
+const PLAIN_OUTPUT_PROPERTIES: [&str; 2] = ["terminal.jline", "terminal.ansi"];
+
+// The server's output is captured when it's written to a log file or our own output is redirected.
+fn is_output_captured(launch_options: &LaunchOptions) -> bool {
+    launch_options.log_to_file || !Term::stdout().is_term()
+}
+
+fn is_headless(launch_options: &LaunchOptions) -> bool {
+    launch_options.headless || !has_display()
+}
+
+// The Java to run the server with. When none can be found, plain "java" is still tried, so that
+// launching fails with the usual advice about installing Java.
+fn configured_java(configuration: &MinecraftServerConfiguration) -> String {
+    resolve_java(configuration.javaPath.as_deref())
+        .map(|java| java.to_string_lossy().to_string())
+        .unwrap_or_else(|| DEFAULT_JAVA.to_string())
+}
+
+// Each argument is passed on as it is, without a shell that could split or unquote it.
+fn server_command(arguments: &[String]) -> Command {
+    let mut command = Command::new(&arguments[0]);
+    command.args(&arguments[1..]);
+    command
+}
+
+// The program and its arguments, one argument per element, so that nothing is ever split or
+// unquoted on the way to the server.
+fn build_command(
+    instance: &str,
+    configuration: &MinecraftServerConfiguration,
+    jar_filename: &str,
+    launch_options: &LaunchOptions,
+) -> Vec<String> {
+    let max_ram_percentage = launch_options.max_ram_percentage;
+    // Resource limits are left out where there's no systemd to enforce them.
+    let mut arguments = if can_run_in_scope() {
+        scope_arguments(
+            configuration.memoryLimit,
+            configuration.cpuQuota,
+            configuration.niceness,
+        )
+    } else {
+        vec![]
+    };
+    arguments.push(configured_java(configuration));
+    // A fixed maximum doesn't know about the container's memory limit, so the JVM is left to size
+    // the heap from the limit instead.
+    if let Some(percentage) = max_ram_percentage {
+        arguments.push("-XX:+UseContainerSupport".to_string());
+        arguments.push(format!("-XX:MaxRAMPercentage={}", percentage));
+    }
+    // Without a maximum, the JVM would only use a quarter of the machine's memory at most. The
+    // suggestion is skipped if it would be less than the initial memory, which Java refuses.
+    let max_memory = match max_ram_percentage {
+        Some(_) => None,
+        None => configuration.maxMemory.or_else(|| {
+            let suggested = suggested_heap();
+            match configuration.minMemory {
+                Some(min_memory) if min_memory > suggested => None,
+                _ => Some(suggested),
+            }
+        }),
+    };
+    if let Some(max_memory) = max_memory {
+        arguments.push(format!("-Xmx{}", max_memory));
+    }
+    if let Some(min_memory) = configuration.minMemory {
+        arguments.push(format!("-Xms{}", min_memory));
+    }
+    for (key, value) in &configuration.systemProperties {
+        arguments.push(format!("-D{}={}", key, value));
+    }
+    if is_output_captured(launch_options) && configuration.plainLogOutput {
+        for key in PLAIN_OUTPUT_PROPERTIES {
+            if !configuration
+                .systemProperties
+                .iter()
+                .any(|(configured, _)| configured == key)
+            {
+                arguments.push(format!("-D{}=false", key));
+            }
+        }
+    }
+    if configuration.jfrProfile {
+        // The server's own profile only covers its startup, so also record the whole run to a
+        // file we can point the user at afterwards.
+        arguments.push(format!(
+            "-XX:StartFlightRecording=dumponexit=true,filename={}",
+            JFR_RECORDING_FILENAME
+        ));
+    }
+    if launch_options.debug_jvm {
+        arguments.push("-ea".to_string());
+        arguments.push("-verbose:gc".to_string());
+        arguments.push(format!("-Xlog:gc*:file={}", gc_log_filename(instance)));
+    }
+    arguments.push("-jar".to_string());
+    arguments.push(jar_filename.to_string());
+
+    for option in get_config_option_info() {
+        // These are reported by validate_configuration before launching.
+        if !configuration.serverType.supports(&option.property) {
+            continue;
+        }
+        let value = configuration.get(option.property.clone());
+        let cli_flag = match (option.property.clone().as_str(), value) {
+            // These are handled by us rather than the server.
+            ("javaPath", _)
+            | ("backupBeforeStart", _)
+            | ("plainLogOutput", _)
+            | ("checkRegionFiles", _)
+            | ("auditLog", _)
+            | ("backupRetention", _)
+            | ("preStartHook", _)
+            | ("postStartHook", _)
+            | ("rconPasswordFile", _)
+            | ("dailyRestartTime", _)
+            | ("dailyRestartWarningMinutes", _)
+            | ("idleShutdownMinutes", _)
+            | ("cpuQuota", _)
+            | ("niceness", _) => vec![],
+            // The GUI can't open without a display, and Java fails to start trying to.
+            ("gui", ConfigurationOptionType::Bool(value)) => {
+                if value && !is_headless(launch_options) {
+                    vec![]
+                } else {
+                    vec!["--nogui".to_string()]
+                }
+            }
+            (property, ConfigurationOptionType::Bool(value)) => {
+                if value {
+                    vec![format!("--{}", property)]
+                } else {
+                    vec![]
+                }
+            }
+            (property, ConfigurationOptionType::OptionU16(value)) => match value {
+                Some(value) => vec![format!("--{}", property), value.to_string()],
+                None => vec![],
+            },
+            (property, ConfigurationOptionType::OptionString(value)) => match value {
+                Some(value) => vec![format!("--{}", property), value],
+                None => vec![],
+            },
+            // Memory and system properties are given to the JVM before the jar, not to the server,
+            // and the server type only decides which flags are passed.
+            (_, ConfigurationOptionType::OptionMemory(_))
+            | (_, ConfigurationOptionType::KeyValues(_))
+            | (_, ConfigurationOptionType::ServerType(_))
+            | (_, ConfigurationOptionType::Choice(_)) => vec![],
+        };
+
+        arguments.extend(cli_flag);
+    }
+    arguments
+}
+
+fn back_up(
+    configuration: &MinecraftServerConfiguration,
+    world_path: &Path,
+    ask_before_pruning: bool,
+    terminal: &Term,
+) -> Result<(), MscError> {
+    // Large worlds take a while to copy, so the progress is redrawn in place, at most every 100ms.
+    let spinner = ['|', '/', '-', '\\'];
+    let mut frame = 0;
+    let mut last_drawn: Option<Instant> = None;
+    let mut copied_bytes = 0;
+    let mut draw_progress = |progress: &BackupProgress| {
+        copied_bytes = progress.copied_bytes;
+        if !terminal.is_term()
+            || last_drawn.is_some_and(|drawn| drawn.elapsed() < Duration::from_millis(100))
+        {
+            return;
+        }
+        last_drawn = Some(Instant::now());
+        let line = match progress.total_bytes {
+            Some(total) => format!(
+                "Backing up the world: {} of {} ({}%), {} files",
+                format_bytes(progress.copied_bytes),
+                format_bytes(total),
+                (progress.copied_bytes * 100)
+                    .checked_div(total)
+                    .unwrap_or(100),
+                progress.copied_files
+            ),
+            None => {
+                frame = (frame + 1) % spinner.len();
+                format!(
+                    "Backing up the world {} {}, {} files",
+                    spinner[frame],
+                    format_bytes(progress.copied_bytes),
+                    progress.copied_files
+                )
+            }
+        };
+        let _ = terminal
+            .clear_line()
+            .and_then(|_| terminal.write_str(&line));
+    };
+    let backup = back_up_world(world_path, &mut draw_progress);
+    if terminal.is_term() {
+        terminal.clear_line().unwrap();
+    }
+    let backup = backup.map_err(|error| {
+        MscError::Io(io::Error::new(
+            error.kind(),
+            format!("Unable to back up {}: {}", world_path.display(), error),
+        ))
+    })?;
+    terminal
+        .write_line(
+            format!(
+                "Backed up the world ({}) to {}.",
+                format_bytes(copied_bytes),
+                backup.display()
+            )
+            .as_str(),
+        )
+        .unwrap();
+
+    if let (Some(keep), Some(directory)) = (configuration.backupRetention, backup.parent()) {
+        // A retention that's set too low would otherwise quietly delete backups someone still wanted.
+        let mut confirm = |backups: &[PathBuf]| {
+            terminal
+                .write_line(
+                    format!(
+                        "Keeping the newest {} backup{} removes {}:",
+                        keep,
+                        if keep == 1 { "" } else { "s" },
+                        if backups.len() == 1 {
+                            "this one"
+                        } else {
+                            "these"
+                        }
+                    )
+                    .as_str(),
+                )
+                .unwrap();
+            for backup in backups {
+                terminal
+                    .write_line(format!("  {}", backup.display()).as_str())
+                    .unwrap();
+            }
+            if !ask_before_pruning || !can_prompt(terminal) {
+                return true;
+            }
+            let remove = dialoguer::Confirm::with_theme(theme())
+                .with_prompt(if backups.len() == 1 {
+                    "Remove it?"
+                } else {
+                    "Remove them?"
+                })
+                .default(false)
+                .interact_on(terminal)
+                .unwrap();
+            if !remove {
+                terminal
+                    .write_line(
+                        style("Kept the old backups. Raise the backup retention to keep them for good.")
+                            .dim()
+                            .to_string()
+                            .as_str(),
+                    )
+                    .unwrap();
+            }
+            remove
+        };
+        let removed = prune_backups(directory, keep, &mut confirm).map_err(|error| {
+            MscError::Io(io::Error::new(
+                error.kind(),
+                format!(
+                    "Unable to remove old backups from {}: {}",
+                    directory.display(),
+                    error
+                ),
+            ))
+        })?;
+        if removed > 0 {
+            terminal
+                .write_line(
+                    format!(
+                        "Removed {} old backup{}, keeping the newest {}.",
+                        removed,
+                        if removed == 1 { "" } else { "s" },
+                        keep
+                    )
+                    .as_str(),
+                )
+                .unwrap();
+        }
+    }
+    Ok(())
+}
+
+// Runs the server until it stops, launching it again after each daily restart. The configuration is
+// only read, never saved, so that launching doesn't touch the configuration file.
+fn run_server(
+    instance: &str,
+    configuration: &MinecraftServerConfiguration,
+    jar_filename: &str,
+    launch_options: &LaunchOptions,
+    profile: Option<&str>,
+    terminal: &Term,
+) -> Result<(), MscError> {
+    let mut launch = Launch::First;
+    while run_server_once(
+        instance,
+        configuration,
+        jar_filename,
+        launch_options,
+        launch,
+        profile,
+        terminal,
+    )?
+    .restarting
+    {
+        launch = Launch::Restart;
+        terminal
+            .write_line(
+                style("Starting the server again for its daily restart.")
+                    .cyan()
+                    .to_string()
+                    .as_str(),
+            )
+            .unwrap();
+    }
+    Ok(())
+}
+
+// How long --benchmark waits for each run to finish starting when --timeout isn't given.
+const DEFAULT_BENCHMARK_TIMEOUT: Duration = Duration::from_secs(600);
+
+struct BenchmarkReport {
+    startup_times: Vec<Duration>,
+    // The world was generated during the first run, which makes that one slower than the rest.
+    generated_world: bool,
+}
+
+fn format_seconds(duration: Duration) -> String {
+    format!("{:.2}s", duration.as_secs_f64())
+}
+
+impl BenchmarkReport {
+    // The shortest, mean, and longest startup times.
+    fn summary(&self) -> Option<(Duration, Duration, Duration)> {
+        let min = *self.startup_times.iter().min()?;
+        let max = *self.startup_times.iter().max()?;
+        let mean = self.startup_times.iter().sum::<Duration>() / self.startup_times.len() as u32;
+        Some((min, mean, max))
+    }
+}
+
+impl Report for BenchmarkReport {
+    fn human(&self) -> Vec<String> {
+        let runs = self.startup_times.len();
+        let mut lines = vec![style(format!(
+            "Startup time over {} run{}:",
+            runs,
+            if runs == 1 { "" } else { "s" }
+        ))
+        .bold()
+        .to_string()];
+        for (index, time) in self.startup_times.iter().enumerate() {
+            lines.push(format!(
+                "  {:<8}{:>8}",
+                format!("Run {}", index + 1),
+                format_seconds(*time)
+            ));
+        }
+        if let Some((min, mean, max)) = self.summary() {
+            for &(label, time) in &[("Min", min), ("Mean", mean), ("Max", max)] {
+                lines.push(
+                    style(format!("  {:<8}{:>8}", label, format_seconds(time)))
+                        .bold()
+                        .to_string(),
+                );
+            }
+        }
+        if self.generated_world && runs > 1 {
+            lines.push(
+                style(
+                    "The world was generated during run 1, which makes it slower than the others.",
+                )
+                .dim()
+                .to_string(),
+            );
+        }
+        lines
+    }
+
+    fn json(&self) -> Json {
+        let milliseconds = |time: Duration| Json::Number(time.as_millis() as i64);
+        let summary = self.summary();
+        Json::object(vec![
+            (
+                "startupTimesMs",
+                Json::Array(
+                    self.startup_times
+                        .iter()
+                        .copied()
+                        .map(milliseconds)
+                        .collect(),
+                ),
+            ),
+            (
+                "minMs",
+                Json::optional(summary.map(|(min, _, _)| min), milliseconds),
+            ),
+            (
+                "meanMs",
+                Json::optional(summary.map(|(_, mean, _)| mean), milliseconds),
+            ),
+            (
+                "maxMs",
+                Json::optional(summary.map(|(_, _, max)| max), milliseconds),
+            ),
+            ("generatedWorld", Json::Bool(self.generated_world)),
+        ])
+    }
+}
+
+// Starts the server `runs` times, stopping it as soon as it has finished starting each time, and
+// times how long that took. A run that doesn't finish starting stops the benchmark.
+fn run_benchmark(
+    instance: &str,
+    configuration: &MinecraftServerConfiguration,
+    jar_filename: &str,
+    launch_options: &LaunchOptions,
+    profile: Option<&str>,
+    runs: u32,
+    terminal: &Term,
+) -> Result<BenchmarkReport, MscError> {
+    let launch_options = LaunchOptions {
+        timeout: Some(launch_options.timeout.unwrap_or(DEFAULT_BENCHMARK_TIMEOUT)),
+        ..launch_options.clone()
+    };
+    let generated_world = !resolve_world_path(configuration).is_dir();
+    let mut startup_times = Vec::new();
+    for run in 1..=runs {
+        terminal
+            .write_line(
+                style(format!("Benchmark run {} of {}", run, runs))
+                    .cyan()
+                    .to_string()
+                    .as_str(),
+            )
+            .unwrap();
+        let outcome = run_server_once(
+            instance,
+            configuration,
+            jar_filename,
+            &launch_options,
+            Launch::Benchmark,
+            profile,
+            terminal,
+        )?;
+        startup_times.push(outcome.startup_time.ok_or_else(|| {
+            MscError::Validation(format!("The server's startup wasn't timed in run {}.", run))
+        })?);
+    }
+    Ok(BenchmarkReport {
+        startup_times,
+        generated_world,
+    })
+}
+
+// The options that a server in a --session goes without, since its output isn't read and nothing is
+// written to its console then.
+fn session_diagnostics(
+    configuration: &MinecraftServerConfiguration,
+    launch_options: &LaunchOptions,
+) -> Vec<Diagnostic> {
+    if launch_options.session.is_none() {
+        return Vec::new();
+    }
+    let mut ignored = Vec::new();
+    if configuration.dailyRestartTime.is_some() {
+        ignored.push("dailyRestartTime");
+    }
+    if !configuration.startupCommands.is_empty() {
+        ignored.push("startupCommands");
+    }
+    if configuration.idleShutdownMinutes.is_some() {
+        ignored.push("idleShutdownMinutes");
+    }
+    if configuration.postStartHook.is_some() {
+        ignored.push("postStartHook");
+    }
+    if ignored.is_empty() {
+        return Vec::new();
+    }
+    vec![Diagnostic {
+        severity: Severity::Warning,
+        message: format!(
+            "{} {} ignored with --session, since the server's console is left to the session.",
+            ignored.join(", "),
+            if ignored.len() == 1 { "is" } else { "are" }
+        ),
+    }]
+}
+
+// Why the server is being launched, which decides whether anyone is around to ask.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Launch {
+    // Started by the user.
+    First,
+    // Started again for its daily restart, which happens unattended.
+    Restart,
+    // Started by --benchmark, which times the server's startup alone, so the hooks and the backup
+    // are left out, and nobody is asked anything.
+    Benchmark,
+}
+
+// How a run of the server ended.
+#[derive(Debug, Default)]
+struct RunOutcome {
+    // The server stopped for its daily restart, so it's launched again.
+    restarting: bool,
+    // How long the server took to finish starting, when it was stopped as soon as it had.
+    startup_time: Option<Duration>,
+}
+
+fn report_session_attached(session: &str, terminal: &Term) {
+    terminal
+        .write_line(
+            format!(
+                "The \"{}\" session was already running, so it was attached to instead of starting another server.",
+                session
+            )
+            .as_str(),
+        )
+        .unwrap();
+}
+
+fn run_server_once(
+    instance: &str,
+    configuration: &MinecraftServerConfiguration,
+    jar_filename: &str,
+    launch_options: &LaunchOptions,
+    launch: Launch,
+    profile: Option<&str>,
+    terminal: &Term,
+) -> Result<RunOutcome, MscError> {
+    let log_directory = &instance_log_directory(profile, instance);
+    let session = match launch_options.session {
+        Some(multiplexer) => Some((
+            multiplexer,
+            session_name(&env::current_dir()?, profile, instance),
+        )),
+        None => None,
+    };
+    // A server that's already running in its session is only attached to, so nothing that prepares
+    // a launch is done, e.g. the pre-start hook or the backup.
+    if let Some((multiplexer, session)) = &session {
+        if attach_if_running(*multiplexer, session)? {
+            report_session_attached(session, terminal);
+            return Ok(RunOutcome::default());
+        }
+    }
+    let may_prompt = launch == Launch::First && can_prompt(terminal);
+    // A server that's already running in the session is attached to, so its port is expected to be
+    // taken.
+    let check_port = !launch_options.skip_port_check && launch_options.session.is_none();
+    let mut diagnostics = validate_configuration(configuration, check_port);
+    diagnostics.extend(session_diagnostics(configuration, launch_options));
+    for diagnostic in &diagnostics {
+        terminal
+            .write_line(format_diagnostic(diagnostic).as_str())
+            .unwrap();
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(MscError::Validation(format!(
+            "The server wasn't started because of {} problem{} with its configuration.",
+            errors,
+            if errors == 1 { "" } else { "s" }
+        )));
+    }
+
+    // A typo in the world's name would otherwise quietly generate a new world next to the real one.
+    if let Some(missing) = find_missing_world(configuration) {
+        if may_prompt
+            && !dialoguer::Confirm::with_theme(theme())
+                .with_prompt(format!("Create a new world named \"{}\"?", missing.name))
+                .default(false)
+                .interact_on(terminal)
+                .unwrap()
+        {
+            return Err(MscError::Validation(
+                "The server wasn't started. Set \"world\" to an existing world to use it."
+                    .to_string(),
+            ));
+        }
+    }
+
+    if let Some(hook) = configuration
+        .preStartHook
+        .as_ref()
+        .filter(|_| launch != Launch::Benchmark)
+    {
+        let status = hook_command(hook, server_port(configuration))
+            .status()
+            .map_err(|error| {
+                MscError::Io(io::Error::new(
+                    error.kind(),
+                    format!("Unable to run the pre-start hook: {}", error),
+                ))
+            })?;
+        if !status.success() {
+            return Err(MscError::Validation(format!(
+                "The server wasn't started because the pre-start hook exited with {}.",
+                status
+            )));
+        }
+    }
+
+    let arguments = build_command(instance, configuration, jar_filename, launch_options);
+    if configuration.gui && configuration.serverType.supports("gui") && is_headless(launch_options)
+    {
+        let reason = if launch_options.headless {
+            "--headless was passed"
+        } else {
+            "there's no display to open it on"
+        };
+        terminal
+            .write_line(
+                style(format!(
+                    "The GUI is enabled, but {}, so the server is started with --nogui.",
+                    reason
+                ))
+                .yellow()
+                .to_string()
+                .as_str(),
+            )
+            .unwrap();
+    }
+    if launch_options.max_ram_percentage.is_none() && is_running_in_container() {
+        terminal
+            .write_line(
+                style("This looks like a container. Pass --container so that Java sizes its memory from the container's memory limit.")
+                    .dim()
+                    .to_string()
+                    .as_str(),
+            )
+            .unwrap();
+    }
+    if launch_options.debug_jvm {
+        // Java refuses to start when the log's folder is missing, which it is before the first run.
+        let gc_log = PathBuf::from(gc_log_filename(instance));
+        if let Some(directory) = gc_log.parent() {
+            fs::create_dir_all(directory).map_err(|error| {
+                MscError::Io(io::Error::new(
+                    error.kind(),
+                    format!("Unable to create {}: {}", directory.display(), error),
+                ))
+            })?;
+        }
+        terminal
+            .write_line(
+                style(format!(
+                    "Assertions are enabled, and garbage collection is logged to {}.",
+                    std::env::current_dir()
+                        .map(|directory| directory.join(&gc_log))
+                        .unwrap_or_else(|_| gc_log.to_path_buf())
+                        .display()
+                ))
+                .dim()
+                .to_string()
+                .as_str(),
+            )
+            .unwrap();
+    }
+    let arguments = match argfile_command(configuration, &arguments, launch_options) {
+        Some(argfile) => {
+            write_argfile(Path::new(ARGFILE_FILENAME), &argfile.java_arguments).map_err(
+                |error| {
+                    MscError::Io(io::Error::new(
+                        error.kind(),
+                        format!("Unable to write to {}: {}", ARGFILE_FILENAME, error),
+                    ))
+                },
+            )?;
+            terminal
+                .write_line(
+                    style(format!(
+                        "{}, so Java reads its arguments from {}.",
+                        argfile.reason, ARGFILE_FILENAME
+                    ))
+                    .dim()
+                    .to_string()
+                    .as_str(),
+                )
+                .unwrap();
+            argfile.command
+        }
+        None => arguments,
+    };
+    let command_string = format_command(&arguments);
+
+    // So that it's clear which version is launched when several jars are around.
+    let banner = match read_jar_version(Path::new(jar_filename)) {
+        Ok(version) => format!("Starting your Minecraft {} Server", version),
+        Err(_) => "Starting your Minecraft Server".to_string(),
+    };
+    terminal
+        .write_line(style(banner).green().to_string().as_str())
+        .unwrap();
+    terminal.write_line(command_string.as_str()).unwrap();
+    let world_path = resolve_world_path(configuration);
+    let world_description = if world_path.is_dir() {
+        "The world is stored in"
+    } else {
+        "A new world will be created in"
+    };
+    terminal
+        .write_line(
+            style(format!(
+                "{} {}",
+                world_description,
+                fs::canonicalize(&world_path)
+                    .or_else(|_| std::env::current_dir().map(|directory| directory
+                        .join(world_path.strip_prefix(".").unwrap_or(&world_path))))
+                    .unwrap_or_else(|_| world_path.clone())
+                    .display()
+            ))
+            .dim()
+            .to_string()
+            .as_str(),
+        )
+        .unwrap();
+    for line in add_on_lines(&world_path) {
+        terminal
+            .write_line(style(line).dim().to_string().as_str())
+            .unwrap();
+    }
+    if configuration.demo {
+        for line in DEMO_BANNER.lines() {
+            terminal
+                .write_line(style(line).yellow().bold().to_string().as_str())
+                .unwrap();
+        }
+    }
+
+    if configuration.backupBeforeStart && world_path.is_dir() && launch != Launch::Benchmark {
+        back_up(
+            configuration,
+            &world_path,
+            !launch_options.no_confirm && may_prompt,
+            terminal,
+        )?;
+    }
+
+    if let Some((multiplexer, session)) = session {
+        match run_in_session(multiplexer, &session, &command_string)? {
+            SessionOutcome::Started => terminal
+                .write_line(
+                    format!(
+                        "The server is running in the \"{}\" session. Attach to it with: {}",
+                        session,
+                        multiplexer.attach_command(&session)
+                    )
+                    .as_str(),
+                )
+                .unwrap(),
+            // Started in the meantime, e.g. by another msc.
+            SessionOutcome::Attached => report_session_attached(&session, terminal),
+        }
+        if launch_options.upnp {
+            terminal
+                .write_line(
+                    style("The port isn't forwarded with --upnp in a session, since it couldn't be closed again when the server stops.")
+                        .yellow()
+                        .to_string()
+                        .as_str(),
+                )
+                .unwrap();
+        }
+        return Ok(RunOutcome::default());
+    }
+
+    let port_mapping = if launch_options.upnp {
+        forward_port(server_port(configuration), terminal)
+    } else {
+        None
+    };
+
+    // A smoke test stops the server itself.
+    let daily_restart = configuration
+        .dailyRestartTime
+        .as_deref()
+        .and_then(parse_time_of_day)
+        .filter(|_| launch_options.timeout.is_none());
+    let idle_shutdown = configuration
+        .idleShutdownMinutes
+        .filter(|_| launch_options.timeout.is_none())
+        .map(|minutes| Duration::from_secs(minutes as u64 * 60));
+    let mut command = server_command(&arguments);
+    // A smoke test types "stop" itself, so the server doesn't get the terminal's input, and the chat
+    // view, daily restarts, and startup commands write to the console themselves.
+    let writes_to_console = launch_options.chat
+        || daily_restart.is_some()
+        || idle_shutdown.is_some()
+        || !configuration.startupCommands.is_empty();
+    if launch_options.timeout.is_some() || writes_to_console {
+        command.stdin(Stdio::piped());
+    } else {
+        command.stdin(Stdio::inherit());
+    }
+    command.stdout(Stdio::piped());
+    command.stderr(Stdio::piped());
+
+    let properties_before =
+        ServerProperties::read(Path::new("server.properties")).unwrap_or_default();
+    let prefix = launch_options
+        .prefix_format
+        .as_ref()
+        .map(|format| OutputPrefix {
+            format: format.clone(),
+            instance: instance.to_string(),
+            mirror: launch_options.prefix_mirror,
+        });
+    let started_at = Instant::now();
+    let launched_at = SystemTime::now();
+    let recording_modified = || {
+        fs::metadata(JFR_RECORDING_FILENAME)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    };
+    let recording_before = recording_modified();
+    let exit_status = command.spawn().and_then(|mut child| {
+        let stdout = child
+            .stdout
+            .take()
+            .expect("Expected the server's stdout to be piped.");
+        let errors = echo_server_errors(
+            child
+                .stderr
+                .take()
+                .expect("Expected the server's stderr to be piped."),
+        );
+        let mut echo = OutputEcho {
+            terminal,
+            mirror: open_console_mirror(launch_options, terminal),
+            prefix: prefix.as_ref(),
+            status: LiveStatus::default(),
+            post_start_hook: configuration
+                .postStartHook
+                .as_deref()
+                .filter(|_| launch != Launch::Benchmark)
+                .map(|hook| (hook, server_port(configuration))),
+            startup_commands: Some(configuration.startupCommands.as_slice()),
+            empty_since: idle_shutdown.map(|_| Arc::new(Mutex::new(None))),
+            chat_only: launch_options.chat,
+            java_mismatch: None,
+            log: open_instance_log(launch_options, log_directory, terminal),
+        };
+        let server_exited = Arc::new(AtomicBool::new(false));
+        let mut restarting = None;
+        if launch_options.timeout.is_none() && writes_to_console {
+            *SERVER_CONSOLE.lock().unwrap() = child.stdin.take();
+            forward_console_input(launch_options.chat);
+        }
+        if let Some(restart_at) = daily_restart {
+            restarting = Some(schedule_daily_restart(
+                restart_at,
+                daily_restart_warnings(configuration),
+                send_to_console,
+                server_exited.clone(),
+            ));
+        }
+        let idle_stopped = match (idle_shutdown, &echo.empty_since) {
+            (Some(idle_for), Some(empty_since)) => Some(schedule_idle_shutdown(
+                idle_for,
+                empty_since.clone(),
+                send_to_console,
+                server_exited.clone(),
+            )),
+            _ => None,
+        };
+        let ready = match launch_options.timeout {
+            Some(timeout) => Some(run_smoke_test(&mut child, stdout, &mut echo, timeout)?),
+            None => {
+                echo_server_output(stdout, &mut echo);
+                None
+            }
+        };
+        let status = child.wait();
+        server_exited.store(true, Ordering::SeqCst);
+        *SERVER_CONSOLE.lock().unwrap() = None;
+        let restarting = restarting.is_some_and(|restarting| restarting.load(Ordering::SeqCst));
+        let idle_stopped = idle_stopped.is_some_and(|stopped| stopped.load(Ordering::SeqCst));
+        let java_mismatch = echo.java_mismatch.or(errors.join().ok().flatten());
+        status.map(|status| (status, ready, restarting, idle_stopped, java_mismatch))
+    });
+    // The port would stay open to this machine with nothing behind it.
+    if let Some(mapping) = port_mapping {
+        let port = mapping.external_port();
+        if let Err(error) = mapping.remove() {
+            terminal
+                .write_line(
+                    style(format!(
+                        "Unable to close port {} on the router: {}",
+                        port, error
+                    ))
+                    .yellow()
+                    .to_string()
+                    .as_str(),
+                )
+                .unwrap();
+        }
+    }
+    let (status, ready, restarting, idle_stopped, java_mismatch) =
+        exit_status.map_err(MscError::Launch)?;
+    let uptime = format_duration(started_at.elapsed());
+    if idle_stopped {
+        terminal
+            .write_line(
+                style(format!(
+                    "Nobody was online for {}, so the server was stopped.",
+                    format_duration(idle_shutdown.unwrap())
+                ))
+                .dim()
+                .to_string()
+                .as_str(),
+            )
+            .unwrap();
+    }
+    match status.code() {
+        Some(0) => {
+            terminal.write_line("It was a success!").unwrap();
+            terminal
+                .write_line(format!("The server stopped cleanly after {}.", uptime).as_str())
+                .unwrap();
+        }
+        Some(code) => {
+            match java_mismatch {
+                Some(mismatch) => terminal
+                    .write_line(
+                        style(format_java_mismatch(&mismatch))
+                            .red()
+                            .to_string()
+                            .as_str(),
+                    )
+                    .unwrap(),
+                None => terminal
+                    .write_line("Something went wrong! Do you have Java installed?")
+                    .unwrap(),
+            }
+            terminal
+                .write_line(
+                    format!("The server exited with code {} after {}.", code, uptime).as_str(),
+                )
+                .unwrap();
+        }
+        // On Unix-based systems there is no exit code when the process was killed by a signal.
+        None => {
+            terminal
+                .write_line("Something went wrong! The server was terminated by a signal.")
+                .unwrap();
+            terminal
+                .write_line(format!("The server ran for {}.", uptime).as_str())
+                .unwrap();
+        }
+    }
+    if !status.success() {
+        report_crash(launched_at, log_directory, terminal);
+    }
+    if ready == Some(None) {
+        return Err(MscError::Validation(format!(
+            "The server didn't finish starting within {}.",
+            format_duration(launch_options.timeout.unwrap())
+        )));
+    }
+    report_properties_changes(&properties_before, terminal);
+    // A recording left over from an earlier run doesn't count.
+    let recording_after = recording_modified();
+    if configuration.jfrProfile && recording_after.is_some() && recording_after != recording_before
+    {
+        let recording = fs::canonicalize(JFR_RECORDING_FILENAME)
+            .unwrap_or_else(|_| PathBuf::from(JFR_RECORDING_FILENAME));
+        terminal
+            .write_line(
+                format!(
+                    "The flight recording was saved to {}. The server's own profile is in the debug folder.",
+                    recording.display()
+                )
+                .as_str(),
+            )
+            .unwrap();
+    }
+    Ok(RunOutcome {
+        restarting,
+        startup_time: ready.flatten(),
+    })
+}
+
+// Applies --set's "key=value" assignments to server.properties, all at once so that a mistake in
+// any of them leaves the file as it was.
+fn set_tunable_properties(assignments: Vec<&str>, terminal: &Term) -> Result<(), MscError> {
+    let path = Path::new("server.properties");
+    let mut properties = ServerProperties::read_or_default(path).map_err(|error| {
+        MscError::Io(io::Error::new(
+            error.kind(),
+            format!("Unable to read server.properties: {}", error),
+        ))
+    })?;
+    let mut changed = Vec::new();
+    for assignment in assignments {
+        let (key, value) = assignment.split_once('=').ok_or_else(|| {
+            MscError::Validation(format!(
+                "\"{}\" isn't a property and a value. Pass them as --set view-distance=12.",
+                assignment
+            ))
+        })?;
+        let (key, value) = (key.trim(), value.trim());
+        if let Some(option) = get_property_option_info()
+            .into_iter()
+            .find(|option| option.property == key)
+        {
+            // Choices are only accepted by their exact names, since older numbers are harder to read.
+            if let Some(validator) = &option.validator {
+                validator
+                    .check(value)
+                    .map_err(|message| MscError::Validation(format!("{}: {}", key, message)))?;
+            }
+            properties.set(key, value);
+            changed.push((key, value));
+        } else {
+            let keys: Vec<&str> = TUNABLE_PROPERTIES
+                .iter()
+                .map(|property| property.key)
+                .chain(CHOICE_PROPERTIES.iter().map(|property| property.key))
+                .collect();
+            return Err(MscError::Validation(format!(
+                "{} can't be changed with --set. Choose one of: {}.",
+                key,
+                keys.join(", ")
+            )));
+        }
+    }
+    properties.write(path).map_err(|error| {
+        MscError::Io(io::Error::new(
+            error.kind(),
+            format!("Unable to write to server.properties: {}", error),
+        ))
+    })?;
+    for (key, value) in changed {
+        terminal
+            .write_line(format!("Set {} to {} in server.properties.", key, value).as_str())
+            .unwrap();
+    }
+    Ok(())
+}
+
+// Lists what the server added to or changed in server.properties while it ran, e.g. after
+// initSettings or after upgrading to a version with new settings.
+fn report_properties_changes(before: &ServerProperties, terminal: &Term) {
+    let after = match ServerProperties::read(Path::new("server.properties")) {
+        Ok(after) => after,
+        Err(_) => return,
+    };
+    let changes = diff_properties(before, &after);
+    if changes.is_empty() {
+        return;
+    }
+
+    terminal
+        .write_line(
+            style("The server changed server.properties:")
+                .bold()
+                .to_string()
+                .as_str(),
+        )
+        .unwrap();
+    for change in changes {
+        terminal
+            .write_line(describe_property_change(change).as_str())
+            .unwrap();
+    }
+}
+
+// The terminal may be recorded or shared, e.g. in a screenshot, so secrets like rcon.password only
+// show that they changed.
+fn describe_property_change(change: PropertyChange) -> String {
+    let line = match change {
+        PropertyChange::Added { key, value } => style(format!(
+            "  + {}={}",
+            key,
+            redact_property_value(&key, &value)
+        ))
+        .green(),
+        PropertyChange::Changed { key, old, new } => style(format!(
+            "  ~ {}: {} → {}",
+            key,
+            redact_property_value(&key, &old),
+            redact_property_value(&key, &new)
+        ))
+        .yellow(),
+        PropertyChange::Removed { key, value } => style(format!(
+            "  - {}={}",
+            key,
+            redact_property_value(&key, &value)
+        ))
+        .red(),
+    };
+    line.to_string()
+}
+
+// run returns before exiting, so that what it holds is dropped first, e.g. the configuration's lock.
+fn main() {
+    match run() {
+        Ok(code) => std::process::exit(code),
+        Err(error) => {
+            eprintln!("{}", style(error).red());
+            std::process::exit(1);
+        }
+    }
+}
+
+// The flags that do something else instead of opening the menu, e.g. launching the server or writing
+// a report. They work in the current directory rather than asking which server to use.
+const NON_MENU_FLAGS: [&str; 14] = [
+    "start",
+    "timeout",
+    "chat",
+    "benchmark",
+    "check",
+    "init_only",
+    "set",
+    "edit",
+    "debug_state",
+    "export_systemd_unit",
+    "health_check",
+    "validate",
+    "list_options",
+    "repair_config",
+];
+
+fn shows_menu(subcommand: &str, matches: &ArgMatches) -> bool {
+    subcommand == "edit" && !NON_MENU_FLAGS.iter().any(|flag| matches.is_present(flag))
+}
+
+// For everything that connects to the server's RCON: stop, attach, and the menu's macros and restores.
+fn rcon_password_file_argument<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("rcon_password_file")
+		.long("rcon-password-file")
+		.value_name("FILE")
+		.help("Reads the RCON password from this file. Otherwise, it comes from the RCON_PASSWORD environment variable, the rconPasswordFile option, or server.properties.")
+		.takes_value(true)
+}
+
+// The options that say which server and configuration to use, which every subcommand needs.
+fn location_arguments<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+		Arg::with_name("working_dir")
+			.long("working-dir")
+			.short("C")
+			.value_name("DIRECTORY")
+			.help("The relative or absolute path to the directory containing your Minecraft server. Takes precedence over the server_directory argument.")
+			.takes_value(true),
+		Arg::with_name("config")
+			.long("config")
+			.value_name("FILE")
+			.help("The configuration file to use. Defaults to the first msc-configuration.ron found in the current directory or its parents, or the one in the server's directory.")
+			.takes_value(true),
+		Arg::with_name("profile")
+			.long("profile")
+			.value_name("NAME")
+			.help("Uses the msc-<NAME>.ron profile next to msc-configuration.ron instead, e.g. one created with \"Duplicate this profile\".")
+			.takes_value(true)
+			.conflicts_with("config")
+			.validator(|name| if is_valid_profile_name(&name) {
+				Ok(())
+			} else {
+				Err("Profile names can only contain letters, numbers, \"-\", and \"_\".".to_string())
+			}),
+		Arg::with_name("config_dir")
+			.long("config-dir")
+			.value_name("DIR")
+			.help("Keeps msc-configuration.ron and the profiles in this directory instead of the server's directory, so the world folder only holds the server's files. Each server gets its own folder in it, named after the server's directory. Without a directory, the platform's configuration directory is used (e.g. ~/.config/minecraft-server-cli).")
+			.takes_value(true)
+			.min_values(0)
+			.max_values(1),
+		Arg::with_name("instance")
+			.long("instance")
+			.value_name("NAME")
+			.help("Which of the server instances in msc-configuration.ron to use, skipping the instance selection.")
+			.takes_value(true),
+		Arg::with_name("output_format")
+			.long("output-format")
+			.value_name("FORMAT")
+			.help("How --check, --health-check, --validate, --list-options, --benchmark, and the status subcommand print their results: styled text, or a line of JSON for scripts.")
+			.possible_values(&OutputFormat::NAMES)
+			.default_value("human")
+			.takes_value(true),
+	]
+}
+
+// The options of the commands that start the server or open the menu. Without a subcommand, the jar
+// is required as before, so that existing invocations keep working.
+fn add_server_arguments<'a, 'b>(app: App<'a, 'b>, jar_required: bool) -> App<'a, 'b> {
+    let mut jar_filename = Arg::with_name("jar_filename")
+		.index(1)
+		.help("The name you saved your Minecraft server .jar file under. This can also be a quoted pattern like \"server-*.jar\" to use the most recently modified match.")
+		.takes_value(true);
+    if jar_required {
+        jar_filename = jar_filename.required_unless("print_default_config");
+    }
+    app.arg(jar_filename)
+		.args(&location_arguments())
+		.arg(Arg::with_name("server_directory")
+			.index(2)
+			.help("The relative or absolute path to the directory containing your Minecraft server. For the menu, it defaults to %AppData%\\.minecraft\\server\\ on Windows and ~/.minecraft/server/ on Unix-based systems, and otherwise to the current directory. Prefer --working-dir, which takes precedence over this.")
+		).arg(Arg::with_name("defer_save")
+			.long("defer-save")
+			.help("Keeps your edits in memory and only saves them when you exit or choose \"Save now\" instead of after every change.")
+		).arg(Arg::with_name("no_save_on_start")
+			.long("no-save-on-start")
+			.help("Never writes the configuration file as part of starting the server, e.g. for read-only deployments. Changes deferred with --defer-save are then only written by \"Save now\".")
+		).arg(Arg::with_name("console_fifo")
+			.long("console-fifo")
+			.value_name("PATH")
+			.help("Mirrors the server's console to a named pipe (FIFO) at this path, creating it if needed, so other tools can read it. On Windows, it's written to a regular file instead.")
+			.takes_value(true)
+		).arg(Arg::with_name("prefix_output")
+			.long("prefix-output")
+			.help("Prefixes each line of the server's output with the time and the instance's name.")
+		).arg(Arg::with_name("prefix_format")
+			.long("prefix-format")
+			.value_name("FORMAT")
+			.help("The prefix used by --prefix-output, where {date}, {time}, and {instance} are filled in. Implies --prefix-output. [default: \"[{time}] [{instance}] \"]")
+			.takes_value(true)
+		).arg(Arg::with_name("prefix_mirror")
+			.long("prefix-mirror")
+			.help("Also prefixes the lines written to --console-fifo, which otherwise gets the server's output unchanged.")
+		).arg(Arg::with_name("session")
+			.long("session")
+			.value_name("MULTIPLEXER")
+			.possible_values(&["screen", "tmux"])
+			.help("Runs the server in a detached screen or tmux session named after the instance (e.g. msc-default) so it keeps running after you disconnect. Attaches to the session instead if it's already running.")
+			.takes_value(true)
+		).arg(Arg::with_name("skip_port_check")
+			.long("skip-port-check")
+			.help("Launches the server even if its port seems to be in use, for setups where another program shares it.")
+		).arg(Arg::with_name("upnp")
+			.long("upnp")
+			.help("Asks the router to forward the server's port through UPnP while the server runs, so that players outside your network can connect without setting up port forwarding.")
+		).arg(rcon_password_file_argument())
+		.arg(Arg::with_name("debug_state")
+			.long("debug-state")
+			.help("Writes every transition of the menu's state machine to stderr, e.g. 2> msc-state.log, for debugging the menu.")
+		).arg(Arg::with_name("debug_jvm")
+			.long("debug-jvm")
+			.help("Starts Java with assertions enabled (-ea) and logs its garbage collection to logs/gc-<instance>.log (-verbose:gc -Xlog:gc*), for diagnosing memory problems. Needs Java 9 or newer.")
+		).arg(Arg::with_name("argfile")
+			.long("argfile")
+			.help("Passes the arguments to Java in msc-java-args.txt (as java @msc-java-args.txt) instead of on the command line, which needs Java 9 or newer. Done anyway on Windows when the command is too long for it.")
+		).arg(Arg::with_name("headless")
+			.long("headless")
+			.help("Starts the server without its GUI even when the gui option is on. This happens anyway when there's no display, e.g. over SSH on Linux.")
+		).arg(Arg::with_name("no_confirm")
+			.long("no-confirm")
+			.help("Removes old backups past the backup retention without asking first.")
+		).arg(Arg::with_name("timeout")
+			.long("timeout")
+			.value_name("SECONDS")
+			.help("Checks that the server boots: starts it without the menu, stops it as soon as it's done starting, and exits with 0, or with 1 if it didn't finish starting within this many seconds. Useful in CI.")
+			.takes_value(true)
+			.validator(|seconds| match seconds.parse::<u64>() {
+				Ok(seconds) if seconds > 0 => Ok(()),
+				_ => Err("The timeout must be a whole number of seconds greater than 0.".to_string()),
+			})
+		).arg(Arg::with_name("benchmark")
+			.long("benchmark")
+			.value_name("RUNS")
+			.help("Measures how long the server takes to start: starts it this many times, stopping it as soon as it's done starting, then shows the shortest, mean, and longest startup times. Each run fails after --timeout seconds, or 10 minutes without it. The hooks and the backup are skipped, and the server's output goes to stderr so that only the results are printed to stdout.")
+			.takes_value(true)
+			.conflicts_with("session")
+			.validator(|runs| match runs.parse::<u32>() {
+				Ok(runs) if runs > 0 => Ok(()),
+				_ => Err("The number of runs must be a whole number greater than 0.".to_string()),
+			})
+		).arg(Arg::with_name("chat")
+			.long("chat")
+			.help("Starts the server without the menu and shows only its chat. What you type is said to everyone as [Server], and lines starting with / are run as commands, e.g. /stop.")
+			.conflicts_with_all(&["timeout", "session"])
+		).arg(Arg::with_name("log_to_file")
+			.long("log-to-file")
+			.help("Appends the server's output to msc-logs/latest.log. Other profiles and instances get their own folder in msc-logs, e.g. msc-logs/instances/creative/latest.log.")
+		).arg(Arg::with_name("container")
+			.long("container")
+			.value_name("PERCENT")
+			.help("Sizes the server's memory for a container with a memory limit: instead of a fixed maximum (-Xmx), the heap may use this percentage of the container's memory (75 by default).")
+			.takes_value(true)
+			.min_values(0)
+			.max_values(1)
+			.validator(|percentage| match percentage.parse::<u8>() {
+				Ok(percentage) if (1..=100).contains(&percentage) => Ok(()),
+				_ => Err("The percentage must be a whole number from 1 to 100.".to_string()),
+			})
+		).arg(Arg::with_name("export_systemd_unit")
+			.long("export-systemd-unit")
+			.value_name("PATH")
+			.help("Prints a systemd unit that runs the server as a service with --start --accept-eula, then exits. With a path, the unit is written there instead, e.g. /etc/systemd/system/minecraft.service.")
+			.takes_value(true)
+			.min_values(0)
+			.max_values(1)
+		).arg(Arg::with_name("health_check")
+			.long("health-check")
+			.help("Checks whether a Minecraft server is responding on this machine's configured port, then exits with 0 if it is and 1 if it isn't.")
+		).arg(Arg::with_name("validate")
+			.long("validate")
+			.help("Checks the configuration for problems without starting the server, then exits with 0 if there are no errors and 1 if there are.")
+		).arg(Arg::with_name("check")
+			.long("check")
+			.help("Validates the configuration, makes sure the jar, Java, and the EULA are ready, and prints the command that would start the server, without starting it. Exits with 0 only if everything passed, e.g. as a pre-flight check in CI.")
+		).arg(Arg::with_name("list_options")
+			.long("list-options")
+			.help("Prints every option with its current value, then exits.")
+		).arg(Arg::with_name("edit")
+			.long("edit")
+			.value_name("OPTION")
+			.help("Opens the editor for one option instead of the menu, e.g. --edit port, and exits once it's saved or cancelled. The options are listed by --list-options.")
+			.takes_value(true)
+			.conflicts_with("start")
+		).arg(Arg::with_name("repair_config")
+			.long("repair-config")
+			.help("Rewrites the configuration file in its canonical form, keeping its values: unknown keys are removed, missing ones get their defaults, and it's pretty-printed. Reports what changed, then exits.")
+		).arg(Arg::with_name("print_default_config")
+			.long("print-default-config")
+			.help("Prints a configuration file with every option set to its default and described in a comment, then exits. Redirect it into msc-configuration.ron to start from it.")
+		).arg(Arg::with_name("init_only")
+			.long("init-only")
+			.help("Runs the server once with --initSettings to create server.properties and eula.txt, then exits without changing your saved configuration.")
+		).arg(Arg::with_name("accept_eula")
+			.long("accept-eula")
+			.help("Accepts the Minecraft EULA (https://aka.ms/MinecraftEULA) by writing eula=true to eula.txt before launching.")
+		).arg(Arg::with_name("read_eula")
+			.long("read-eula")
+			.help("Shows the Minecraft EULA and only accepts it once you've scrolled to its end and agreed. The text is downloaded the first time and cached after that.")
+			.conflicts_with("accept_eula")
+		).arg(Arg::with_name("set")
+			.long("set")
+			.value_name("PROPERTY=VALUE")
+			.multiple(true)
+			.number_of_values(1)
+			.help("Changes view-distance or simulation-distance (3 to 32 chunks), gamemode, or difficulty in server.properties, e.g. --set view-distance=12 or --set difficulty=hard. Can be passed more than once.")
+			.takes_value(true)
+		).arg(Arg::with_name("start")
+			.long("start")
+			.help("Starts the server right away without showing the menu. Fails if the EULA hasn't been accepted.")
+		).arg(Arg::with_name("theme")
+			.long("theme")
+			.value_name("THEME")
+			.possible_values(&["plain", "colorful"])
+			.help("How the prompts look. Overrides the theme in msc-configuration.ron.")
+			.takes_value(true)
+		)
+}
+
+// Returns the exit code, e.g. 1 when --validate found errors.
+fn run() -> Result<i32, MscError> {
+    let app = add_server_arguments(App::new("minecraft-server-cli"), true)
+		.version("0.1.0")
+		.author("Andria Brown <andria_girl@pm.me>")
+		.about("A command-line interface used to edit and persist your Minecraft server command-line settings and start your Minecraft server.")
+		.setting(AppSettings::SubcommandsNegateReqs)
+		.subcommand(add_server_arguments(
+			SubCommand::with_name("start")
+				.about("Starts the server right away without showing the menu, like --start."),
+			true,
+		))
+		.subcommand(add_server_arguments(
+			SubCommand::with_name("edit")
+				.about("Opens the menu, which is also what happens without a subcommand."),
+			true,
+		))
+		.subcommand(SubCommand::with_name("stop")
+			.about("Stops the running server through RCON, which needs enable-rcon=true and an rcon.password in server.properties.")
+			.args(&location_arguments())
+			.arg(rcon_password_file_argument())
+			.arg(Arg::with_name("now")
+				.long("now")
+				.help("Stops the server right away instead of counting down through the shutdownWarnings first.")
+			)
+		)
+		.subcommand(SubCommand::with_name("attach")
+			.about("Reconnects to a server running in the background: attaches to its screen or tmux session from --session, or else opens its console through RCON.")
+			.args(&location_arguments())
+			.arg(rcon_password_file_argument())
+		)
+		.subcommand(SubCommand::with_name("status")
+			.about("Checks whether the server is responding, then exits with 0 if it is and 1 if it isn't, like --health-check.")
+			.args(&location_arguments())
+		);
+    let all_matches = app.get_matches();
+    let (subcommand, matches) = match all_matches.subcommand() {
+        (name, Some(matches)) => (name, matches),
+        _ => ("edit", &all_matches),
+    };
+    // conflicts_with only covers the --start flag, not the subcommand.
+    if subcommand == "start" && matches.is_present("edit") {
+        clap::Error::with_description(
+            &format!(
+                "The argument '--edit <OPTION>' cannot be used with the start subcommand\n\n{}\n\nFor more information try --help",
+                matches.usage()
+            ),
+            clap::ErrorKind::ArgumentConflict,
+        )
+        .exit();
+    }
+
+    if matches.is_present("print_default_config") {
+        print!("{}", default_configuration_template());
+        return Ok(0);
+    }
+
+    let jar_pattern = matches.value_of("jar_filename").map(str::to_string);
+    let output_format = matches
+        .value_of("output_format")
+        .and_then(OutputFormat::from_name)
+        .unwrap_or(OutputFormat::Human);
+    let launch_directory =
+        std::env::current_dir().expect("Expected to be able to read the current directory.");
+    let explicit_directory = matches
+        .value_of("working_dir")
+        .or_else(|| matches.value_of("server_directory"));
+    let configuration_directory = match matches.value_of("config_dir") {
+        Some(directory) => Some(launch_directory.join(normalize_directory(directory))),
+        None if matches.is_present("config_dir") => {
+            Some(get_default_configuration_directory().ok_or_else(|| {
+                MscError::Validation(
+                    "Unable to find your configuration directory. Please pass one to --config-dir."
+                        .to_string(),
+                )
+            })?)
+        }
+        None => None,
+    };
+    let discovered_configuration = match (
+        matches.value_of("config"),
+        &configuration_directory,
+        explicit_directory,
+    ) {
+        (None, None, None) => find_configuration_upwards(&launch_directory),
+        _ => None,
+    };
+    let server_directory = match (&discovered_configuration, explicit_directory) {
+        (Some(path), _) => path.parent().unwrap().to_path_buf(),
+        (None, Some(directory)) => normalize_directory(directory),
+        // Flags like --start run unattended, so they use where they were run from.
+        (None, None) if !shows_menu(subcommand, matches) => launch_directory.clone(),
+        // Without anything pointing at a server, the one from last time is suggested.
+        (None, None) => match get_default_configuration_directory()
+            .as_deref()
+            .and_then(last_server_directory)
+        {
+            Some(last) if Term::stdout().is_term() => {
+                let directory: String = dialoguer::Input::with_theme(theme())
+                    .with_prompt("Server directory")
+                    .default(last.display().to_string())
+                    .interact_text()
+                    .unwrap();
+                normalize_directory(directory.trim())
+            }
+            _ => normalize_directory(&get_default_configuration_path()),
+        },
+    };
+    set_current_dir(server_directory).expect(
+        "Expected to be able to change the current directory to your Minecraft Server's directory.",
+    );
+    let configuration_path = match (
+        matches.value_of("config"),
+        configuration_directory,
+        discovered_configuration,
+    ) {
+        (Some(path), _, _) => launch_directory.join(normalize_directory(path)),
+        (None, Some(directory), _) => {
+            let server_directory = env::current_dir()
+                .expect("Expected to be able to read your Minecraft Server's directory.");
+            let directory = server_configuration_directory(&directory, &server_directory);
+            fs::create_dir_all(&directory).map_err(|source| MscError::ConfigWrite {
+                path: directory.clone(),
+                source,
+            })?;
+            directory.join(CONFIGURATION_FILENAME)
+        }
+        (None, None, Some(path)) => path,
+        (None, None, None) => PathBuf::from(CONFIGURATION_FILENAME),
+    };
+    let configuration_path = match matches.value_of("profile") {
+        Some(name) => configuration_path.with_file_name(profile_filename(name)),
+        None => configuration_path,
+    };
+    // Only a convenience for the next run, so failing to remember the directory isn't an error.
+    if let (Some(state_directory), Ok(directory)) = (
+        get_default_configuration_directory(),
+        std::env::current_dir(),
+    ) {
+        let _ = remember_server_directory(&state_directory, &directory);
+    }
+
+    // Prompts need a terminal to read the answers from. When the output is piped, e.g. by a CI runner,
+    // only the flags that don't ask anything can be used.
+    let interactive = Term::stdout().is_term();
+    // Kept until the tool exits, at which point the lock is released.
+    // Checking on a running server mustn't complain that the running server holds the lock.
+    let mut _configuration_lock = if ["status", "stop", "attach"].contains(&subcommand) {
+        None
+    } else {
+        acquire_configuration_lock(&configuration_path)
+    };
+    if matches.is_present("repair_config") {
+        repair_configuration(&configuration_path, &Term::stdout())?;
+        return Ok(0);
+    }
+    let mut instances = get_instances(&configuration_path, interactive)?;
+    let (instance, configuration) = match matches.value_of("instance") {
+        Some(name) => match instances.remove(name) {
+            Some(configuration) => (name.to_string(), configuration),
+            None => {
+                let names: Vec<String> = instances.keys().cloned().collect();
+                return Err(MscError::Validation(format!(
+                    "There is no instance named \"{}\". The available instances are: {}.",
+                    name,
+                    names.join(", ")
+                )));
+            }
+        },
+        None if instances.len() > 1 && !interactive => {
+            let names: Vec<String> = instances.keys().cloned().collect();
+            return Err(MscError::Validation(format!(
+                "There are several instances, so choose one with --instance. The available instances are: {}.",
+                names.join(", ")
+            )));
+        }
+        None if instances.len() > 1 => {
+            match select_instance(&configuration_path, instances, &Term::stdout())? {
+                Some(selected) => selected,
+                None => return Ok(0),
+            }
+        }
+        None => instances.into_iter().next().unwrap(),
+    };
+    if subcommand == "status" {
+        return Ok(health_check(&configuration, output_format, &Term::stdout()));
+    }
+    if subcommand == "stop" {
+        stop_server(
+            &configuration,
+            matches.value_of("rcon_password_file"),
+            !matches.is_present("now"),
+            &Term::stdout(),
+        )?;
+        return Ok(0);
+    }
+    if subcommand == "attach" {
+        attach_to_server(
+            &configuration,
+            profile_name(&configuration_path).as_deref(),
+            &instance,
+            matches.value_of("rcon_password_file"),
+            &Term::stdout(),
+        )?;
+        return Ok(0);
+    }
+
+    let jar_pattern = jar_pattern.expect("Expected clap to require the jar's filename.");
+    // --check reports a pattern that matches nothing along with everything else.
+    let (jar_filename, jar_error) = match resolve_jar(&jar_pattern) {
+        Ok(jar) => (jar.to_string_lossy().to_string(), None),
+        Err(error) if matches.is_present("check") => (jar_pattern.clone(), Some(error)),
+        Err(error) => return Err(error.into()),
+    };
+    if jar_filename != jar_pattern {
+        println!(
+            "Using {}, the newest jar matching \"{}\".",
+            jar_filename, jar_pattern
+        );
+    }
+
+    let mut prompt_theme = configuration.theme.clone().unwrap_or_default();
+    if let Some(name) = matches.value_of("theme") {
+        prompt_theme.colorful = name == "colorful";
+    }
+    set_theme(&prompt_theme);
+
+    let mut machine = Machine {
+        state: AppState::ChoiceMenu,
         editor_state: None,
         selected_configuration_option: None,
-        configuration: get_configuration(),
+        configuration,
+        instance,
+        configuration_path,
+        dirty: false,
+        history: Vec::new(),
+        trace_transitions: matches.is_present("debug_state"),
+        audit_entries: Vec::new(),
+    };
+    let defer_save = matches.is_present("defer_save");
+    let no_save_on_start = matches.is_present("no_save_on_start");
+    let launch_options = LaunchOptions {
+        console_fifo: matches.value_of("console_fifo").map(PathBuf::from),
+        session: matches.value_of("session").and_then(Multiplexer::from_name),
+        skip_port_check: matches.is_present("skip_port_check"),
+        no_confirm: matches.is_present("no_confirm"),
+        headless: matches.is_present("headless"),
+        argfile: matches.is_present("argfile"),
+        upnp: matches.is_present("upnp"),
+        debug_jvm: matches.is_present("debug_jvm"),
+        prefix_format: match matches.value_of("prefix_format") {
+            Some(format) => Some(format.to_string()),
+            None if matches.is_present("prefix_output") => Some(DEFAULT_PREFIX_FORMAT.to_string()),
+            None => None,
+        },
+        prefix_mirror: matches.is_present("prefix_mirror"),
+        timeout: matches
+            .value_of("timeout")
+            .map(|seconds| Duration::from_secs(seconds.parse().unwrap())),
+        max_ram_percentage: if matches.is_present("container") {
+            Some(
+                matches
+                    .value_of("container")
+                    .map(|percentage| percentage.parse().unwrap())
+                    .unwrap_or(DEFAULT_MAX_RAM_PERCENTAGE),
+            )
+        } else {
+            None
+        },
+        chat: matches.is_present("chat"),
+        log_to_file: matches.is_present("log_to_file"),
     };
     let config_option_info = get_config_option_info();
-    let mut terminal = Term::stdout();
+    let terminal = Term::stdout();
+    let mut server_started = false;
+
+    if matches.is_present("export_systemd_unit") {
+        let working_directory = std::env::current_dir()?;
+        let configuration_path = working_directory.join(&machine.configuration_path);
+        let mut exec_start = vec![
+            std::env::current_exe()?.to_string_lossy().to_string(),
+            jar_pattern,
+            "--working-dir".to_string(),
+            working_directory.to_string_lossy().to_string(),
+            "--config".to_string(),
+            configuration_path.to_string_lossy().to_string(),
+            "--instance".to_string(),
+            machine.instance.clone(),
+            "--start".to_string(),
+            "--accept-eula".to_string(),
+        ];
+        if let Some(percentage) = launch_options.max_ram_percentage {
+            exec_start.push(format!("--container={}", percentage));
+        }
+        // The JVM needs memory beyond its heap, e.g. for its code cache and threads.
+        let memory_max = machine.configuration.memoryLimit.or_else(|| {
+            machine
+                .configuration
+                .maxMemory
+                .unwrap_or_else(suggested_heap)
+                .megabytes
+                .checked_add(1024)
+                .map(|megabytes| MemorySize { megabytes })
+        });
+        let unit = systemd_unit(
+            &machine.instance,
+            &working_directory,
+            &exec_start,
+            memory_max,
+            machine.configuration.cpuQuota,
+            machine.configuration.niceness,
+        );
+        match matches.value_of("export_systemd_unit") {
+            Some(path) => {
+                let path = launch_directory.join(path);
+                fs::write(&path, unit).map_err(|source| {
+                    MscError::Io(io::Error::new(
+                        source.kind(),
+                        format!("Unable to write to {}: {}", path.display(), source),
+                    ))
+                })?;
+                println!(
+                    "Wrote the unit to {}. Enable it with: systemctl enable --now {}",
+                    path.display(),
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                );
+            }
+            None => print!("{}", unit),
+        }
+        return Ok(0);
+    }
+
+    if matches.is_present("health_check") {
+        return Ok(health_check(
+            &machine.configuration,
+            output_format,
+            &terminal,
+        ));
+    }
+
+    if matches.is_present("validate") {
+        let report = ValidationReport {
+            diagnostics: validate_configuration(
+                &machine.configuration,
+                !matches.is_present("skip_port_check"),
+            ),
+        };
+        emit(&report, output_format, &terminal);
+        return Ok(if report.has_errors() { 1 } else { 0 });
+    }
+
+    if matches.is_present("list_options") {
+        emit(
+            &OptionsReport {
+                rows: option_rows(&config_option_info, &machine.configuration),
+            },
+            output_format,
+            &terminal,
+        );
+        return Ok(0);
+    }
+
+    if matches.is_present("read_eula") {
+        if !interactive {
+            return Err(MscError::Validation(
+                "Reading the EULA needs an interactive terminal. Read it at the link below and pass --accept-eula instead.".to_string(),
+            ));
+        }
+        let cache = get_default_configuration_directory()
+            .unwrap_or_else(std::env::temp_dir)
+            .join(EULA_CACHE_FILENAME);
+        let text = fetch_eula_text(&cache).map_err(|error| {
+            MscError::Validation(format!(
+                "{} You can read the EULA at {} and pass --accept-eula instead.",
+                error, EULA_URL
+            ))
+        })?;
+        let finished = page_text(&terminal, &text)?;
+        let agreed = finished
+            && dialoguer::Confirm::with_theme(theme())
+                .with_prompt("Do you agree to the Minecraft EULA?")
+                .default(false)
+                .interact_on(&terminal)
+                .unwrap();
+        if !agreed {
+            return Err(MscError::Validation(
+                "The EULA wasn't accepted, so the server can't be started.".to_string(),
+            ));
+        }
+    }
+
+    if matches.is_present("accept_eula") || matches.is_present("read_eula") {
+        accept_eula().map_err(|error| {
+            MscError::Io(io::Error::new(
+                error.kind(),
+                format!("Unable to write to {}: {}", EULA_FILENAME, error),
+            ))
+        })?;
+        terminal
+            .write_line(
+                format!(
+                    "Recorded your acceptance of the Minecraft EULA ({}) in {}.",
+                    EULA_URL, EULA_FILENAME
+                )
+                .as_str(),
+            )
+            .unwrap();
+    }
+
+    if let Some(assignments) = matches.values_of("set") {
+        set_tunable_properties(assignments.collect(), &terminal)?;
+    }
+
+    // The editor returns to the menu once it's done, which is where --edit exits instead.
+    let edit_only = match matches.value_of("edit") {
+        Some(property) => {
+            let option = config_option_info
+                .iter()
+                .find(|option| option.property == property)
+                .cloned()
+                .ok_or_else(|| {
+                    MscError::Validation(format!(
+                        "\"{}\" isn't an option. Pass --list-options to see them all.",
+                        property
+                    ))
+                })?;
+            machine.dispatch(
+                Event::AppEvent(AppEvent::SelectedOption),
+                Some(Payload::ConfigurationOption(option)),
+            );
+            true
+        }
+        None => false,
+    };
+
+    if matches.is_present("check") {
+        let report = check_readiness(
+            &machine.instance,
+            &machine.configuration,
+            &jar_filename,
+            jar_error.as_ref(),
+            &launch_options,
+        );
+        emit(&report, output_format, &terminal);
+        return Ok(if report.is_ready() { 0 } else { 1 });
+    }
+
+    if matches.is_present("init_only") {
+        // The server writes server.properties and eula.txt and then quits. This is never persisted so
+        // that the next regular launch starts the server normally.
+        let mut configuration = machine.configuration.clone();
+        configuration.initSettings = true;
+        // The files need to exist by the time this returns, so the server isn't detached here.
+        let launch_options = LaunchOptions {
+            session: None,
+            ..launch_options
+        };
+        run_server(
+            &machine.instance,
+            &configuration,
+            &jar_filename,
+            &launch_options,
+            profile_name(&machine.configuration_path).as_deref(),
+            &terminal,
+        )?;
+        return Ok(0);
+    }
+
+    if subcommand == "start"
+        || matches.is_present("start")
+        || matches.is_present("timeout")
+        || matches.is_present("chat")
+        || matches.is_present("benchmark")
+    {
+        // The server would refuse to boot, and nobody is around to notice its message.
+        if !is_eula_accepted() {
+            return Err(MscError::Validation(format!(
+                "The Minecraft EULA ({}) hasn't been accepted in {}. Pass --accept-eula to accept it, or --read-eula to read it first.",
+                EULA_URL, EULA_FILENAME
+            )));
+        }
+        if let Some(runs) = matches.value_of("benchmark") {
+            // The server's output goes to stderr, so that stdout only has the results, e.g. for
+            // --output-format json.
+            let report = run_benchmark(
+                &machine.instance,
+                &machine.configuration,
+                &jar_filename,
+                &launch_options,
+                profile_name(&machine.configuration_path).as_deref(),
+                runs.parse().unwrap(),
+                &Term::stderr(),
+            )?;
+            emit(&report, output_format, &terminal);
+            return Ok(0);
+        }
+        run_server(
+            &machine.instance,
+            &machine.configuration,
+            &jar_filename,
+            &launch_options,
+            profile_name(&machine.configuration_path).as_deref(),
+            &terminal,
+        )?;
+        return Ok(0);
+    }
+
+    if !interactive {
+        // Accepting the EULA or changing server.properties was all that was asked for.
+        if matches.is_present("accept_eula") || matches.is_present("set") {
+            return Ok(0);
+        }
+        if edit_only {
+            return Err(MscError::Validation(
+                "--edit needs an interactive terminal, but the output isn't one.".to_string(),
+            ));
+        }
+        return Err(MscError::Validation(
+            "The menu needs an interactive terminal, but the output isn't one. Pass --start to launch the server with your saved settings, or use --list-options, --init-only, --accept-eula, or --health-check.".to_string(),
+        ));
+    }
+
+    let mut java = configured_java(&machine.configuration);
+    let mut java_available = is_java_available(&java);
+    let mut disable_init_settings_after_run = false;
+    let mut disable_erase_cache_after_run = false;
+    // The options that "Quick setup" has yet to go through, and how many it started with.
+    let mut quick_setup: Option<(Vec<ConfigurationOption>, usize)> = None;
 
     while machine.state != AppState::Exited {
         terminal
             .clear_screen()
             .expect("Expected to be able to clear the terminal.");
 
-        match machine.state.clone() {
+        match machine.state {
             AppState::ChoiceMenu => {
+                if edit_only {
+                    machine.dispatch(Event::AppEvent(AppEvent::Exit), None);
+                    continue;
+                }
+                // Quick setup goes straight from one editor to the next, and saves once it's done.
+                if let Some((steps, _)) = quick_setup.as_mut() {
+                    if !steps.is_empty() {
+                        let option = steps.remove(0);
+                        machine.dispatch(
+                            Event::AppEvent(AppEvent::SelectedOption),
+                            Some(Payload::ConfigurationOption(option)),
+                        );
+                        continue;
+                    }
+                    quick_setup = None;
+                    if machine.dirty {
+                        autosave(&mut machine, defer_save)?;
+                    }
+                }
                 terminal.set_title("Minecraft Server CLI — Choice Menu");
-                let select_options =
-                    get_names(config_option_info.clone(), machine.configuration.clone());
-                let result = dialoguer::Select::new()
-                    .with_prompt("Please select the value you wish to change")
-                    .items(&select_options)
-                    .interact_on_opt(&terminal)
-                    .unwrap()
+                // Java is only checked again when a different one is chosen, since checking starts a
+                // JVM.
+                if configured_java(&machine.configuration) != java {
+                    java = configured_java(&machine.configuration);
+                    java_available = is_java_available(&java);
+                }
+                terminal
+                    .write_line(&format_readiness(is_eula_accepted(), java_available))
                     .unwrap();
+                let actions = get_menu_actions(&machine, defer_save, &jar_filename);
+                let quick_toggles = get_quick_toggles(&config_option_info);
+                let option_names =
+                    get_names(config_option_info.clone(), machine.configuration.clone())
+                        .into_iter()
+                        .zip(&config_option_info)
+                        .map(|(name, option)| {
+                            match quick_toggles
+                                .iter()
+                                .find(|(_, toggle)| toggle.property == option.property)
+                            {
+                                Some((key, _)) => {
+                                    format!("{} {}", name, style(format!("[{}]", key)).dim())
+                                }
+                                None => name,
+                            }
+                        });
+                let select_options: Vec<String> = actions
+                    .iter()
+                    .map(|action| action.label().to_string())
+                    .chain(option_names)
+                    .collect();
+                let keys: Vec<char> = quick_toggles.iter().map(|(key, _)| *key).collect();
+                let result = match select_or_key(
+                    &terminal,
+                    "Please select the value you wish to change",
+                    &select_options,
+                    &keys,
+                )? {
+                    MenuChoice::Item(result) => result,
+                    MenuChoice::Key(key) => {
+                        let (_, option) = quick_toggles
+                            .into_iter()
+                            .find(|(toggle_key, _)| *toggle_key == key)
+                            .unwrap();
+                        if let ConfigurationOptionType::Bool(value) =
+                            machine.configuration.get(option.property.clone())
+                        {
+                            machine.dispatch(
+                                Event::AppEvent(AppEvent::SelectedOption),
+                                Some(Payload::ConfigurationOption(option.clone())),
+                            );
+                            machine.dispatch(
+                                Event::EditorEvent(EditorEvent::SubmitValue),
+                                Some(Payload::ConfigurationOptionType(
+                                    ConfigurationOptionType::Bool(!value),
+                                )),
+                            );
+                            autosave(&mut machine, defer_save)?;
+                        }
+                        continue;
+                    }
+                };
+
+                if let Some(action) = actions.get(result) {
+                    match action {
+                        MenuAction::QuickSetup => {
+                            let steps: Vec<ConfigurationOption> = QUICK_SETUP_OPTIONS
+                                .iter()
+                                .filter_map(|property| {
+                                    config_option_info
+                                        .iter()
+                                        .find(|option| option.property == *property)
+                                        .cloned()
+                                })
+                                .collect();
+                            let total = steps.len();
+                            quick_setup = Some((steps, total));
+                        }
+                        MenuAction::StartServer => {
+                            // The server only writes its files and quits with initSettings, which
+                            // otherwise looks like it crashed right away.
+                            if machine.configuration.initSettings {
+                                terminal.write_line(style("\"Initialize server settings\" is enabled, so the server will create server.properties and eula.txt and then exit instead of staying up.").yellow().to_string().as_str()).unwrap();
+                                disable_init_settings_after_run =
+                                    dialoguer::Confirm::with_theme(theme())
+                                        .with_prompt("Disable \"Initialize server settings\" after this run, so the next start runs the server normally?")
+                                        .default(true)
+                                        .interact_on(&terminal)
+                                        .unwrap();
+                            }
+                            // Left on, every start would throw away the caches and rebuild them.
+                            if machine.configuration.eraseCache {
+                                terminal.write_line(style("\"Erase the cache\" is enabled, so the server will delete the world's lighting and biome caches and rebuild them as chunks load. The first load will be slow.").yellow().to_string().as_str()).unwrap();
+                                disable_erase_cache_after_run =
+                                    dialoguer::Confirm::with_theme(theme())
+                                        .with_prompt("Disable \"Erase the cache\" after this run, so later starts keep the rebuilt caches?")
+                                        .default(false)
+                                        .interact_on(&terminal)
+                                        .unwrap();
+                            }
+                            // Each argument on its own line makes quoting and ordering mistakes
+                            // easy to spot, which the single command line hides.
+                            let arguments = build_command(
+                                &machine.instance,
+                                &machine.configuration,
+                                &jar_filename,
+                                &launch_options,
+                            );
+                            for (heading, lines) in launch_argument_sections(
+                                &machine.configuration,
+                                &arguments,
+                                &launch_options,
+                            ) {
+                                terminal.write_line(&heading).unwrap();
+                                for line in lines {
+                                    terminal
+                                        .write_line(style(line).dim().to_string().as_str())
+                                        .unwrap();
+                                }
+                            }
+                            if !dialoguer::Confirm::with_theme(theme())
+                                .with_prompt("Start the server?")
+                                .default(true)
+                                .interact_on(&terminal)
+                                .unwrap()
+                            {
+                                disable_init_settings_after_run = false;
+                                disable_erase_cache_after_run = false;
+                                continue;
+                            }
+                            machine.dispatch(Event::AppEvent(AppEvent::StartServer), None)
+                        }
+                        MenuAction::SaveNow => save_machine_configuration(&mut machine)?,
+                        MenuAction::Undo => {
+                            if machine.undo() {
+                                autosave(&mut machine, defer_save)?;
+                            }
+                        }
+                        MenuAction::ImportServerProperties => {
+                            let message =
+                                match ServerProperties::read(Path::new("server.properties")) {
+                                    Ok(properties) => {
+                                        let (configuration, changed) = import_server_properties(
+                                            &machine.configuration,
+                                            &properties,
+                                        );
+                                        if changed.is_empty() {
+                                            style(
+                                            "Your configuration already matches server.properties."
+                                                .to_string(),
+                                        )
+                                        .green()
+                                        } else {
+                                            machine.replace_configuration(configuration);
+                                            autosave(&mut machine, defer_save)?;
+                                            style(format!(
+                                                "Imported from server.properties: {}.",
+                                                changed.join(", ")
+                                            ))
+                                            .green()
+                                        }
+                                    }
+                                    Err(error) => style(format!(
+                                        "Unable to read server.properties: {}",
+                                        error
+                                    ))
+                                    .red(),
+                                };
+                            terminal.write_line(&message.to_string()).unwrap();
+                            std::thread::sleep(std::time::Duration::from_secs(2));
+                        }
+                        MenuAction::SetActiveJar => {
+                            let message = match set_active_jar(Path::new(&jar_filename)) {
+                                Ok(()) if cfg!(unix) => style(format!(
+                                    "{} now links to {}.",
+                                    ACTIVE_JAR_FILENAME, jar_filename
+                                ))
+                                .green(),
+                                Ok(()) => style(format!(
+                                    "Copied {} to {}.",
+                                    jar_filename, ACTIVE_JAR_FILENAME
+                                ))
+                                .green(),
+                                Err(error) => {
+                                    style(format!("Unable to set the active jar: {}", error)).red()
+                                }
+                            };
+                            terminal.write_line(&message.to_string()).unwrap();
+                            std::thread::sleep(std::time::Duration::from_secs(2));
+                        }
+                        MenuAction::ChooseJava => {
+                            let installs = discover_java_installs();
+                            if installs.is_empty() {
+                                terminal.write_line(style("No Java installations were found in Program Files or the registry. You can still enter the path to java.exe in \"Java executable\".").yellow().to_string().as_str()).unwrap();
+                                std::thread::sleep(std::time::Duration::from_secs(3));
+                                continue;
+                            }
+                            let items: Vec<String> = installs
+                                .iter()
+                                .map(|install| match &install.version {
+                                    Some(version) => {
+                                        format!("Java {} ({})", version, install.home.display())
+                                    }
+                                    None => install.home.display().to_string(),
+                                })
+                                .chain(std::iter::once(format!(
+                                    "\"{}\" from the PATH",
+                                    DEFAULT_JAVA
+                                )))
+                                .collect();
+                            let result = dialoguer::Select::with_theme(theme())
+                                .with_prompt("Which Java should run the server?")
+                                .items(&items)
+                                .default(0)
+                                .interact_on(&terminal)
+                                .unwrap();
+                            let mut configuration = machine.configuration.clone();
+                            configuration.javaPath = installs
+                                .get(result)
+                                .map(|install| install.executable.to_string_lossy().to_string());
+                            machine.replace_configuration(configuration);
+                            autosave(&mut machine, defer_save)?;
+                        }
+                        MenuAction::EnableWhitelist | MenuAction::DisableWhitelist => {
+                            let enable = *action == MenuAction::EnableWhitelist;
+                            match set_whitelist_enabled(enable) {
+                                Ok(()) if enable => {
+                                    terminal.write_line(style("The whitelist is enabled (white-list and enforce-whitelist in server.properties), so only the players in whitelist.json can join.").green().to_string().as_str()).unwrap();
+                                    let players = count_whitelisted_players();
+                                    terminal.write_line(&format!("{} has {} player{}. Add players with \"whitelist add <player>\" in the server console, which looks up their UUID and saves them to whitelist.json.", WHITELIST_FILENAME, players, if players == 1 { "" } else { "s" })).unwrap();
+                                }
+                                Ok(()) => terminal.write_line(style(format!("The whitelist is disabled, so anyone can join. {} is kept for when you enable it again.", WHITELIST_FILENAME)).green().to_string().as_str()).unwrap(),
+                                Err(error) => terminal.write_line(style(format!("Unable to change the whitelist: {}", error)).red().to_string().as_str()).unwrap(),
+                            }
+                            std::thread::sleep(std::time::Duration::from_secs(4));
+                        }
+                        MenuAction::EnableRcon => {
+                            let properties = ServerProperties::read(Path::new("server.properties"))
+                                .unwrap_or_default();
+                            let current_port = properties
+                                .get("rcon.port")
+                                .and_then(|port| port.parse().ok())
+                                .unwrap_or(DEFAULT_RCON_PORT);
+                            let server_port = server_port(&machine.configuration);
+                            let port: String = dialoguer::Input::with_theme(theme())
+                                .with_prompt("RCON port")
+                                .default(current_port.to_string())
+                                .validate_with(|input: &String| {
+                                    OptionValidator::Range(1, 65535).check(input)?;
+                                    if input.trim().parse::<u16>() == Ok(server_port) {
+                                        return Err(format!(
+                                            "The server already uses port {}. Choose another one.",
+                                            server_port
+                                        ));
+                                    }
+                                    Ok(())
+                                })
+                                .interact_text_on(&terminal)
+                                .unwrap();
+                            let port: u16 = port.trim().parse().unwrap();
+                            let password = dialoguer::Password::with_theme(theme())
+                                .with_prompt("RCON password (leave empty to generate a strong one)")
+                                .allow_empty_password(true)
+                                .interact_on(&terminal)
+                                .unwrap();
+                            let generated = password.is_empty();
+                            let password = if generated {
+                                generate_rcon_password()
+                            } else {
+                                Ok(password)
+                            };
+                            // The stop and attach subcommands read the password from this file when
+                            // it's set, so it has to match the server's.
+                            let result = password.and_then(|password| {
+                                if let Some(weakness) = rcon_password_weakness(&password) {
+                                    terminal.write_line(style(format!("The password is weak: {}. Anyone who can reach port {} gets full control of the server.", weakness, port)).yellow().to_string().as_str()).unwrap();
+                                }
+                                enable_rcon(port, &password)?;
+                                if let Some(path) = &machine.configuration.rconPasswordFile {
+                                    write_password_file(Path::new(path), &password)?;
+                                }
+                                Ok(password)
+                            });
+                            match result {
+                                Ok(password) => {
+                                    terminal.write_line(style(format!("RCON is enabled on port {} (enable-rcon, rcon.port, and rcon.password in server.properties). Restart the server for it to take effect.", port)).green().to_string().as_str()).unwrap();
+                                    if generated {
+                                        terminal
+                                            .write_line(&format!(
+                                                "The generated password is: {}",
+                                                password
+                                            ))
+                                            .unwrap();
+                                    }
+                                    if let Some(path) = &machine.configuration.rconPasswordFile {
+                                        terminal.write_line(&format!("Also saved the password to {}, the RCON password file.", path)).unwrap();
+                                    }
+                                }
+                                Err(error) => terminal
+                                    .write_line(
+                                        style(format!("Unable to enable RCON: {}", error))
+                                            .red()
+                                            .to_string()
+                                            .as_str(),
+                                    )
+                                    .unwrap(),
+                            }
+                            terminal
+                                .write_line(
+                                    style("Press any key to go back to the menu.")
+                                        .dim()
+                                        .to_string()
+                                        .as_str(),
+                                )
+                                .unwrap();
+                            terminal.read_key().unwrap();
+                        }
+                        MenuAction::DisableRcon => {
+                            match disable_rcon() {
+                                Ok(()) => terminal.write_line(style("RCON is disabled, so the stop and attach subcommands can't reach the server. Its port and password are kept for when you enable it again.").green().to_string().as_str()).unwrap(),
+                                Err(error) => terminal.write_line(style(format!("Unable to disable RCON: {}", error)).red().to_string().as_str()).unwrap(),
+                            }
+                            std::thread::sleep(std::time::Duration::from_secs(3));
+                        }
+                        MenuAction::ConnectionInfo => {
+                            let port = server_port(&machine.configuration);
+                            match lan_address() {
+                                Some(address) => terminal.write_line(&format!("On your network: {}", connection_string(address, port))).unwrap(),
+                                None => terminal.write_line(style("Unable to find this machine's address on your network.").yellow().to_string().as_str()).unwrap(),
+                            }
+                            // Looking it up tells a third party about this machine, so it's only done
+                            // when asked.
+                            if dialoguer::Confirm::with_theme(theme())
+                                .with_prompt(format!("Look up your public address for friends outside your network (asks {})?", PUBLIC_ADDRESS_SERVICE))
+                                .default(true)
+                                .interact_on(&terminal)
+                                .unwrap()
+                            {
+                                match public_address() {
+                                    Ok(address) => {
+                                        terminal.write_line(&format!("Over the internet: {}", connection_string(address, port))).unwrap();
+                                        terminal.write_line(style(format!("Friends outside your network can only connect once your router forwards port {} (TCP) to this machine.", port)).dim().to_string().as_str()).unwrap();
+                                    }
+                                    Err(error) => terminal.write_line(style(format!("Unable to look up your public address, e.g. because you're offline: {}", error)).yellow().to_string().as_str()).unwrap(),
+                                }
+                            }
+                            terminal
+                                .write_line(
+                                    style("Press any key to go back to the menu.")
+                                        .dim()
+                                        .to_string()
+                                        .as_str(),
+                                )
+                                .unwrap();
+                            terminal.read_key().unwrap();
+                        }
+                        MenuAction::EditServerProperties => {
+                            let options = get_property_option_info();
+                            let properties =
+                                ServerProperties::read_or_default(Path::new("server.properties"))?;
+                            let mut items: Vec<String> = options
+                                .iter()
+                                .map(|option| {
+                                    format!(
+                                        "{} ({})",
+                                        option.name,
+                                        server_property_value(&option.property, &properties)
+                                    )
+                                })
+                                .collect();
+                            items.push("Back".to_string());
+                            let selection = dialoguer::Select::with_theme(theme())
+                                .with_prompt("Which setting should be changed?")
+                                .items(&items)
+                                .default(0)
+                                .interact_on_opt(&terminal)?;
+                            match selection {
+                                Some(index) if index < options.len() => {
+                                    machine.dispatch(
+                                        Event::AppEvent(AppEvent::SelectedOption),
+                                        Some(Payload::ConfigurationOption(options[index].clone())),
+                                    );
+                                }
+                                _ => continue,
+                            }
+                        }
+                        MenuAction::RunMacro => {
+                            let names: Vec<String> = machine
+                                .configuration
+                                .macros
+                                .iter()
+                                .map(|(name, commands)| {
+                                    format!("{} ({})", name, commands.join(", "))
+                                })
+                                .collect();
+                            let selection = dialoguer::Select::with_theme(theme())
+                                .with_prompt("Which macro should run?")
+                                .items(&names)
+                                .default(0)
+                                .interact_on_opt(&terminal)?;
+                            let index = match selection {
+                                Some(index) => index,
+                                None => continue,
+                            };
+                            let (name, commands) = &machine.configuration.macros[index];
+                            match run_macro(
+                                &machine.configuration,
+                                commands,
+                                matches.value_of("rcon_password_file"),
+                                &terminal,
+                            ) {
+                                Ok(()) => terminal
+                                    .write_line(
+                                        style(format!("Ran \"{}\".", name))
+                                            .green()
+                                            .to_string()
+                                            .as_str(),
+                                    )
+                                    .unwrap(),
+                                Err(error) => terminal
+                                    .write_line(style(error.to_string()).red().to_string().as_str())
+                                    .unwrap(),
+                            }
+                            terminal
+                                .write_line(
+                                    style("Press any key to go back to the menu.")
+                                        .dim()
+                                        .to_string()
+                                        .as_str(),
+                                )
+                                .unwrap();
+                            terminal.read_key().unwrap();
+                        }
+                        MenuAction::ExportShareable => {
+                            let sanitized = sanitize_configuration(&machine.configuration);
+                            let message = match fs::write(
+                                SHAREABLE_CONFIGURATION_FILENAME,
+                                ron::to_string(&sanitized).unwrap(),
+                            ) {
+                                Ok(()) => style(format!(
+                                    "Wrote {} without passwords or absolute paths. It's safe to share.",
+                                    SHAREABLE_CONFIGURATION_FILENAME
+                                ))
+                                .green(),
+                                Err(error) => style(format!(
+                                    "Unable to write to {}: {}",
+                                    SHAREABLE_CONFIGURATION_FILENAME, error
+                                ))
+                                .red(),
+                            };
+                            terminal.write_line(&message.to_string()).unwrap();
+                            std::thread::sleep(std::time::Duration::from_secs(2));
+                        }
+                        MenuAction::WorldInfo => {
+                            let world = resolve_world_path(&machine.configuration);
+                            let level_dat = world.join("level.dat");
+                            match read_level_dat(&level_dat) {
+                                Ok(info) => print_table(&level_info_rows(&info)),
+                                Err(error) if error.kind() == io::ErrorKind::NotFound => terminal.write_line(format!("{} doesn't have a level.dat yet. The server writes it the first time it starts.", world.display()).as_str()).unwrap(),
+                                Err(error) => terminal
+                                    .write_line(
+                                        style(format!(
+                                            "Unable to read {}: {}",
+                                            level_dat.display(),
+                                            error
+                                        ))
+                                        .red()
+                                        .to_string()
+                                        .as_str(),
+                                    )
+                                    .unwrap(),
+                            }
+                            for line in add_on_lines(&world) {
+                                terminal.write_line(&line).unwrap();
+                            }
+                            terminal
+                                .write_line(
+                                    style("Press any key to go back to the menu.")
+                                        .dim()
+                                        .to_string()
+                                        .as_str(),
+                                )
+                                .unwrap();
+                            terminal.read_key().unwrap();
+                        }
+                        MenuAction::RenameWorld => {
+                            let world = resolve_world_path(&machine.configuration);
+                            if is_world_in_use(&world) {
+                                terminal.write_line(style(format!("{} is in use by a running server. Stop the server before renaming its world.", world.display())).red().to_string().as_str()).unwrap();
+                                std::thread::sleep(std::time::Duration::from_secs(2));
+                                continue;
+                            }
+                            let name: String = dialoguer::Input::with_theme(theme())
+                                .with_prompt(format!(
+                                    "New name for the world in {}",
+                                    world.display()
+                                ))
+                                .validate_with(|input: &String| {
+                                    OptionValidator::FolderName.check(input)
+                                })
+                                .interact_text_on(&terminal)
+                                .unwrap();
+                            let name = name.trim().to_string();
+                            if world.with_file_name(&name).exists() {
+                                terminal.write_line(format!("{} already exists. Please try again with another name.", world.with_file_name(&name).display()).as_str()).unwrap();
+                                std::thread::sleep(std::time::Duration::from_secs(2));
+                                continue;
+                            }
+                            let has_properties = Path::new("server.properties").is_file();
+                            match rename_world(&world, &name) {
+                                Ok(renamed) => {
+                                    let mut configuration = machine.configuration.clone();
+                                    configuration.world = Some(name.clone());
+                                    machine.replace_configuration(configuration);
+                                    // The world option would point at the old name, so the world is
+                                    // put back where the saved configuration expects it.
+                                    if let Err(error) = autosave(&mut machine, defer_save) {
+                                        machine.undo();
+                                        machine.dirty = false;
+                                        if let Err(undo_error) = renamed.undo() {
+                                            terminal.write_line(style(format!("Unable to move the world back to {} ({}), so set the world option to \"{}\".", world.display(), undo_error, name)).red().to_string().as_str()).unwrap();
+                                        }
+                                        return Err(error);
+                                    }
+                                    terminal.write_line(style(format!("Renamed the world to \"{}\" and updated the world option{}.", name, if has_properties { " and level-name in server.properties" } else { "" })).green().to_string().as_str()).unwrap();
+                                }
+                                Err(error) => terminal
+                                    .write_line(
+                                        style(format!(
+                                            "Unable to rename {}: {}",
+                                            world.display(),
+                                            error
+                                        ))
+                                        .red()
+                                        .to_string()
+                                        .as_str(),
+                                    )
+                                    .unwrap(),
+                            }
+                            std::thread::sleep(std::time::Duration::from_secs(2));
+                        }
+                        MenuAction::RestoreBackup => {
+                            let world = resolve_world_path(&machine.configuration);
+                            let backups = list_backups(&world);
+                            let items: Vec<String> =
+                                backups.iter().map(|backup| backup_label(backup)).collect();
+                            let selection = dialoguer::Select::with_theme(theme())
+                                .with_prompt(format!(
+                                    "Which backup of {} should be restored?",
+                                    world.display()
+                                ))
+                                .items(&items)
+                                .default(0)
+                                .interact_on_opt(&terminal)?;
+                            let index = match selection {
+                                Some(index) => index,
+                                None => continue,
+                            };
+                            let (backup, label) = (&backups[index], &items[index]);
+                            let prompt = if world.exists() {
+                                format!("Replace {} with the backup from {}? The world as it is now will be lost.", world.display(), label)
+                            } else {
+                                format!(
+                                    "Restore {} from the backup from {}?",
+                                    world.display(),
+                                    label
+                                )
+                            };
+                            if !dialoguer::Confirm::with_theme(theme())
+                                .with_prompt(prompt)
+                                .default(false)
+                                .interact_on(&terminal)
+                                .unwrap()
+                            {
+                                continue;
+                            }
+                            if is_world_in_use(&world) {
+                                terminal.write_line("The world is in use by a running server, so it's stopped first.").unwrap();
+                                if let Err(error) = stop_server(
+                                    &machine.configuration,
+                                    matches.value_of("rcon_password_file"),
+                                    true,
+                                    &terminal,
+                                ) {
+                                    terminal
+                                        .write_line(
+                                            style(format!(
+                                                "{} Stop the server yourself, then try again.",
+                                                error
+                                            ))
+                                            .red()
+                                            .to_string()
+                                            .as_str(),
+                                        )
+                                        .unwrap();
+                                    std::thread::sleep(std::time::Duration::from_secs(4));
+                                    continue;
+                                }
+                                // The server saves the world on its way out, which can take a while.
+                                let deadline = Instant::now() + Duration::from_secs(60);
+                                while is_world_in_use(&world) && Instant::now() < deadline {
+                                    std::thread::sleep(Duration::from_millis(500));
+                                }
+                                if is_world_in_use(&world) {
+                                    terminal.write_line(style("The server didn't stop within a minute, so the world wasn't restored.").red().to_string().as_str()).unwrap();
+                                    std::thread::sleep(std::time::Duration::from_secs(4));
+                                    continue;
+                                }
+                            }
+                            match restore_backup(backup, &world) {
+                                Ok(Some(kept)) => terminal.write_line(style(format!("Restored {} from the backup from {}. The world it replaced was kept as {}.", world.display(), label, kept.display())).green().to_string().as_str()).unwrap(),
+                                Ok(None) => terminal.write_line(style(format!("Restored {} from the backup from {}.", world.display(), label)).green().to_string().as_str()).unwrap(),
+                                Err(error) => terminal.write_line(style(format!("Unable to restore the backup, so the world was left as it was: {}", error)).red().to_string().as_str()).unwrap(),
+                            }
+                            std::thread::sleep(std::time::Duration::from_secs(3));
+                        }
+                        MenuAction::DuplicateProfile => {
+                            let name: String = dialoguer::Input::with_theme(theme())
+                                .with_prompt("Name of the new profile")
+                                .interact_text_on(&terminal)
+                                .unwrap();
+                            let name = name.trim();
+                            let path = machine
+                                .configuration_path
+                                .with_file_name(profile_filename(name));
+                            if !is_valid_profile_name(name) || path == machine.configuration_path {
+                                terminal.write_line("Profile names can only contain letters, numbers, \"-\", and \"_\", and can't be the current profile. Please try again.").unwrap();
+                                std::thread::sleep(std::time::Duration::from_secs(2));
+                                continue;
+                            }
+                            let overwrite = !path.exists()
+                                || dialoguer::Confirm::with_theme(theme())
+                                    .with_prompt(format!(
+                                        "{} already exists. Replace it, along with all of its instances?",
+                                        path.display()
+                                    ))
+                                    .default(false)
+                                    .interact_on(&terminal)
+                                    .unwrap();
+                            if !overwrite {
+                                continue;
+                            }
+                            let configuration =
+                                replace_profile(&path, machine.configuration.clone())?;
+                            let switch = dialoguer::Confirm::with_theme(theme())
+                                .with_prompt(format!(
+                                    "Saved a copy as {}. Switch to the \"{}\" profile?",
+                                    path.display(),
+                                    name
+                                ))
+                                .default(true)
+                                .interact_on(&terminal)
+                                .unwrap();
+                            if switch {
+                                if machine.dirty {
+                                    save_machine_configuration(&mut machine)?;
+                                }
+                                _configuration_lock = acquire_configuration_lock(&path);
+                                machine.configuration_path = path;
+                                machine.instance = DEFAULT_INSTANCE.to_string();
+                                machine.configuration = configuration;
+                                machine.history.clear();
+                            }
+                        }
+                        MenuAction::CompareProfiles => {
+                            let mut configurations =
+                                list_profile_configurations(&machine.configuration_path);
+                            let labels: Vec<String> = configurations
+                                .iter()
+                                .map(|(label, _)| label.clone())
+                                .collect();
+                            let first = dialoguer::Select::with_theme(theme())
+                                .with_prompt("Compare")
+                                .items(&labels)
+                                .default(0)
+                                .interact_on(&terminal)
+                                .unwrap();
+                            let (left_label, left) = configurations.remove(first);
+                            let second = dialoguer::Select::with_theme(theme())
+                                .with_prompt(format!("Compare {} with", left_label))
+                                .items(
+                                    &configurations
+                                        .iter()
+                                        .map(|(label, _)| label.clone())
+                                        .collect::<Vec<String>>(),
+                                )
+                                .default(0)
+                                .interact_on(&terminal)
+                                .unwrap();
+                            let (right_label, right) = configurations.remove(second);
 
-                if result == 0 {
-                    machine.dispatch(Event::AppEvent(AppEvent::StartServer), None);
-                } else if result == 1 {
-                    machine.dispatch(Event::AppEvent(AppEvent::Exit), None);
+                            let differences =
+                                diff_configurations(&config_option_info, &left, &right);
+                            if differences.is_empty() {
+                                println!("{} and {} are the same.", left_label, right_label);
+                            } else {
+                                let mut rows =
+                                    vec![["Option".to_string(), left_label, right_label]];
+                                for option in differences {
+                                    rows.push([
+                                        option.name.clone(),
+                                        format_option_value(
+                                            &option.property,
+                                            left.get(option.property.clone()),
+                                        ),
+                                        format_option_value(
+                                            &option.property,
+                                            right.get(option.property.clone()),
+                                        ),
+                                    ]);
+                                }
+                                print_table(&rows);
+                            }
+                            terminal
+                                .write_line(
+                                    style("Press any key to go back to the menu.")
+                                        .dim()
+                                        .to_string()
+                                        .as_str(),
+                                )
+                                .unwrap();
+                            terminal.read_key().unwrap();
+                        }
+                        MenuAction::SwitchInstance => {
+                            if machine.dirty {
+                                save_machine_configuration(&mut machine)?;
+                            }
+                            let (instance, configuration) = match select_instance(
+                                &machine.configuration_path,
+                                get_instances(&machine.configuration_path, interactive)?,
+                                &terminal,
+                            )? {
+                                Some(selected) => selected,
+                                None => continue,
+                            };
+                            machine.instance = instance;
+                            machine.configuration = configuration;
+                            machine.history.clear();
+                        }
+                        MenuAction::Exit => machine.dispatch(Event::AppEvent(AppEvent::Exit), None),
+                    }
                 } else {
-                    match config_option_info.get(result - 2) {
+                    match config_option_info.get(result - actions.len()) {
                         Some(option) => {
                             let payload = Some(Payload::ConfigurationOption(option.clone()));
                             machine.dispatch(Event::AppEvent(AppEvent::SelectedOption), payload);
@@ -312,103 +5347,981 @@ fn main() {
             AppState::Running => {
                 terminal.set_title("Minecraft Server");
 
-                let vars = run_server(
-                    machine.configuration.clone(),
-                    jar_filename.clone(),
-                    terminal.clone(),
-                );
-                machine.configuration = vars.0;
-                terminal = vars.1;
+                // Changes that are still waiting to be saved are saved before the server starts
+                // rather than after it stops.
+                server_started = true;
+                settle_pending_changes(&mut machine, no_save_on_start, server_started)?;
+                run_server(
+                    &machine.instance,
+                    &machine.configuration,
+                    &jar_filename,
+                    &launch_options,
+                    profile_name(&machine.configuration_path).as_deref(),
+                    &terminal,
+                )?;
+                if disable_init_settings_after_run {
+                    let mut configuration = machine.configuration.clone();
+                    configuration.initSettings = false;
+                    machine.replace_configuration(configuration);
+                    terminal
+                        .write_line("Disabled \"Initialize server settings\" for the next start.")
+                        .unwrap();
+                }
+                if disable_erase_cache_after_run {
+                    let mut configuration = machine.configuration.clone();
+                    configuration.eraseCache = false;
+                    machine.replace_configuration(configuration);
+                    terminal
+                        .write_line("Disabled \"Erase the cache\" for the next start.")
+                        .unwrap();
+                }
 
                 machine.dispatch(Event::AppEvent(AppEvent::Exit), None);
             }
             AppState::Exited => {}
             AppState::EditingConfiguration => {
                 terminal.set_title("Minecraft Server CLI — Editing Configuration");
-                let editor_state = machine.editor_state.clone().expect(
+                let editor_state = machine.editor_state.expect(
                     "Expected to have an editor state while in EditingConfiguration app state.",
                 );
                 let option = machine.selected_configuration_option.as_ref().expect("Expected a configuration option to have been chosen before editing the configuration.").clone();
+                // Quick setup saves everything at the end instead.
+                let defer_save = defer_save || quick_setup.is_some();
+                if let Some((steps, total)) = &quick_setup {
+                    terminal
+                        .write_line(
+                            style(format!(
+                                "Quick setup: step {} of {}",
+                                total - steps.len(),
+                                total
+                            ))
+                            .cyan()
+                            .to_string()
+                            .as_str(),
+                        )
+                        .unwrap();
+                }
                 terminal
                     .write_line(
-                        format!(
-                            "Editing Configuration > {}",
-                            style(option.name).bold().to_string()
-                        )
-                        .as_str(),
+                        format!("Editing Configuration > {}", style(&option.name).bold()).as_str(),
                     )
                     .unwrap();
                 terminal.write_line(option.description.as_str()).unwrap();
+                if let Some(details) = get_option_details(&option.property) {
+                    terminal
+                        .write_line(style(details).dim().to_string().as_str())
+                        .unwrap();
+                }
 
                 match editor_state {
                     EditorState::SelectOnOff => {
-                        let result = dialoguer::Select::new()
-                            .items(&vec!["Enable", "Disable"])
-                            .interact_on_opt(&terminal)
-                            .unwrap()
+                        let result = dialoguer::Select::with_theme(theme())
+                            .items(&["Enable", "Disable", "Reset to default"])
+                            .interact_on_opt(&terminal)?;
+                        let result = match result {
+                            Some(result) => result,
+                            None => {
+                                machine.dispatch(Event::EditorEvent(EditorEvent::Cancel), None);
+                                continue;
+                            }
+                        };
+
+                        if result == 2 {
+                            machine.dispatch(Event::EditorEvent(EditorEvent::ResetToDefault), None);
+                        } else {
+                            machine.dispatch(
+                                Event::EditorEvent(EditorEvent::SubmitValue),
+                                Some(Payload::ConfigurationOptionType(
+                                    ConfigurationOptionType::Bool(result == 0),
+                                )),
+                            );
+                        }
+                        autosave(&mut machine, defer_save)?;
+                    }
+                    EditorState::NumberInput => {
+                        // Any number that fits is fine for options without their own rules.
+                        let validator = option
+                            .validator
+                            .clone()
+                            .unwrap_or(OptionValidator::Range(0, u16::MAX));
+                        let mut input = dialoguer::Input::with_theme(theme());
+                        if option
+                            .r#type
+                            .contains(ConfigurationOptionTypeFlag::ServerProperty)
+                        {
+                            input.default(server_property_value(
+                                &option.property,
+                                &ServerProperties::read_or_default(Path::new("server.properties"))?,
+                            ));
+                        }
+                        let result: String = input
+                            .validate_with(|input: &String| validator.check(input))
+                            .interact_text_on(&terminal)
                             .unwrap();
 
+                        if option
+                            .r#type
+                            .contains(ConfigurationOptionTypeFlag::ServerProperty)
+                        {
+                            write_server_property(&mut machine, &option, result.trim(), &terminal)?;
+                        }
                         machine.dispatch(
                             Event::EditorEvent(EditorEvent::SubmitValue),
                             Some(Payload::ConfigurationOptionType(
-                                ConfigurationOptionType::Bool(result == 0),
+                                ConfigurationOptionType::OptionU16(Some(
+                                    result.trim().parse().unwrap(),
+                                )),
                             )),
                         );
-                        machine.configuration = save_configuration(machine.configuration);
-                    }
-                    EditorState::NumberInput => {
-                        let result: String =
-                            dialoguer::Input::new().interact_text_on(&terminal).unwrap();
-
-                        match result.parse::<u16>() {
-                            Ok(result) => {
-                                if result == 0 {
-                                    terminal.write_line("You entered an invalid port number of \"0\". Please try again.").unwrap();
-                                    std::thread::sleep(std::time::Duration::from_secs(1));
-                                } else {
-                                    machine.dispatch(
-                                        Event::EditorEvent(EditorEvent::SubmitValue),
-                                        Some(Payload::ConfigurationOptionType(
-                                            ConfigurationOptionType::OptionU16(Some(result)),
-                                        )),
-                                    );
-                                    machine.configuration =
-                                        save_configuration(machine.configuration);
-                                }
-                            }
-                            Err(_) => {
-                                terminal.write_line(format!("You entered an invalid port number of \"{}\". Please try again.", result).as_str()).unwrap();
-                                std::thread::sleep(std::time::Duration::from_secs(1));
-                            }
-                        }
+                        autosave(&mut machine, defer_save)?;
                     }
                     EditorState::TextInput => {
-                        let result: String =
-                            dialoguer::Input::new().interact_text_on(&terminal).unwrap();
+                        // What's checked is what's saved, so that e.g. a world named " world" that
+                        // passed as "world" doesn't end up as a folder with a leading space.
+                        let validator = option.validator.clone();
+                        let result: String = dialoguer::Input::with_theme(theme())
+                            .validate_with(|input: &String| match &validator {
+                                Some(validator) => validator.check(input.trim()),
+                                None => Ok(()),
+                            })
+                            .interact_text_on(&terminal)
+                            .unwrap();
 
                         machine.dispatch(
                             Event::EditorEvent(EditorEvent::SubmitValue),
                             Some(Payload::ConfigurationOptionType(
-                                ConfigurationOptionType::OptionString(Some(result)),
+                                ConfigurationOptionType::OptionString(Some(
+                                    result.trim().to_string(),
+                                )),
                             )),
                         );
-                        machine.configuration = save_configuration(machine.configuration);
+                        autosave(&mut machine, defer_save)?;
                     }
-                    EditorState::SelectValueOrNone => {
-                        let result = dialoguer::Select::new()
-                            .items(&vec!["Enter a value", "Disable"])
-                            .interact_on_opt(&terminal)
-                            .unwrap()
+                    EditorState::MemoryInput => {
+                        let result: String = dialoguer::Input::with_theme(theme())
+                            .interact_text_on(&terminal)
                             .unwrap();
+
+                        match parse_memory(&result) {
+                            Ok(result) => {
+                                machine.dispatch(
+                                    Event::EditorEvent(EditorEvent::SubmitValue),
+                                    Some(Payload::ConfigurationOptionType(
+                                        ConfigurationOptionType::OptionMemory(Some(result)),
+                                    )),
+                                );
+                                autosave(&mut machine, defer_save)?;
+                            }
+                            Err(message) => {
+                                terminal
+                                    .write_line(format!("{} Please try again.", message).as_str())
+                                    .unwrap();
+                                std::thread::sleep(std::time::Duration::from_secs(2));
+                            }
+                        }
+                    }
+                    EditorState::SelectChoice => {
+                        let choices = match &option.validator {
+                            Some(OptionValidator::OneOf(choices)) => choices.clone(),
+                            _ => Vec::new(),
+                        };
+                        let in_server_properties = option
+                            .r#type
+                            .contains(ConfigurationOptionTypeFlag::ServerProperty);
+                        let current = if in_server_properties {
+                            server_property_value(
+                                &option.property,
+                                &ServerProperties::read_or_default(Path::new("server.properties"))?,
+                            )
+                        } else {
+                            format_option_value(
+                                &option.property,
+                                machine.configuration.get(option.property.clone()),
+                            )
+                        };
+                        let mut items = choices.clone();
+                        items.push("Reset to default".to_string());
+                        let result = dialoguer::Select::with_theme(theme())
+                            .items(&items)
+                            .default(
+                                choices
+                                    .iter()
+                                    .position(|choice| *choice == current)
+                                    .unwrap_or(0),
+                            )
+                            .interact_on_opt(&terminal)?;
+                        let result = match result {
+                            Some(result) => result,
+                            None => {
+                                machine.dispatch(Event::EditorEvent(EditorEvent::Cancel), None);
+                                continue;
+                            }
+                        };
+
+                        if result == choices.len() {
+                            if in_server_properties {
+                                write_server_property(
+                                    &mut machine,
+                                    &option,
+                                    &server_property_default(&option.property),
+                                    &terminal,
+                                )?;
+                            }
+                            machine.dispatch(Event::EditorEvent(EditorEvent::ResetToDefault), None);
+                        } else {
+                            if in_server_properties {
+                                write_server_property(
+                                    &mut machine,
+                                    &option,
+                                    &choices[result],
+                                    &terminal,
+                                )?;
+                            }
+                            machine.dispatch(
+                                Event::EditorEvent(EditorEvent::SubmitValue),
+                                Some(Payload::ConfigurationOptionType(
+                                    ConfigurationOptionType::Choice(choices[result].clone()),
+                                )),
+                            );
+                        }
+                        autosave(&mut machine, defer_save)?;
+                    }
+                    EditorState::KeyValueList => {
+                        let mut entries = match machine.configuration.get(option.property.clone()) {
+                            ConfigurationOptionType::KeyValues(entries) => entries,
+                            _ => Vec::new(),
+                        };
+                        let original_entries = entries.clone();
+                        let mut reset = false;
+                        loop {
+                            let mut items: Vec<String> = entries
+                                .iter()
+                                .map(|(key, value)| format!("Remove {}={}", key, value))
+                                .collect();
+                            items.push("Add a property".to_string());
+                            items.push("Reset to default".to_string());
+                            items.push("Done".to_string());
+                            let result = dialoguer::Select::with_theme(theme())
+                                .items(&items)
+                                .default(entries.len())
+                                .interact_on_opt(&terminal)?;
+                            // Escape leaves the list as it was.
+                            let result = match result {
+                                Some(result) => result,
+                                None => {
+                                    entries = original_entries.clone();
+                                    break;
+                                }
+                            };
+
+                            if result < entries.len() {
+                                entries.remove(result);
+                            } else if result == entries.len() {
+                                let key: String = dialoguer::Input::with_theme(theme())
+                                    .with_prompt("Key")
+                                    .interact_text_on(&terminal)
+                                    .unwrap();
+                                let key = key.trim().to_string();
+                                if key.is_empty()
+                                    || key.contains(|character: char| {
+                                        character.is_whitespace() || character == '='
+                                    })
+                                {
+                                    terminal.write_line(format!("You entered an invalid key of \"{}\". Please try again.", key).as_str()).unwrap();
+                                    std::thread::sleep(std::time::Duration::from_secs(1));
+                                    continue;
+                                }
+                                let value: String = dialoguer::Input::with_theme(theme())
+                                    .with_prompt("Value")
+                                    .allow_empty(true)
+                                    .interact_text_on(&terminal)
+                                    .unwrap();
+                                // Setting a key that's already there replaces its value.
+                                match entries.iter_mut().find(|(existing, _)| *existing == key) {
+                                    Some(entry) => entry.1 = value,
+                                    None => entries.push((key, value)),
+                                }
+                            } else if result == entries.len() + 1 {
+                                reset = true;
+                                break;
+                            } else {
+                                break;
+                            }
+                        }
+
+                        if reset {
+                            machine.dispatch(Event::EditorEvent(EditorEvent::ResetToDefault), None);
+                            autosave(&mut machine, defer_save)?;
+                        } else if entries == original_entries {
+                            machine.dispatch(Event::EditorEvent(EditorEvent::Cancel), None);
+                        } else {
+                            machine.dispatch(
+                                Event::EditorEvent(EditorEvent::SubmitValue),
+                                Some(Payload::ConfigurationOptionType(
+                                    ConfigurationOptionType::KeyValues(entries),
+                                )),
+                            );
+                            autosave(&mut machine, defer_save)?;
+                        }
+                    }
+                    EditorState::SelectValueOrNone => {
+                        let result = dialoguer::Select::with_theme(theme())
+                            .items(&["Enter a value", "Disable", "Reset to default"])
+                            .interact_on_opt(&terminal)?;
+                        let result = match result {
+                            Some(result) => result,
+                            None => {
+                                machine.dispatch(Event::EditorEvent(EditorEvent::Cancel), None);
+                                continue;
+                            }
+                        };
                         if result == 0 {
                             machine.dispatch(Event::EditorEvent(EditorEvent::SelectedValue), None);
+                        } else if result == 1 {
+                            // Clearing a value is one keystroke away, so make sure it wasn't a slip.
+                            let value = machine.configuration.get(option.property.clone());
+                            let is_set = match &value {
+                                ConfigurationOptionType::OptionU16(value) => value.is_some(),
+                                ConfigurationOptionType::OptionString(value) => value.is_some(),
+                                ConfigurationOptionType::OptionMemory(value) => value.is_some(),
+                                _ => false,
+                            };
+                            let confirmed = !is_set
+                                || dialoguer::Confirm::with_theme(theme())
+                                    .with_prompt(format!(
+                                        "Clear {} (currently {})?",
+                                        option.name,
+                                        format_option_value(&option.property, value)
+                                    ))
+                                    .default(false)
+                                    .interact_on(&terminal)
+                                    .unwrap();
+                            if confirmed {
+                                machine
+                                    .dispatch(Event::EditorEvent(EditorEvent::SelectedNone), None);
+                                autosave(&mut machine, defer_save)?;
+                            } else {
+                                machine.dispatch(Event::EditorEvent(EditorEvent::Cancel), None);
+                            }
                         } else {
-                            machine.dispatch(Event::EditorEvent(EditorEvent::SelectedNone), None);
-                            machine.configuration = save_configuration(machine.configuration);
+                            machine.dispatch(Event::EditorEvent(EditorEvent::ResetToDefault), None);
+                            autosave(&mut machine, defer_save)?;
                         }
                     }
                 }
             }
         }
     }
+
+    match settle_pending_changes(&mut machine, no_save_on_start, server_started)? {
+        PendingChanges::None => {}
+        PendingChanges::Discarded => terminal
+            .write_line(
+                style("Your unsaved changes were discarded because of --no-save-on-start.")
+                    .yellow()
+                    .to_string()
+                    .as_str(),
+            )
+            .unwrap(),
+        PendingChanges::Saved => terminal
+            .write_line(
+                format!(
+                    "Saved your changes to the \"{}\" instance in {}.",
+                    machine.instance,
+                    machine.configuration_path.display()
+                )
+                .as_str(),
+            )
+            .unwrap(),
+    }
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory for one test, which tests running in parallel don't share.
+    fn scratch_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("msc-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn parse_error(name: &str, contents: &str) -> String {
+        let directory = scratch_directory(name);
+        let path = directory.join(CONFIGURATION_FILENAME);
+        fs::write(&path, contents).unwrap();
+        let result = read_configuration_file(&path);
+        fs::remove_dir_all(directory).unwrap();
+        match result {
+            Err(MscError::ConfigParse { description, .. }) => description,
+            other => panic!("Expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn truncated_configuration_is_a_parse_error_with_its_position() {
+        let description = parse_error("truncated", "(serverType:Vanilla,\nbonusChest:tr");
+        assert!(
+            description.contains("msc-configuration.ron:2:"),
+            "{}",
+            description
+        );
+        assert!(description.contains("2 | bonusChest:tr"), "{}", description);
+    }
+
+    #[test]
+    fn mistyped_configuration_is_a_parse_error_with_its_position() {
+        let description = parse_error("mistyped", "(\n    port: \"25565\",\n)");
+        assert!(
+            description.contains("msc-configuration.ron:2:"),
+            "{}",
+            description
+        );
+        assert!(
+            description.contains("2 |     port: \"25565\","),
+            "{}",
+            description
+        );
+        assert!(description.contains('^'), "{}", description);
+    }
+
+    fn machine_with_changes(configuration_path: PathBuf) -> Machine {
+        Machine {
+            state: AppState::ChoiceMenu,
+            editor_state: None,
+            selected_configuration_option: None,
+            configuration: MinecraftServerConfiguration {
+                port: Some(25570),
+                ..MinecraftServerConfiguration::default()
+            },
+            instance: DEFAULT_INSTANCE.to_string(),
+            configuration_path,
+            dirty: true,
+            history: Vec::new(),
+            trace_transitions: false,
+            audit_entries: Vec::new(),
+        }
+    }
+
+    // Goes through the same steps as run: the server starts, it stops, another option is edited,
+    // and the menu exits. Returns what became of the changes at each of the two points.
+    fn start_edit_and_exit(
+        machine: &mut Machine,
+        no_save_on_start: bool,
+    ) -> (PendingChanges, PendingChanges) {
+        let on_start = settle_pending_changes(machine, no_save_on_start, true).unwrap();
+        machine.configuration.port = Some(25575);
+        machine.dirty = true;
+        let on_exit = settle_pending_changes(machine, no_save_on_start, true).unwrap();
+        (on_start, on_exit)
+    }
+
+    #[test]
+    fn no_save_on_start_discards_changes_once_the_server_started() {
+        let directory = scratch_directory("no-save-on-start");
+        let path = directory.join(CONFIGURATION_FILENAME);
+        fs::write(&path, "(port:Some(25565))").unwrap();
+        let mut machine = machine_with_changes(path.clone());
+
+        assert_eq!(
+            start_edit_and_exit(&mut machine, true),
+            (PendingChanges::Discarded, PendingChanges::Discarded)
+        );
+        assert!(machine.dirty);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "(port:Some(25565))");
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn no_save_on_start_still_saves_when_the_server_never_started() {
+        let directory = scratch_directory("no-save-without-start");
+        let path = directory.join(CONFIGURATION_FILENAME);
+        fs::write(&path, "(port:Some(25565))").unwrap();
+        let mut machine = machine_with_changes(path.clone());
+
+        assert_eq!(
+            settle_pending_changes(&mut machine, true, false).unwrap(),
+            PendingChanges::Saved
+        );
+        assert!(!machine.dirty);
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("port:Some(25570)"));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn pending_changes_are_saved_on_start_and_exit_without_no_save_on_start() {
+        let directory = scratch_directory("save-on-start");
+        let path = directory.join(CONFIGURATION_FILENAME);
+        fs::write(&path, "(port:Some(25565))").unwrap();
+        let mut machine = machine_with_changes(path.clone());
+
+        assert_eq!(
+            start_edit_and_exit(&mut machine, false),
+            (PendingChanges::Saved, PendingChanges::Saved)
+        );
+        assert!(!machine.dirty);
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("port:Some(25575)"));
+        assert_eq!(
+            settle_pending_changes(&mut machine, false, true).unwrap(),
+            PendingChanges::None
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn write_atomically_replaces_the_file() {
+        let directory = scratch_directory("atomic-write");
+        let path = directory.join(CONFIGURATION_FILENAME);
+        fs::write(&path, "old").unwrap();
+
+        write_atomically(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(!directory.join("msc-configuration.ron.tmp").exists());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn write_atomically_leaves_the_original_when_the_write_fails() {
+        let directory = scratch_directory("atomic-write-fails");
+        let path = directory.join(CONFIGURATION_FILENAME);
+        fs::write(&path, "old").unwrap();
+        // The temporary file can't be created where a directory is in the way.
+        let temporary_path = directory.join("msc-configuration.ron.tmp");
+        fs::create_dir(&temporary_path).unwrap();
+        fs::write(temporary_path.join("keep"), "").unwrap();
+
+        assert!(write_atomically(&path, "new").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn write_atomically_removes_the_temporary_file_when_the_rename_fails() {
+        let directory = scratch_directory("atomic-rename-fails");
+        // A file can't be renamed over a directory, so the rename fails after the write succeeded.
+        let path = directory.join(CONFIGURATION_FILENAME);
+        fs::create_dir(&path).unwrap();
+        fs::write(path.join("old"), "old").unwrap();
+
+        assert!(write_atomically(&path, "new").is_err());
+        assert!(!directory.join("msc-configuration.ron.tmp").exists());
+        assert_eq!(fs::read_to_string(path.join("old")).unwrap(), "old");
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn server_command_keeps_arguments_with_spaces_and_quotes_intact() {
+        let configuration = MinecraftServerConfiguration {
+            javaPath: Some("/opt/my java/bin/java".to_string()),
+            maxMemory: Some(MemorySize { megabytes: 2048 }),
+            universe: Some("my worlds".to_string()),
+            world: Some("Steve's \"best\" world = 1".to_string()),
+            plainLogOutput: false,
+            systemProperties: vec![
+                (
+                    "log4j.configurationFile".to_string(),
+                    "my log4j.xml".to_string(),
+                ),
+                ("motd".to_string(), "a=b \"c\" 'd' $HOME".to_string()),
+            ],
+            ..MinecraftServerConfiguration::default()
+        };
+        let arguments = build_command(
+            DEFAULT_INSTANCE,
+            &configuration,
+            "server \"1.20\" = final.jar",
+            &LaunchOptions::default(),
+        );
+        let command = server_command(&arguments);
+
+        assert_eq!(command.get_program(), "/opt/my java/bin/java");
+        let arguments: Vec<&std::ffi::OsStr> = command.get_args().collect();
+        assert_eq!(
+            arguments,
+            vec![
+                "-Xmx2G",
+                "-Dlog4j.configurationFile=my log4j.xml",
+                "-Dmotd=a=b \"c\" 'd' $HOME",
+                "-jar",
+                "server \"1.20\" = final.jar",
+                "--bonusChest",
+                "--nogui",
+                "--universe",
+                "my worlds",
+                "--world",
+                "Steve's \"best\" world = 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn distances_are_edited_as_numbers_from_3_to_32() {
+        for option in get_property_option_info()
+            .into_iter()
+            .filter(|option| find_tunable_property(&option.property).is_some())
+        {
+            let validator = option.validator.clone().unwrap();
+            assert!(validator.check("3").is_ok());
+            assert!(validator.check("32").is_ok());
+            assert!(validator.check("2").is_err());
+            assert!(validator.check("33").is_err());
+            assert!(validator.check("far").is_err());
+
+            let mut machine = machine_with_changes(PathBuf::from(CONFIGURATION_FILENAME));
+            machine.dispatch(
+                Event::AppEvent(AppEvent::SelectedOption),
+                Some(Payload::ConfigurationOption(option)),
+            );
+            assert_eq!(machine.editor_state, Some(EditorState::NumberInput));
+        }
+    }
+
+    #[test]
+    fn debug_jvm_logs_gc_to_a_file_per_instance() {
+        let launch_options = LaunchOptions {
+            debug_jvm: true,
+            ..LaunchOptions::default()
+        };
+        let arguments = build_command(
+            DEFAULT_INSTANCE,
+            &MinecraftServerConfiguration::default(),
+            "server.jar",
+            &launch_options,
+        );
+        let gc_log = format!("-Xlog:gc*:file={}", gc_log_filename(DEFAULT_INSTANCE));
+        assert!(arguments.contains(&"-ea".to_string()));
+        assert!(arguments.contains(&"-verbose:gc".to_string()));
+        assert!(arguments.contains(&gc_log));
+        // Instances that share the server's folder don't write to the same log.
+        let other = build_command(
+            "creative",
+            &MinecraftServerConfiguration::default(),
+            "server.jar",
+            &launch_options,
+        );
+        assert!(other.contains(&"-Xlog:gc*:file=logs/gc-creative.log".to_string()));
+        assert!(!other.contains(&gc_log));
+    }
+
+    #[test]
+    fn key_value_lists_can_be_reset_to_default() {
+        let mut machine = machine_with_changes(PathBuf::from(CONFIGURATION_FILENAME));
+        machine.configuration.systemProperties = vec![("motd".to_string(), "hi".to_string())];
+        let option = get_config_option_info()
+            .into_iter()
+            .find(|option| option.property == "systemProperties")
+            .unwrap();
+        machine.dispatch(
+            Event::AppEvent(AppEvent::SelectedOption),
+            Some(Payload::ConfigurationOption(option)),
+        );
+        assert_eq!(machine.editor_state, Some(EditorState::KeyValueList));
+
+        machine.dispatch(Event::EditorEvent(EditorEvent::ResetToDefault), None);
+        assert_eq!(machine.editor_state, None);
+        assert_eq!(machine.state, AppState::ChoiceMenu);
+        assert!(machine.configuration.systemProperties.is_empty());
+    }
+
+    #[test]
+    fn saving_an_instance_keeps_the_others() {
+        let directory = scratch_directory("save-keeps-instances");
+        let path = directory.join(CONFIGURATION_FILENAME);
+        save_configuration(&path, "creative", MinecraftServerConfiguration::default()).unwrap();
+        save_configuration(
+            &path,
+            DEFAULT_INSTANCE,
+            MinecraftServerConfiguration::default(),
+        )
+        .unwrap();
+
+        let instances = read_configuration_file(&path).unwrap().instances;
+        assert!(instances.contains_key("creative"));
+        assert!(instances.contains_key(DEFAULT_INSTANCE));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn saving_over_an_unparsable_file_leaves_it_alone() {
+        let directory = scratch_directory("save-unparsable");
+        let path = directory.join(CONFIGURATION_FILENAME);
+        fs::write(&path, "(instances: {\"a\": (port: Some(1)), \"b\": (").unwrap();
+
+        let result = save_configuration(&path, "a", MinecraftServerConfiguration::default());
+        assert!(matches!(result, Err(MscError::ConfigParse { .. })));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "(instances: {\"a\": (port: Some(1)), \"b\": ("
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn session_servers_warn_about_what_they_ignore() {
+        let configuration = MinecraftServerConfiguration {
+            dailyRestartTime: Some("04:00".to_string()),
+            startupCommands: vec!["say hi".to_string()],
+            ..MinecraftServerConfiguration::default()
+        };
+        let session = LaunchOptions {
+            session: Some(Multiplexer::Tmux),
+            ..LaunchOptions::default()
+        };
+        let diagnostics = session_diagnostics(&configuration, &session);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert!(diagnostics[0]
+            .message
+            .starts_with("dailyRestartTime, startupCommands are ignored"));
+
+        assert!(session_diagnostics(&configuration, &LaunchOptions::default()).is_empty());
+        assert!(session_diagnostics(&MinecraftServerConfiguration::default(), &session).is_empty());
+    }
+
+    #[test]
+    fn lines_that_arent_utf8_are_kept_and_reading_goes_on() {
+        let output: &[u8] = b"[Server] Starting\r\nCaf\xe9 opened\n\xff\nDone (1.0s)!";
+        let lines: Vec<String> = lossy_lines(output).collect();
+        assert_eq!(
+            lines,
+            [
+                "[Server] Starting",
+                "Caf\u{fffd} opened",
+                "\u{fffd}",
+                "Done (1.0s)!"
+            ]
+        );
+    }
+
+    #[test]
+    fn audit_entries_leave_out_secrets() {
+        let mut machine = machine_with_changes(PathBuf::from(CONFIGURATION_FILENAME));
+        machine.configuration.auditLog = true;
+        let hook = get_config_option_info()
+            .into_iter()
+            .find(|option| option.property == "preStartHook")
+            .unwrap();
+        machine.dispatch(
+            Event::AppEvent(AppEvent::SelectedOption),
+            Some(Payload::ConfigurationOption(hook)),
+        );
+        machine.dispatch(Event::EditorEvent(EditorEvent::SelectedValue), None);
+        machine.dispatch(
+            Event::EditorEvent(EditorEvent::SubmitValue),
+            Some(Payload::ConfigurationOptionType(
+                ConfigurationOptionType::OptionString(Some(
+                    "curl https://example.com/hook?token=hunter2".to_string(),
+                )),
+            )),
+        );
+        assert_eq!(
+            machine.audit_entries,
+            [AuditEntry {
+                property: "preStartHook".to_string(),
+                old: "None".to_string(),
+                new: "Some(\"<redacted>\")".to_string(),
+            }]
+        );
+        assert_eq!(
+            redact_property_value("rcon.password", "hunter2"),
+            "<redacted>"
+        );
+        assert_eq!(redact_property_value("server-port", "25565"), "25565");
+    }
+
+    #[test]
+    fn secret_properties_only_show_that_they_changed() {
+        let line = describe_property_change(PropertyChange::Changed {
+            key: "rcon.password".to_string(),
+            old: "hunter2".to_string(),
+            new: "correct-horse".to_string(),
+        });
+        assert!(
+            line.contains("rcon.password: <redacted> → <redacted>"),
+            "{}",
+            line
+        );
+        let line = describe_property_change(PropertyChange::Added {
+            key: "motd".to_string(),
+            value: "Welcome".to_string(),
+        });
+        assert!(line.contains("+ motd=Welcome"), "{}", line);
+    }
+
+    #[test]
+    fn duplicating_over_a_profile_replaces_all_of_it() {
+        let directory = scratch_directory("replace-profile");
+        let path = directory.join(profile_filename("creative"));
+        fs::write(
+            &path,
+            "(instances:{\"default\":(port:Some(25565)),\"lobby\":(port:Some(25566))})",
+        )
+        .unwrap();
+        replace_profile(
+            &path,
+            MinecraftServerConfiguration {
+                port: Some(25570),
+                ..MinecraftServerConfiguration::default()
+            },
+        )
+        .unwrap();
+        let instances = read_configuration_file(&path).unwrap().instances;
+        assert_eq!(instances.keys().collect::<Vec<_>>(), [DEFAULT_INSTANCE]);
+        assert_eq!(instances[DEFAULT_INSTANCE].port, Some(25570));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn java_mismatches_say_which_java_to_install() {
+        assert_eq!(
+            format_java_mismatch(&JavaVersionMismatch {
+                required: 21,
+                current: Some(17),
+            }),
+            "This server requires a newer Java version: you have Java 17, but it needs Java 21. Install Java 21 or newer, or set the Java executable option to one."
+        );
+        assert_eq!(
+            format_java_mismatch(&JavaVersionMismatch {
+                required: 8,
+                current: None,
+            }),
+            "This server requires a newer Java version: it needs Java 8. Install Java 8 or newer, or set the Java executable option to one."
+        );
+    }
+
+    #[test]
+    fn validation_reports_list_their_diagnostics_as_json() {
+        let report = ValidationReport {
+            diagnostics: vec![
+                Diagnostic {
+                    severity: Severity::Warning,
+                    message: "The port is below 1024.".to_string(),
+                },
+                Diagnostic {
+                    severity: Severity::Error,
+                    message: "The jar \"server.jar\" doesn't exist.".to_string(),
+                },
+            ],
+        };
+        assert_eq!(
+            report.json().to_string(),
+            r#"{"valid":false,"diagnostics":[{"severity":"warning","message":"The port is below 1024."},{"severity":"error","message":"The jar \"server.jar\" doesn't exist."}]}"#
+        );
+        let empty = ValidationReport {
+            diagnostics: Vec::new(),
+        };
+        assert_eq!(
+            empty.json().to_string(),
+            r#"{"valid":true,"diagnostics":[]}"#
+        );
+    }
+
+    #[test]
+    fn add_ons_are_counted_and_listed_when_there_are_any() {
+        assert_eq!(
+            format_add_ons(vec![
+                (
+                    "Plugins",
+                    vec!["EssentialsX.jar".to_string(), "WorldEdit.jar".to_string()]
+                ),
+                ("Datapacks", Vec::new()),
+            ]),
+            ["Plugins (2): EssentialsX.jar, WorldEdit.jar"]
+        );
+        assert!(format_add_ons(vec![("Plugins", Vec::new())]).is_empty());
+    }
+
+    #[test]
+    fn argfile_mode_lists_the_command_and_the_file_separately() {
+        let configuration = MinecraftServerConfiguration::default();
+        let java = configured_java(&configuration);
+        let arguments: Vec<String> = [
+            java.as_str(),
+            "-Xmx2G",
+            "-Dmotd=A server",
+            "-jar",
+            "server.jar",
+        ]
+        .iter()
+        .map(|argument| argument.to_string())
+        .collect();
+
+        let direct =
+            launch_argument_sections(&configuration, &arguments, &LaunchOptions::default());
+        assert_eq!(direct.len(), 1);
+        assert_eq!(
+            direct[0].1,
+            [
+                format!("0  {}", java),
+                "1  -Xmx2G".to_string(),
+                "2  -Dmotd=A server".to_string(),
+                "3  -jar".to_string(),
+                "4  server.jar".to_string(),
+            ]
+        );
+
+        let launch_options = LaunchOptions {
+            argfile: true,
+            ..LaunchOptions::default()
+        };
+        let argfile = launch_argument_sections(&configuration, &arguments, &launch_options);
+        assert_eq!(
+            argfile[0].1,
+            [format!("0  {}", java), format!("1  @{}", ARGFILE_FILENAME)]
+        );
+        assert_eq!(
+            argfile[1].0,
+            format!(
+                "--argfile was passed, so Java reads these arguments from {}:",
+                ARGFILE_FILENAME
+            )
+        );
+        assert_eq!(
+            argfile[1].1,
+            [
+                "0  -Xmx2G",
+                "1  -Dmotd=A server",
+                "2  -jar",
+                "3  server.jar"
+            ]
+        );
+    }
+
+    #[test]
+    fn each_server_gets_its_own_folder_in_the_configuration_directory() {
+        let configuration_directory = Path::new("/home/steve/.config/minecraft-server-cli");
+        let survival =
+            server_configuration_directory(configuration_directory, Path::new("/srv/survival"));
+        assert_eq!(survival.parent(), Some(configuration_directory));
+        let folder = survival.file_name().unwrap().to_str().unwrap().to_string();
+        assert!(folder.starts_with("survival-"), "{}", folder);
+
+        // The same every time, and different for servers with the same folder name elsewhere.
+        assert_eq!(
+            server_configuration_directory(configuration_directory, Path::new("/srv/survival")),
+            survival
+        );
+        assert_ne!(
+            server_configuration_directory(configuration_directory, Path::new("/opt/survival")),
+            survival
+        );
+        let odd =
+            server_configuration_directory(configuration_directory, Path::new("/srv/my world.1"));
+        assert!(odd
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("my_world_1-"));
+        let root = server_configuration_directory(configuration_directory, Path::new("/"));
+        assert_eq!(root.file_name().unwrap().len(), 8);
+    }
 }
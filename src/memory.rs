@@ -0,0 +1,244 @@
+use std::fmt;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+// An amount of memory for the JVM's heap, normalized to megabytes.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct MemorySize {
+    pub megabytes: u32,
+}
+
+// Parses values like "2G", "512M", "512MB", or "1024" (megabytes when no unit is given).
+pub fn parse_memory(value: &str) -> Result<MemorySize, String> {
+    let value = value.trim();
+    let digits_end = value
+        .find(|character: char| !character.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(digits_end);
+    if amount.is_empty() {
+        return Err(format!(
+            "\"{}\" is not an amount of memory. Use a whole number with an optional unit, e.g. 2G or 512M.",
+            value
+        ));
+    }
+
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "M" | "MB" => 1,
+        "G" | "GB" => 1024,
+        "T" | "TB" => 1024 * 1024,
+        unit => {
+            return Err(format!(
+                "\"{}\" is not a memory unit. Use M for megabytes or G for gigabytes.",
+                unit
+            ))
+        }
+    };
+    let megabytes = amount
+        .parse::<u32>()
+        .ok()
+        .and_then(|amount| amount.checked_mul(multiplier))
+        .ok_or_else(|| format!("{} is too much memory.", value))?;
+    if megabytes == 0 {
+        return Err("The amount of memory must be greater than 0.".to_string());
+    }
+
+    Ok(MemorySize { megabytes })
+}
+
+// The machine's physical memory in megabytes, on the platforms where we know how to find it out.
+#[cfg(target_os = "linux")]
+fn total_memory_mb() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let kilobytes = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes / 1024)
+}
+
+#[cfg(target_os = "macos")]
+fn total_memory_mb() -> Option<u64> {
+    let mut bytes: u64 = 0;
+    let mut size = std::mem::size_of::<u64>();
+    let result = unsafe {
+        libc::sysctlbyname(
+            b"hw.memsize\0".as_ptr() as *const libc::c_char,
+            &mut bytes as *mut u64 as *mut libc::c_void,
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if result == 0 {
+        Some(bytes / 1024 / 1024)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn total_memory_mb() -> Option<u64> {
+    None
+}
+
+// The memory limit of the cgroup that we run in, when it's lower than the machine's memory. cgroup v2
+// writes "max" when there's no limit, while v1 writes a huge number instead. Both are read, since
+// hybrid setups have both, and the lower limit is the one that applies.
+#[cfg(target_os = "linux")]
+fn cgroup_memory_limit_mb() -> Option<u64> {
+    let limit = lowest_memory_limit_mb(
+        [
+            "/sys/fs/cgroup/memory.max",
+            "/sys/fs/cgroup/memory/memory.limit_in_bytes",
+        ]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok()),
+    )?;
+    match total_memory_mb() {
+        Some(total) if limit < total => Some(limit),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cgroup_memory_limit_mb() -> Option<u64> {
+    None
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn lowest_memory_limit_mb(limits: impl Iterator<Item = String>) -> Option<u64> {
+    limits
+        .filter_map(|limit| limit.trim().parse::<u64>().ok())
+        .map(|bytes| bytes / 1024 / 1024)
+        .min()
+}
+
+// Guesses whether we run in a container from the files that Docker and Podman create, or from a
+// memory limit on our cgroup.
+#[cfg(target_os = "linux")]
+pub fn is_running_in_container() -> bool {
+    std::path::Path::new("/.dockerenv").exists()
+        || std::path::Path::new("/run/.containerenv").exists()
+        || cgroup_memory_limit_mb().is_some()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn is_running_in_container() -> bool {
+    false
+}
+
+// The share of the container's memory that --container lets the heap use by default, which leaves
+// room for the JVM's own memory outside of the heap.
+pub const DEFAULT_MAX_RAM_PERCENTAGE: u8 = 75;
+
+// The maximum memory used when none is configured: half of the memory that we may use in whole
+// gigabytes, between 1G and 8G (past which garbage collection pauses tend to get worse rather than
+// better), or 2G when the memory is unknown. That's the machine's memory, or the cgroup's limit when
+// it's lower, e.g. in a container. With less than 2G, the heap gets half of it in megabytes, since
+// 1G would leave no room for the JVM itself.
+pub fn suggested_heap_mb() -> u16 {
+    heap_for_memory_mb(cgroup_memory_limit_mb().or_else(total_memory_mb))
+}
+
+fn heap_for_memory_mb(memory: Option<u64>) -> u16 {
+    match memory {
+        Some(memory) if memory < 2048 => (memory / 2).max(1) as u16,
+        Some(memory) => (memory / 2 / 1024 * 1024).clamp(1024, 8192) as u16,
+        None => 2048,
+    }
+}
+
+pub fn suggested_heap() -> MemorySize {
+    MemorySize {
+        megabytes: suggested_heap_mb() as u32,
+    }
+}
+
+// Uses the largest unit that represents the size exactly, e.g. 2048 megabytes is "2G". This is also
+// the format that the JVM expects for -Xmx and -Xms.
+impl fmt::Display for MemorySize {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if self.megabytes.is_multiple_of(1024) {
+            write!(formatter, "{}G", self.megabytes / 1024)
+        } else {
+            write!(formatter, "{}M", self.megabytes)
+        }
+    }
+}
+
+// Memory sizes are stored as strings like "2G" so that the configuration stays easy to edit by hand.
+impl Serialize for MemorySize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for MemorySize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        parse_memory(&value).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_heap_is_half_the_memory_within_bounds() {
+        assert_eq!(heap_for_memory_mb(None), 2048);
+        assert_eq!(heap_for_memory_mb(Some(64 * 1024)), 8192);
+        assert_eq!(heap_for_memory_mb(Some(7 * 1024)), 3072);
+        assert_eq!(heap_for_memory_mb(Some(3 * 1024)), 1024);
+        assert_eq!(heap_for_memory_mb(Some(1024)), 512);
+    }
+
+    #[test]
+    fn the_lowest_cgroup_limit_applies() {
+        let limits = ["max\n", "4294967296\n", "9223372036854771712\n"];
+        assert_eq!(
+            lowest_memory_limit_mb(limits.iter().map(|limit| limit.to_string())),
+            Some(4096)
+        );
+        assert_eq!(
+            lowest_memory_limit_mb(std::iter::once("max\n".to_string())),
+            None
+        );
+    }
+
+    #[test]
+    fn memory_values_are_parsed_in_megabytes() {
+        let megabytes = |value: &str| parse_memory(value).map(|size| size.megabytes);
+        assert_eq!(megabytes("1024"), Ok(1024));
+        assert_eq!(megabytes("512M"), Ok(512));
+        assert_eq!(megabytes(" 512mb "), Ok(512));
+        assert_eq!(megabytes("2G"), Ok(2048));
+        assert_eq!(megabytes("2 GB"), Ok(2048));
+        assert_eq!(megabytes("1T"), Ok(1024 * 1024));
+        assert!(megabytes("G").is_err());
+        assert!(megabytes("1.5G").is_err());
+        assert!(megabytes("2K").is_err());
+        assert!(megabytes("0G").is_err());
+        assert!(megabytes("-1G").is_err());
+        assert!(megabytes("5000T").is_err());
+    }
+
+    #[test]
+    fn memory_sizes_are_written_in_the_largest_exact_unit() {
+        assert_eq!(MemorySize { megabytes: 2048 }.to_string(), "2G");
+        assert_eq!(MemorySize { megabytes: 1536 }.to_string(), "1536M");
+        assert_eq!(
+            ron::to_string(&MemorySize { megabytes: 512 }).unwrap(),
+            "\"512M\""
+        );
+        assert_eq!(
+            ron::from_str::<MemorySize>("\"4G\"").unwrap(),
+            MemorySize { megabytes: 4096 }
+        );
+        assert!(ron::from_str::<MemorySize>("\"4 bananas\"").is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use std::io;
+
+use console::{Key, Term};
+
+use crate::theme::theme;
+
+// What was picked in a menu: an item with Enter, or one of the shortcut keys.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MenuChoice {
+    Item(usize),
+    Key(char),
+}
+
+fn render(formatted: Result<String, std::fmt::Error>) -> io::Result<String> {
+    formatted.map_err(|error| io::Error::other(error.to_string()))
+}
+
+// The first item to show so that the selected one stays in view, scrolling as little as possible
+// from `top`.
+fn scroll_top(top: usize, selected: usize, height: usize) -> usize {
+    if selected < top {
+        selected
+    } else if selected >= top + height {
+        selected + 1 - height
+    } else {
+        top
+    }
+}
+
+// A select prompt drawn with the same theme as dialoguer's, which can't report other keys. Pressing
+// one of `keys` returns it right away instead of an item. Menus that are taller than the terminal
+// show a page of items at a time that scrolls with the selection.
+pub fn select_or_key(
+    terminal: &Term,
+    prompt: &str,
+    items: &[String],
+    keys: &[char],
+) -> io::Result<MenuChoice> {
+    let prompt_line = render({
+        let mut line = String::new();
+        theme()
+            .format_select_prompt(&mut line, prompt)
+            .map(|()| line)
+    })?;
+    terminal.write_line(&prompt_line)?;
+
+    // Rows are kept for the prompt, the footer, and the line the cursor ends up on.
+    let height = (terminal.size().0 as usize)
+        .saturating_sub(3)
+        .max(1)
+        .min(items.len());
+    let mut selected = 0;
+    let mut top = 0;
+    loop {
+        top = scroll_top(top, selected, height);
+        for (index, item) in items.iter().enumerate().skip(top).take(height) {
+            let line = render({
+                let mut line = String::new();
+                theme()
+                    .format_select_prompt_item(&mut line, item, index == selected)
+                    .map(|()| line)
+            })?;
+            terminal.write_line(&line)?;
+        }
+        let paged = height < items.len();
+        if paged {
+            terminal.write_line(
+                &console::style(format!(
+                    "{}-{} of {}. Use ↑/↓ or PageUp/PageDown to see more.",
+                    top + 1,
+                    top + height,
+                    items.len()
+                ))
+                .dim()
+                .to_string(),
+            )?;
+        }
+        terminal.hide_cursor()?;
+        terminal.flush()?;
+
+        let key = terminal.read_key()?;
+        terminal.clear_last_lines(height + paged as usize)?;
+        match key {
+            Key::ArrowDown | Key::Char('j') => selected = (selected + 1) % items.len(),
+            Key::ArrowUp | Key::Char('k') => selected = (selected + items.len() - 1) % items.len(),
+            Key::PageDown => selected = (selected + height).min(items.len() - 1),
+            Key::PageUp => selected = selected.saturating_sub(height),
+            Key::Home => selected = 0,
+            Key::End => selected = items.len() - 1,
+            Key::Enter | Key::Char(' ') => {
+                let selection_line = render({
+                    let mut line = String::new();
+                    theme()
+                        .format_select_prompt_selection(&mut line, prompt, &items[selected])
+                        .map(|()| line)
+                })?;
+                terminal.clear_last_lines(1)?;
+                terminal.write_line(&selection_line)?;
+                terminal.show_cursor()?;
+                return Ok(MenuChoice::Item(selected));
+            }
+            Key::Char(character) if keys.contains(&character) => {
+                terminal.show_cursor()?;
+                return Ok(MenuChoice::Key(character));
+            }
+            _ => {}
+        }
+    }
+}
+
+// Breaks a line at spaces so that it fits in `width` columns, splitting words that are longer.
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in line.split_whitespace() {
+        let mut word = word.to_string();
+        while word.chars().count() > width {
+            if !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            let rest = word.split_off(
+                word.char_indices()
+                    .nth(width)
+                    .map_or(word.len(), |(index, _)| index),
+            );
+            lines.push(word);
+            word = rest;
+        }
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(&word);
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+// Shows a long text a screen at a time. Returns true once the reader has scrolled to the end and
+// pressed Enter there, or false if they quit before that.
+pub fn page_text(terminal: &Term, text: &str) -> io::Result<bool> {
+    let (rows, columns) = terminal.size();
+    let lines: Vec<String> = text
+        .lines()
+        .flat_map(|line| wrap_line(line, (columns as usize).max(20)))
+        .collect();
+    // One row is kept for the footer.
+    let height = (rows as usize).saturating_sub(1).max(1);
+    let last_top = lines.len().saturating_sub(height);
+
+    let mut top = 0;
+    terminal.hide_cursor()?;
+    loop {
+        terminal.clear_screen()?;
+        for line in lines.iter().skip(top).take(height) {
+            terminal.write_line(line)?;
+        }
+        let at_end = top >= last_top;
+        let footer = if at_end {
+            "End of the text. Press Enter to continue, or q to quit.".to_string()
+        } else {
+            format!(
+                "Lines {}-{} of {}. Use ↓/Space to scroll, or q to quit.",
+                top + 1,
+                top + height,
+                lines.len()
+            )
+        };
+        terminal.write_str(&console::style(footer).reverse().to_string())?;
+        terminal.flush()?;
+
+        match terminal.read_key()? {
+            Key::ArrowDown | Key::Char('j') => top = (top + 1).min(last_top),
+            Key::ArrowUp | Key::Char('k') => top = top.saturating_sub(1),
+            Key::Char(' ') | Key::PageDown => top = (top + height).min(last_top),
+            Key::PageUp => top = top.saturating_sub(height),
+            Key::Home => top = 0,
+            Key::End => top = last_top,
+            Key::Enter if at_end => break,
+            Key::Char('q') | Key::Escape => {
+                terminal.clear_screen()?;
+                terminal.show_cursor()?;
+                return Ok(false);
+            }
+            _ => {}
+        }
+    }
+    terminal.clear_screen()?;
+    terminal.show_cursor()?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_page_follows_the_selection() {
+        // Moving within the page doesn't scroll.
+        assert_eq!(scroll_top(0, 9, 10), 0);
+        assert_eq!(scroll_top(0, 10, 10), 1);
+        assert_eq!(scroll_top(5, 3, 10), 3);
+        // Wrapping around from the last item to the first, and back.
+        assert_eq!(scroll_top(40, 0, 10), 0);
+        assert_eq!(scroll_top(0, 49, 10), 40);
+    }
+
+    #[test]
+    fn lines_wrap_at_spaces_and_split_long_words() {
+        assert_eq!(wrap_line("a short line", 20), ["a short line"]);
+        assert_eq!(
+            wrap_line("the quick brown fox jumps", 10),
+            ["the quick", "brown fox", "jumps"]
+        );
+        assert_eq!(
+            wrap_line("see https://example.com/eula", 10),
+            ["see", "https://ex", "ample.com/", "eula"]
+        );
+        // Blank lines are kept so that paragraphs stay apart.
+        assert_eq!(wrap_line("", 10), [""]);
+        assert_eq!(wrap_line("ééééé", 2), ["éé", "éé", "é"]);
+    }
+}
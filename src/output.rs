@@ -0,0 +1,108 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::clock::DateTime;
+
+// Prepended to each line of the server's output, e.g. "[{time}] [{instance}] ". The placeholders
+// {date}, {time}, and {instance} are filled in for every line.
+#[derive(Debug, Clone)]
+pub struct OutputPrefix {
+    pub format: String,
+    pub instance: String,
+    // Whether the console mirror gets the prefix too, instead of the server's raw output.
+    pub mirror: bool,
+}
+
+pub const DEFAULT_PREFIX_FORMAT: &str = "[{time}] [{instance}] ";
+
+impl OutputPrefix {
+    pub fn apply(&self, line: &str) -> String {
+        let now = DateTime::now();
+        let prefix = self
+            .format
+            .replace("{date}", &now.date())
+            .replace("{time}", &now.time())
+            .replace("{instance}", &self.instance);
+        format!("{}{}", prefix, line)
+    }
+}
+
+// Mirrors the server's console to a FIFO that external tools can read from. Writing never blocks:
+// lines are dropped while nothing is reading from the FIFO, and the FIFO is reopened once a reader
+// connects again. On Windows, where there are no FIFOs, the console is appended to a regular file.
+pub struct ConsoleMirror {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl ConsoleMirror {
+    #[cfg(unix)]
+    pub fn create(path: &Path) -> io::Result<ConsoleMirror> {
+        use std::{ffi::CString, os::unix::ffi::OsStrExt, os::unix::fs::FileTypeExt};
+
+        match path.metadata() {
+            Ok(metadata) if metadata.file_type().is_fifo() => {}
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a FIFO.", path.display()),
+                ))
+            }
+            Err(_) => {
+                let c_path = CString::new(path.as_os_str().as_bytes())
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+                if unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) } != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+        }
+
+        Ok(ConsoleMirror {
+            path: path.to_path_buf(),
+            file: None,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn create(path: &Path) -> io::Result<ConsoleMirror> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(ConsoleMirror {
+            path: path.to_path_buf(),
+            file: Some(file),
+        })
+    }
+
+    // Opening a FIFO for writing without a reader fails immediately with O_NONBLOCK instead of
+    // waiting for one to appear.
+    #[cfg(unix)]
+    fn open(&self) -> io::Result<File> {
+        use std::os::unix::fs::OpenOptionsExt;
+        OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&self.path)
+    }
+
+    #[cfg(not(unix))]
+    fn open(&self) -> io::Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+    }
+
+    pub fn write_line(&mut self, line: &str) {
+        if self.file.is_none() {
+            self.file = self.open().ok();
+        }
+        if let Some(file) = self.file.as_mut() {
+            if writeln!(file, "{}", line).is_err() {
+                // The reader went away (or isn't keeping up), try again with the next line.
+                self.file = None;
+            }
+        }
+    }
+}
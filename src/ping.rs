@@ -0,0 +1,206 @@
+use std::{
+    io::{self, Error, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpStream},
+    time::{Duration, Instant},
+};
+
+// What a server reports about itself through the Server List Ping (the same request the multiplayer
+// screen makes).
+#[derive(Debug, Clone)]
+pub struct ServerStatus {
+    pub version: Option<String>,
+    pub online_players: Option<u64>,
+    pub max_players: Option<u64>,
+    pub latency: Duration,
+}
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// The status is a string of at most 32767 characters, each of which takes up to 4 bytes in UTF-8.
+const MAX_STATUS_LENGTH: i32 = 32767 * 4;
+
+fn write_varint(buffer: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7F == 0 {
+            buffer.push(value as u8);
+            return;
+        }
+        buffer.push((value & 0x7F | 0x80) as u8);
+        value >>= 7;
+    }
+}
+
+fn read_varint(stream: &mut impl Read) -> io::Result<i32> {
+    let mut result = 0u32;
+    for position in 0..5 {
+        let mut byte = [0u8];
+        stream.read_exact(&mut byte)?;
+        result |= ((byte[0] & 0x7F) as u32) << (7 * position);
+        if byte[0] & 0x80 == 0 {
+            return Ok(result as i32);
+        }
+    }
+    Err(Error::new(
+        ErrorKind::InvalidData,
+        "The server sent a malformed VarInt.",
+    ))
+}
+
+fn write_packet(stream: &mut TcpStream, id: i32, data: &[u8]) -> io::Result<()> {
+    let mut body = Vec::new();
+    write_varint(&mut body, id);
+    body.extend_from_slice(data);
+    let mut packet = Vec::new();
+    write_varint(&mut packet, body.len() as i32);
+    packet.extend_from_slice(&body);
+    stream.write_all(&packet)
+}
+
+// Finds the value following `"key":` in a JSON document, which is enough to pick a few fields out of
+// the status response without a full JSON parser.
+pub fn json_value_after<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let start = json.find(&format!("\"{}\"", key))? + key.len() + 2;
+    let rest = json[start..].trim_start().strip_prefix(':')?.trim_start();
+    if let Some(string) = rest.strip_prefix('"') {
+        string.find('"').map(|end| &string[..end])
+    } else {
+        let end = rest
+            .find(|character: char| !character.is_ascii_digit())
+            .unwrap_or(rest.len());
+        Some(&rest[..end])
+    }
+}
+
+pub fn ping_server(address: SocketAddr) -> Result<ServerStatus, Error> {
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    // Handshake: protocol version (-1 when only asking for the status), address, port, next state.
+    let host = address.ip().to_string();
+    let mut handshake = Vec::new();
+    write_varint(&mut handshake, -1);
+    write_varint(&mut handshake, host.len() as i32);
+    handshake.extend_from_slice(host.as_bytes());
+    handshake.extend_from_slice(&address.port().to_be_bytes());
+    write_varint(&mut handshake, 1);
+    write_packet(&mut stream, 0x00, &handshake)?;
+
+    let requested_at = Instant::now();
+    write_packet(&mut stream, 0x00, &[])?;
+
+    let _length = read_varint(&mut stream)?;
+    let latency = requested_at.elapsed();
+    let json = read_status_json(&mut stream)?;
+    Ok(parse_status(&json, latency))
+}
+
+// Reads the rest of the status response after its length: the packet ID and the JSON document.
+fn read_status_json(stream: &mut impl Read) -> Result<String, Error> {
+    if read_varint(stream)? != 0x00 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "The server didn't respond with a status.",
+        ));
+    }
+    let json_length = read_varint(stream)?;
+    if !(0..=MAX_STATUS_LENGTH).contains(&json_length) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "The server sent a malformed status.",
+        ));
+    }
+    let mut json = vec![0u8; json_length as usize];
+    stream.read_exact(&mut json)?;
+    Ok(String::from_utf8_lossy(&json).to_string())
+}
+
+fn parse_status(json: &str, latency: Duration) -> ServerStatus {
+    let version = json
+        .find("\"version\"")
+        .and_then(|start| json_value_after(&json[start..], "name"))
+        .map(|version| version.to_string());
+    let players = json.find("\"players\"").map(|start| &json[start..]);
+    let player_count = |key| {
+        players
+            .and_then(|players| json_value_after(players, key))
+            .and_then(|count| count.parse().ok())
+    };
+
+    ServerStatus {
+        version,
+        online_players: player_count("online"),
+        max_players: player_count("max"),
+        latency,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUS: &str = r#"{"version": {"name": "Paper 1.20.4", "protocol": 765}, "players": {"max": 20, "online": 3, "sample": []}, "description": {"text": "A Minecraft Server"}}"#;
+
+    fn status_response(id: i32, json: &str) -> Vec<u8> {
+        let mut response = Vec::new();
+        write_varint(&mut response, id);
+        write_varint(&mut response, json.len() as i32);
+        response.extend_from_slice(json.as_bytes());
+        response
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for value in [0, 1, 127, 128, 300, 25565, i32::MAX, -1, i32::MIN] {
+            let mut buffer = Vec::new();
+            write_varint(&mut buffer, value);
+            assert!(buffer.len() <= 5);
+            assert_eq!(read_varint(&mut buffer.as_slice()).unwrap(), value);
+        }
+        let mut buffer = Vec::new();
+        write_varint(&mut buffer, 300);
+        assert_eq!(buffer, [0xac, 0x02]);
+        // Six bytes that all say another one follows.
+        assert!(read_varint(&mut [0x80u8; 6].as_slice()).is_err());
+        assert!(read_varint(&mut [0x80u8].as_slice()).is_err());
+    }
+
+    #[test]
+    fn json_values_are_found_after_their_keys() {
+        assert_eq!(json_value_after(STATUS, "name"), Some("Paper 1.20.4"));
+        assert_eq!(json_value_after(STATUS, "protocol"), Some("765"));
+        assert_eq!(json_value_after(STATUS, "missing"), None);
+        assert_eq!(json_value_after(r#"{"name": "unterminated"#, "name"), None);
+        assert_eq!(json_value_after(r#"{"name""#, "name"), None);
+    }
+
+    #[test]
+    fn status_responses_are_read_and_parsed() {
+        let json = read_status_json(&mut status_response(0, STATUS).as_slice()).unwrap();
+        let status = parse_status(&json, Duration::from_millis(12));
+        assert_eq!(status.version.as_deref(), Some("Paper 1.20.4"));
+        assert_eq!(status.online_players, Some(3));
+        assert_eq!(status.max_players, Some(20));
+
+        let empty = parse_status("{}", Duration::ZERO);
+        assert_eq!(
+            (empty.version, empty.online_players, empty.max_players),
+            (None, None, None)
+        );
+    }
+
+    #[test]
+    fn malformed_status_responses_are_rejected() {
+        assert!(read_status_json(&mut status_response(1, STATUS).as_slice()).is_err());
+        let response = status_response(0, STATUS);
+        for length in 0..response.len() {
+            assert!(read_status_json(&mut &response[..length]).is_err());
+        }
+        for length in [-1, MAX_STATUS_LENGTH + 1] {
+            let mut response = vec![0];
+            write_varint(&mut response, length);
+            assert!(read_status_json(&mut response.as_slice()).is_err());
+        }
+    }
+}
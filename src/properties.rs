@@ -0,0 +1,429 @@
+use std::{fmt, fs, io, path::Path};
+
+// A parsed `server.properties` file. Comments and blank lines are kept so that the file can be written
+// back without losing anything the server or the user put there.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ServerProperties {
+    lines: Vec<PropertiesLine>,
+    line_ending: LineEnding,
+}
+
+// Files edited on Windows often use CRLF, and writing them back with LF would change every line.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    // The style most of the lines use, since a file touched by several editors can end up mixed.
+    fn detect(text: &str) -> LineEnding {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        if crlf > lf {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+// A server.properties setting that can be changed from the menu and with --set, as a whole number.
+pub struct TunableProperty {
+    pub key: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    // What the server uses when the property isn't set.
+    pub default: u16,
+    pub min: u16,
+    pub max: u16,
+}
+
+// The two settings that matter most for performance, both in chunks: how far players can see, and
+// how far around them the world keeps ticking.
+pub static TUNABLE_PROPERTIES: [TunableProperty; 2] = [
+    TunableProperty {
+        key: "view-distance",
+        name: "View distance",
+        description: "How many chunks around each player are sent to them.",
+        default: 10,
+        min: 3,
+        max: 32,
+    },
+    TunableProperty {
+        key: "simulation-distance",
+        name: "Simulation distance",
+        description: "How many chunks around each player keep ticking.",
+        default: 10,
+        min: 3,
+        max: 32,
+    },
+];
+
+pub fn find_tunable_property(key: &str) -> Option<&'static TunableProperty> {
+    TUNABLE_PROPERTIES
+        .iter()
+        .find(|property| property.key == key)
+}
+
+impl TunableProperty {
+    pub fn current(&self, properties: &ServerProperties) -> u16 {
+        properties
+            .get(self.key)
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(self.default)
+    }
+}
+
+// A server.properties setting that takes one of a few names.
+pub struct ChoiceProperty {
+    pub key: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    // Exactly as the server expects them, in the order of the numbers that older files use instead.
+    pub choices: &'static [&'static str],
+    pub default: &'static str,
+}
+
+pub static CHOICE_PROPERTIES: [ChoiceProperty; 2] = [
+    ChoiceProperty {
+        key: "gamemode",
+        name: "Game mode",
+        description: "The game mode that players start in when they first join.",
+        choices: &["survival", "creative", "adventure", "spectator"],
+        default: "survival",
+    },
+    ChoiceProperty {
+        key: "difficulty",
+        name: "Difficulty",
+        description: "How hard the world's mobs are and how quickly hunger drains.",
+        choices: &["peaceful", "easy", "normal", "hard"],
+        default: "easy",
+    },
+];
+
+pub fn find_choice_property(key: &str) -> Option<&'static ChoiceProperty> {
+    CHOICE_PROPERTIES
+        .iter()
+        .find(|property| property.key == key)
+}
+
+impl ChoiceProperty {
+    // The choice that the server reads the value as. Names are case-sensitive to the server, so
+    // e.g. "Creative" isn't one.
+    pub fn parse(&self, value: &str) -> Option<&'static str> {
+        let value = value.trim();
+        match value.parse::<usize>() {
+            Ok(number) => self.choices.get(number).copied(),
+            Err(_) => self
+                .choices
+                .iter()
+                .find(|choice| **choice == value)
+                .copied(),
+        }
+    }
+
+    pub fn current(&self, properties: &ServerProperties) -> &'static str {
+        properties
+            .get(self.key)
+            .and_then(|value| self.parse(value))
+            .unwrap_or(self.default)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PropertyChange {
+    Added {
+        key: String,
+        value: String,
+    },
+    Changed {
+        key: String,
+        old: String,
+        new: String,
+    },
+    Removed {
+        key: String,
+        value: String,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum PropertiesLine {
+    Other(String),
+    Property { key: String, value: String },
+}
+
+// Reverses the escaping done by Java's `Properties.store`, e.g. "https\://example.com" or "é".
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut characters = text.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            result.push(character);
+            continue;
+        }
+        match characters.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('f') => result.push('\u{c}'),
+            Some('u') => {
+                let code: String = characters.by_ref().take(4).collect();
+                match u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                    Some(character) => result.push(character),
+                    None => result.push_str(&code),
+                }
+            }
+            Some(character) => result.push(character),
+            None => {}
+        }
+    }
+    result
+}
+
+// Escapes text the way Java's `Properties.store` does so that the server reads back the same value.
+fn escape(text: &str, is_key: bool) -> String {
+    let mut result = String::with_capacity(text.len());
+    for (index, character) in text.chars().enumerate() {
+        match character {
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            '=' | ':' | '#' | '!' => {
+                result.push('\\');
+                result.push(character);
+            }
+            ' ' if is_key || index == 0 => result.push_str("\\ "),
+            character => result.push(character),
+        }
+    }
+    result
+}
+
+fn parse_line(line: &str) -> PropertiesLine {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('!') {
+        return PropertiesLine::Other(line.to_string());
+    }
+
+    // The key ends at the first unescaped "=", ":", or whitespace.
+    let mut escaped = false;
+    let mut key_end = trimmed.len();
+    for (index, character) in trimmed.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if character == '\\' {
+            escaped = true;
+        } else if character == '=' || character == ':' || character.is_whitespace() {
+            key_end = index;
+            break;
+        }
+    }
+    let (key, rest) = trimmed.split_at(key_end);
+    let rest = rest.trim_start();
+    let rest = rest
+        .strip_prefix('=')
+        .or_else(|| rest.strip_prefix(':'))
+        .unwrap_or(rest)
+        .trim_start();
+
+    PropertiesLine::Property {
+        key: unescape(key),
+        value: unescape(rest),
+    }
+}
+
+impl ServerProperties {
+    pub fn parse(text: &str) -> ServerProperties {
+        ServerProperties {
+            lines: text.lines().map(parse_line).collect(),
+            line_ending: LineEnding::detect(text),
+        }
+    }
+
+    pub fn read(path: &Path) -> io::Result<ServerProperties> {
+        Ok(ServerProperties::parse(&fs::read_to_string(path)?))
+    }
+
+    // Like read, but a file that doesn't exist yet is empty, as it is before the server first runs.
+    // Any other error is returned so that a file that can't be read is never overwritten.
+    pub fn read_or_default(path: &Path) -> io::Result<ServerProperties> {
+        match ServerProperties::read(path) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                Ok(ServerProperties::default())
+            }
+            result => result,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries()
+            .find(|(entry_key, _)| *entry_key == key)
+            .map(|(_, value)| value)
+    }
+
+    // Changes the value of an existing property in place, or adds it to the end of the file.
+    pub fn set(&mut self, key: &str, value: &str) {
+        for line in self.lines.iter_mut() {
+            if let PropertiesLine::Property {
+                key: line_key,
+                value: line_value,
+            } = line
+            {
+                if line_key == key {
+                    *line_value = value.to_string();
+                    return;
+                }
+            }
+        }
+        self.lines.push(PropertiesLine::Property {
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(|line| match line {
+            PropertiesLine::Property { key, value } => Some((key.as_str(), value.as_str())),
+            PropertiesLine::Other(_) => None,
+        })
+    }
+}
+
+// Compares two versions of a properties file key by key, in the order the keys appear.
+pub fn diff_properties(before: &ServerProperties, after: &ServerProperties) -> Vec<PropertyChange> {
+    let mut changes: Vec<PropertyChange> = after
+        .entries()
+        .filter_map(|(key, value)| match before.get(key) {
+            None => Some(PropertyChange::Added {
+                key: key.to_string(),
+                value: value.to_string(),
+            }),
+            Some(old) if old != value => Some(PropertyChange::Changed {
+                key: key.to_string(),
+                old: old.to_string(),
+                new: value.to_string(),
+            }),
+            Some(_) => None,
+        })
+        .collect();
+    changes.extend(
+        before
+            .entries()
+            .filter(|(key, _)| after.get(key).is_none())
+            .map(|(key, value)| PropertyChange::Removed {
+                key: key.to_string(),
+                value: value.to_string(),
+            }),
+    );
+    changes
+}
+
+impl fmt::Display for ServerProperties {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let line_ending = self.line_ending.as_str();
+        for line in &self.lines {
+            match line {
+                PropertiesLine::Other(text) => write!(formatter, "{}{}", text, line_ending)?,
+                PropertiesLine::Property { key, value } => write!(
+                    formatter,
+                    "{}={}{}",
+                    escape(key, true),
+                    escape(value, false),
+                    line_ending
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CRLF_FILE: &str = "#Minecraft server properties\r\n#Sat Apr 20 13:37:00 CEST 2024\r\n\r\nenable-rcon=false\r\nmotd=A Minecraft Server\r\n# A comment of our own\r\nview-distance=10\r\n\r\nserver-port=25565\r\n";
+
+    #[test]
+    fn keys_and_values_are_unescaped() {
+        let properties = ServerProperties::parse(
+            "# server-port=1\n\
+             ! also a comment\n\
+             motd = A \\u00e9 server\n\
+             resource-pack:https\\://example.com/pack.zip\n\
+             \tlevel-name   world\n\
+             weird\\=key=value\n\
+             empty=\n",
+        );
+        assert_eq!(
+            properties.entries().collect::<Vec<_>>(),
+            [
+                ("motd", "A \u{e9} server"),
+                ("resource-pack", "https://example.com/pack.zip"),
+                ("level-name", "world"),
+                ("weird=key", "value"),
+                ("empty", ""),
+            ]
+        );
+        assert_eq!(properties.get("server-port"), None);
+        assert_eq!(unescape("\\u00zz\\t\\"), "00zz\t");
+    }
+
+    #[test]
+    fn crlf_file_round_trips_byte_for_byte() {
+        let properties = ServerProperties::parse(CRLF_FILE);
+        assert_eq!(properties.to_string(), CRLF_FILE);
+    }
+
+    #[test]
+    fn set_keeps_crlf_line_endings() {
+        let mut properties = ServerProperties::parse(CRLF_FILE);
+        properties.set("view-distance", "12");
+        properties.set("difficulty", "hard");
+        assert_eq!(
+            properties.to_string(),
+            CRLF_FILE.replace("view-distance=10", "view-distance=12") + "difficulty=hard\r\n"
+        );
+    }
+
+    #[test]
+    fn lf_file_stays_lf() {
+        let mut properties = ServerProperties::parse("motd=Hello\nserver-port=25565\n");
+        properties.set("difficulty", "hard");
+        assert_eq!(
+            properties.to_string(),
+            "motd=Hello\nserver-port=25565\ndifficulty=hard\n"
+        );
+    }
+
+    #[test]
+    fn read_or_default_is_empty_only_for_a_missing_file() {
+        let directory =
+            std::env::temp_dir().join(format!("msc-test-read-or-default-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        let missing =
+            ServerProperties::read_or_default(&directory.join("server.properties")).unwrap();
+        assert_eq!(missing.to_string(), "");
+
+        // A directory in place of the file can't be read, and mustn't be treated as empty.
+        assert!(ServerProperties::read_or_default(&directory).is_err());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
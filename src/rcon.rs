@@ -0,0 +1,372 @@
+use std::{
+    env,
+    fs::{File, OpenOptions},
+    io::{self, Error, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpStream},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    properties::ServerProperties,
+    secret::{SecretSource, RCON_PASSWORD_VARIABLE},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+pub const DEFAULT_RCON_PORT: u16 = 25575;
+
+// RCON gives full control of the server, so its password shouldn't be one that's tried first.
+const COMMON_PASSWORDS: &[&str] = &[
+    "password",
+    "minecraft",
+    "admin",
+    "rcon",
+    "changeme",
+    "123456",
+    "12345678",
+    "qwerty",
+];
+const MIN_PASSWORD_LENGTH: usize = 8;
+const GENERATED_PASSWORD_LENGTH: usize = 24;
+
+const LOGIN: i32 = 3;
+const COMMAND: i32 = 2;
+// Not a request the server knows, which it answers with "Unknown request" right after it has sent
+// everything for the requests before it.
+const SENTINEL: i32 = 0;
+// The server splits long responses into packets of up to 4096 bytes, but other implementations may
+// not, so anything up to this is accepted.
+const MAX_PACKET_LENGTH: i32 = 1024 * 1024;
+
+// Where the server listens for RCON, from server.properties, or None when RCON is disabled.
+pub fn rcon_port(properties: &ServerProperties) -> Option<u16> {
+    if properties.get("enable-rcon") != Some("true") {
+        return None;
+    }
+    Some(
+        properties
+            .get("rcon.port")
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(DEFAULT_RCON_PORT),
+    )
+}
+
+// Where to get the RCON password: --rcon-password-file, then the RCON_PASSWORD environment variable,
+// then the configuration's rconPasswordFile, and finally rcon.password in server.properties.
+pub fn rcon_password_source(
+    password_file: Option<&str>,
+    configured_password_file: Option<&str>,
+    properties: &ServerProperties,
+) -> Option<SecretSource> {
+    if let Some(path) = password_file {
+        Some(SecretSource::File(PathBuf::from(path)))
+    } else if env::var_os(RCON_PASSWORD_VARIABLE).is_some() {
+        Some(SecretSource::Environment(
+            RCON_PASSWORD_VARIABLE.to_string(),
+        ))
+    } else if let Some(path) = configured_password_file {
+        Some(SecretSource::File(PathBuf::from(path)))
+    } else {
+        properties
+            .get("rcon.password")
+            .filter(|password| !password.is_empty())
+            .map(|password| SecretSource::Inline(password.to_string()))
+    }
+}
+
+// Why the RCON password is easy to guess, or None when it isn't.
+pub fn rcon_password_weakness(password: &str) -> Option<&'static str> {
+    if password.is_empty() {
+        Some("it's empty, so the server won't listen for RCON at all")
+    } else if COMMON_PASSWORDS.contains(&password.to_lowercase().as_str()) {
+        Some("it's one of the first passwords anyone would try")
+    } else if password.chars().count() < MIN_PASSWORD_LENGTH {
+        Some("it's shorter than 8 characters")
+    } else {
+        None
+    }
+}
+
+// A random password of letters and digits, which need no escaping in server.properties. Lookalike
+// characters are left out in case it's copied by hand.
+pub fn generate_rcon_password() -> io::Result<String> {
+    const ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz23456789";
+    password_from_bytes(ALPHABET, GENERATED_PASSWORD_LENGTH, random_bytes)
+}
+
+// Picks each character from the alphabet with the same chance. Bytes at or above the largest
+// multiple of the alphabet's length would favor its first characters, so they're drawn again.
+fn password_from_bytes(
+    alphabet: &[u8],
+    length: usize,
+    mut fill: impl FnMut(&mut [u8]) -> io::Result<()>,
+) -> io::Result<String> {
+    let limit = 256 - 256 % alphabet.len();
+    let mut password = String::with_capacity(length);
+    let mut bytes = [0u8; 64];
+    while password.len() < length {
+        fill(&mut bytes)?;
+        for byte in bytes.iter().map(|byte| *byte as usize) {
+            if byte < limit && password.len() < length {
+                password.push(alphabet[byte % alphabet.len()] as char);
+            }
+        }
+    }
+    Ok(password)
+}
+
+#[cfg(unix)]
+fn random_bytes(bytes: &mut [u8]) -> io::Result<()> {
+    File::open("/dev/urandom")?.read_exact(bytes)
+}
+
+#[cfg(windows)]
+fn random_bytes(bytes: &mut [u8]) -> io::Result<()> {
+    // Asks for the system's preferred generator instead of opening one.
+    const BCRYPT_USE_SYSTEM_PREFERRED_RNG: u32 = 0x2;
+    #[link(name = "bcrypt")]
+    extern "system" {
+        fn BCryptGenRandom(
+            algorithm: *mut std::ffi::c_void,
+            buffer: *mut u8,
+            length: u32,
+            flags: u32,
+        ) -> i32;
+    }
+    let status = unsafe {
+        BCryptGenRandom(
+            std::ptr::null_mut(),
+            bytes.as_mut_ptr(),
+            bytes.len() as u32,
+            BCRYPT_USE_SYSTEM_PREFERRED_RNG,
+        )
+    };
+    if status == 0 {
+        Ok(())
+    } else {
+        Err(Error::other(format!(
+            "the system's random number generator failed with {:#x}",
+            status
+        )))
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn random_bytes(_bytes: &mut [u8]) -> io::Result<()> {
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        "there's no random number generator to make a password with, so choose one yourself",
+    ))
+}
+
+// The password gives full control of the server, so only its owner may read the file.
+#[cfg(unix)]
+fn create_private_file(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // The mode only applies to a file that's created, not one that was already there.
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn create_private_file(path: &Path) -> io::Result<File> {
+    File::create(path)
+}
+
+pub fn write_password_file(path: &Path, password: &str) -> io::Result<()> {
+    writeln!(create_private_file(path)?, "{}", password)
+}
+
+// Turns RCON on in server.properties with its port and password together, since the server ignores
+// enable-rcon without a password.
+pub fn enable_rcon(port: u16, password: &str) -> io::Result<()> {
+    let path = Path::new("server.properties");
+    let mut properties = ServerProperties::read_or_default(path)?;
+    properties.set("enable-rcon", "true");
+    properties.set("rcon.port", &port.to_string());
+    properties.set("rcon.password", password);
+    properties.write(path)
+}
+
+// Turns RCON off, keeping the port and password for when it's enabled again.
+pub fn disable_rcon() -> io::Result<()> {
+    let path = Path::new("server.properties");
+    let mut properties = ServerProperties::read_or_default(path)?;
+    properties.set("enable-rcon", "false");
+    properties.write(path)
+}
+
+// A logged in connection to the server's remote console.
+pub struct RconClient {
+    stream: TcpStream,
+    next_id: i32,
+}
+
+impl RconClient {
+    pub fn connect(address: SocketAddr, password: &str) -> Result<RconClient, Error> {
+        let stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let mut client = RconClient { stream, next_id: 1 };
+        // The server answers a wrong password with a request ID of -1.
+        client.send(LOGIN, password)?;
+        let (id, _) = read_packet(&mut client.stream)?;
+        if id == -1 {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "The server rejected the RCON password.",
+            ));
+        }
+        Ok(client)
+    }
+
+    // Runs a console command, e.g. "stop" or "say hi", and returns what it printed. A long response
+    // comes in several packets, so they're read until the answer to a sentinel request that's sent
+    // right after the command.
+    pub fn command(&mut self, command: &str) -> Result<String, Error> {
+        let id = self.send(COMMAND, command)?;
+        let sentinel = self.send(SENTINEL, "")?;
+        read_response(&mut self.stream, id, sentinel)
+    }
+
+    // Packets are a little-endian length, request ID, and type, followed by a null-terminated body
+    // and one more null byte. Returns the request's ID.
+    fn send(&mut self, kind: i32, body: &str) -> Result<i32, Error> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&((body.len() + 10) as i32).to_le_bytes());
+        packet.extend_from_slice(&id.to_le_bytes());
+        packet.extend_from_slice(&kind.to_le_bytes());
+        packet.extend_from_slice(body.as_bytes());
+        packet.extend_from_slice(&[0, 0]);
+        self.stream.write_all(&packet)?;
+        Ok(id)
+    }
+}
+
+// Reads one packet, returning its request ID and its body without the null bytes.
+fn read_packet(reader: &mut impl Read) -> Result<(i32, Vec<u8>), Error> {
+    let mut read_i32 = || -> io::Result<i32> {
+        let mut bytes = [0u8; 4];
+        reader.read_exact(&mut bytes)?;
+        Ok(i32::from_le_bytes(bytes))
+    };
+    let length = read_i32()?;
+    if !(10..=MAX_PACKET_LENGTH).contains(&length) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "The server sent a malformed RCON packet.",
+        ));
+    }
+    let id = read_i32()?;
+    let _kind = read_i32()?;
+    let mut body = vec![0u8; length as usize - 8];
+    reader.read_exact(&mut body)?;
+    body.truncate(body.len() - 2);
+    Ok((id, body))
+}
+
+// Joins the bodies of the packets for request `id` until the sentinel's answer arrives. The bytes
+// are joined before they're decoded, since a character can be split between packets.
+fn read_response(reader: &mut impl Read, id: i32, sentinel: i32) -> Result<String, Error> {
+    let mut response = Vec::new();
+    loop {
+        let (packet_id, body) = read_packet(reader)?;
+        if packet_id == sentinel {
+            return Ok(String::from_utf8_lossy(&response).to_string());
+        }
+        if packet_id == id {
+            response.extend_from_slice(&body);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(id: i32, body: &[u8]) -> Vec<u8> {
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&((body.len() + 10) as i32).to_le_bytes());
+        packet.extend_from_slice(&id.to_le_bytes());
+        packet.extend_from_slice(&0i32.to_le_bytes());
+        packet.extend_from_slice(body);
+        packet.extend_from_slice(&[0, 0]);
+        packet
+    }
+
+    #[test]
+    fn responses_split_across_packets_are_joined() {
+        let long = "x".repeat(4096);
+        let mut stream = Vec::new();
+        stream.extend(packet(7, long.as_bytes()));
+        // "é" split between two packets.
+        stream.extend(packet(7, b"caf\xc3"));
+        stream.extend(packet(7, b"\xa9"));
+        stream.extend(packet(8, b"Unknown request 0"));
+        stream.extend(packet(9, b"the next response"));
+        let mut reader = io::Cursor::new(stream);
+        assert_eq!(
+            read_response(&mut reader, 7, 8).unwrap(),
+            format!("{}café", long)
+        );
+        assert_eq!(read_packet(&mut reader).unwrap().0, 9);
+    }
+
+    #[test]
+    fn malformed_and_cut_off_packets_are_errors() {
+        let mut too_short = io::Cursor::new(packet(1, b"")[..8].to_vec());
+        assert!(read_packet(&mut too_short).is_err());
+        let mut bad_length = io::Cursor::new([4i32.to_le_bytes(), [0; 4], [0; 4]].concat());
+        assert_eq!(
+            read_packet(&mut bad_length).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+        // The response ends before the sentinel's answer.
+        let mut cut_off = io::Cursor::new(packet(7, b"partial"));
+        assert!(read_response(&mut cut_off, 7, 8).is_err());
+    }
+
+    #[test]
+    fn bytes_that_would_favor_some_characters_are_drawn_again() {
+        // With 3 characters, 255 would be the 86th "a" out of 256 bytes, which is one too many.
+        let mut draws =
+            vec![vec![255u8; 64], [0, 1, 2, 254, 253, 252, 255, 5].repeat(8)].into_iter();
+        let password = password_from_bytes(b"abc", 12, |bytes| {
+            bytes.copy_from_slice(&draws.next().unwrap());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(password, "abccbacabccb");
+    }
+
+    #[test]
+    fn generated_passwords_are_strong() {
+        let password = generate_rcon_password().unwrap();
+        assert_eq!(password.len(), GENERATED_PASSWORD_LENGTH);
+        assert_eq!(rcon_password_weakness(&password), None);
+        assert_ne!(password, generate_rcon_password().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn password_files_are_only_readable_by_their_owner() {
+        use std::os::unix::fs::PermissionsExt;
+        let path =
+            std::env::temp_dir().join(format!("msc-test-rcon-password-{}", std::process::id()));
+        std::fs::write(&path, "old").unwrap();
+        write_password_file(&path, "secret").unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(contents, "secret\n");
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::BTreeSet;
+
+use ron::{Map, Value};
+
+use crate::machine::{MinecraftServerConfiguration, DEFAULT_INSTANCE};
+
+// A key that rewriting the configuration in its canonical form removes or adds.
+#[derive(Debug, PartialEq, Clone)]
+pub enum KeyChange {
+    // Not an option (anymore), e.g. a typo or an option from another version of this tool.
+    Dropped { instance: String, key: String },
+    // Missing from the file, so it gets its default.
+    Filled { instance: String, key: String },
+}
+
+fn map_keys(map: &Map) -> BTreeSet<String> {
+    map.keys()
+        .filter_map(|key| match key {
+            Value::String(key) => Some(key.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+// The keys of every option, taken from how the configuration is saved so that this never falls
+// out of date with the struct.
+fn option_keys() -> BTreeSet<String> {
+    let default = ron::to_string(&MinecraftServerConfiguration::default()).unwrap();
+    match ron::from_str::<Value>(&default) {
+        Ok(Value::Map(map)) => map_keys(&map),
+        _ => BTreeSet::new(),
+    }
+}
+
+// Compares the keys of each instance in the file with the options, for a file that already parses.
+pub fn find_key_changes(configuration_string: &str) -> Vec<KeyChange> {
+    let top_level = match ron::from_str::<Value>(configuration_string) {
+        Ok(Value::Map(map)) => map,
+        _ => return Vec::new(),
+    };
+    let instances_value = top_level
+        .iter()
+        .find(|(key, _)| **key == Value::String("instances".to_string()))
+        .map(|(_, value)| value);
+    let instances: Vec<(String, Map)> = match instances_value {
+        Some(Value::Map(instances)) => instances
+            .iter()
+            .filter_map(|(name, configuration)| match (name, configuration) {
+                (Value::String(name), Value::Map(configuration)) => {
+                    Some((name.clone(), configuration.clone()))
+                }
+                _ => None,
+            })
+            .collect(),
+        _ => vec![(DEFAULT_INSTANCE.to_string(), top_level)],
+    };
+
+    let options = option_keys();
+    let mut changes = Vec::new();
+    for (instance, configuration) in instances {
+        let keys = map_keys(&configuration);
+        changes.extend(keys.difference(&options).map(|key| KeyChange::Dropped {
+            instance: instance.clone(),
+            key: key.clone(),
+        }));
+        changes.extend(options.difference(&keys).map(|key| KeyChange::Filled {
+            instance: instance.clone(),
+            key: key.clone(),
+        }));
+    }
+    changes
+}
@@ -0,0 +1,166 @@
+use std::fmt;
+
+use console::Term;
+
+// How the status, validation, and list commands print their results: styled text for people, or a
+// single line of JSON for scripts.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OutputFormat {
+    Human,
+    Json,
+}
+
+impl OutputFormat {
+    pub const NAMES: [&'static str; 2] = ["human", "json"];
+
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "human" => Some(OutputFormat::Human),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+// Just enough JSON to write reports, since there's no JSON library among the dependencies. Objects
+// keep their keys in the order they were added.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object(entries: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn string(value: impl Into<String>) -> Json {
+        Json::String(value.into())
+    }
+
+    pub fn optional<T>(value: Option<T>, convert: impl FnOnce(T) -> Json) -> Json {
+        value.map(convert).unwrap_or(Json::Null)
+    }
+}
+
+fn write_json_string(formatter: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    formatter.write_str("\"")?;
+    for character in value.chars() {
+        match character {
+            '"' => formatter.write_str("\\\"")?,
+            '\\' => formatter.write_str("\\\\")?,
+            '\n' => formatter.write_str("\\n")?,
+            '\r' => formatter.write_str("\\r")?,
+            '\t' => formatter.write_str("\\t")?,
+            character if (character as u32) < 0x20 => {
+                write!(formatter, "\\u{:04x}", character as u32)?
+            }
+            character => write!(formatter, "{}", character)?,
+        }
+    }
+    formatter.write_str("\"")
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => formatter.write_str("null"),
+            Json::Bool(value) => write!(formatter, "{}", value),
+            Json::Number(value) => write!(formatter, "{}", value),
+            Json::String(value) => write_json_string(formatter, value),
+            Json::Array(values) => {
+                formatter.write_str("[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        formatter.write_str(",")?;
+                    }
+                    write!(formatter, "{}", value)?;
+                }
+                formatter.write_str("]")
+            }
+            Json::Object(entries) => {
+                formatter.write_str("{")?;
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        formatter.write_str(",")?;
+                    }
+                    write_json_string(formatter, key)?;
+                    write!(formatter, ":{}", value)?;
+                }
+                formatter.write_str("}")
+            }
+        }
+    }
+}
+
+// The result of a command that can print itself either way.
+pub trait Report {
+    // The styled lines shown in a terminal.
+    fn human(&self) -> Vec<String>;
+    fn json(&self) -> Json;
+}
+
+pub fn emit(report: &dyn Report, format: OutputFormat, terminal: &Term) {
+    match format {
+        OutputFormat::Human => {
+            for line in report.human() {
+                terminal.write_line(&line).unwrap();
+            }
+        }
+        OutputFormat::Json => terminal.write_line(&report.json().to_string()).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_is_written_compactly_in_insertion_order() {
+        let report = Json::object(vec![
+            ("valid", Json::Bool(false)),
+            ("port", Json::Number(-25565)),
+            ("motd", Json::optional(None::<&str>, Json::string)),
+            (
+                "names",
+                Json::Array(vec![Json::string("a"), Json::Array(Vec::new())]),
+            ),
+            ("empty", Json::object(Vec::new())),
+        ]);
+        assert_eq!(
+            report.to_string(),
+            r#"{"valid":false,"port":-25565,"motd":null,"names":["a",[]],"empty":{}}"#
+        );
+    }
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(
+            Json::string("\"quoted\" \\ path\nnew\tline\r\u{1} é").to_string(),
+            r#""\"quoted\" \\ path\nnew\tline\r\u0001 é""#
+        );
+        assert_eq!(
+            Json::object(vec![("a\"b", Json::Null)]).to_string(),
+            r#"{"a\"b":null}"#
+        );
+    }
+
+    #[test]
+    fn output_formats_are_chosen_by_name() {
+        for name in OutputFormat::NAMES {
+            assert!(OutputFormat::from_name(name).is_some());
+        }
+        assert_eq!(OutputFormat::from_name("json"), Some(OutputFormat::Json));
+        assert_eq!(OutputFormat::from_name("JSON"), None);
+    }
+}
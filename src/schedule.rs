@@ -0,0 +1,282 @@
+use std::{
+    cmp::Reverse,
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{clock::DateTime, machine::MinecraftServerConfiguration};
+
+// How long before a daily restart the players are warned, when dailyRestartWarningMinutes isn't set.
+const DEFAULT_RESTART_WARNING_MINUTES: u16 = 5;
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+// How late the restart can still be noticed, e.g. when the machine was busy.
+const RESTART_WINDOW: u32 = 60;
+
+// Parses a 24-hour time of day like "04:30" into minutes after midnight.
+pub fn parse_time_of_day(value: &str) -> Option<u32> {
+    let (hour, minute) = value.trim().split_once(':')?;
+    // "4:5" is more likely a typo of "4:50" than "4:05".
+    if minute.len() != 2 {
+        return None;
+    }
+    let (hour, minute): (u32, u32) = (hour.parse().ok()?, minute.parse().ok()?);
+    if hour < 24 && minute < 60 {
+        Some(hour * 60 + minute)
+    } else {
+        None
+    }
+}
+
+// The warnings before the daily restart, as (seconds before it, message): the shutdownWarnings when
+// there are any, and otherwise a single one dailyRestartWarningMinutes ahead.
+pub fn daily_restart_warnings(configuration: &MinecraftServerConfiguration) -> Vec<(u32, String)> {
+    if !configuration.shutdownWarnings.is_empty() {
+        return configuration.shutdownWarnings.clone();
+    }
+    let minutes = configuration
+        .dailyRestartWarningMinutes
+        .unwrap_or(DEFAULT_RESTART_WARNING_MINUTES);
+    if minutes == 0 {
+        return Vec::new();
+    }
+    vec![(
+        minutes as u32 * 60,
+        format!(
+            "The server will restart in {} minute{}.",
+            minutes,
+            if minutes == 1 { "" } else { "s" }
+        ),
+    )]
+}
+
+fn second_of_day(time: &DateTime) -> u32 {
+    (time.hour * 60 + time.minute) * 60 + time.second
+}
+
+// What happens when the time left until the restart goes from `last_left` to `left` seconds: the
+// warnings whose lead time came up, and whether the restart is due.
+fn restart_tick(warnings: &[(u32, String)], last_left: u32, left: u32) -> (Vec<&str>, bool) {
+    let due = warnings
+        .iter()
+        .filter(|(lead, _)| last_left > *lead && *lead >= left)
+        .map(|(_, message)| message.as_str())
+        .collect();
+    // The countdown starts over from a day once the restart time has passed. A server that was
+    // launched right at the restart time has nothing to count down from.
+    let passed = left == 0 || (left > last_left && last_left <= RESTART_WINDOW);
+    (due, passed && last_left > 0)
+}
+
+// Broadcasts each warning through `send` as its lead time comes up, then stops the server once the
+// daily restart's minute comes around, until `server_exited` is set. The returned flag is set when
+// the stop was sent for the restart, so that the server is launched again instead of being treated
+// as stopped.
+pub fn schedule_daily_restart(
+    restart_at: u32,
+    warnings: Vec<(u32, String)>,
+    send: fn(&str),
+    server_exited: Arc<AtomicBool>,
+) -> Arc<AtomicBool> {
+    let restarting = Arc::new(AtomicBool::new(false));
+    let restarting_flag = restarting.clone();
+    let restart_at = restart_at * 60;
+    let seconds_left =
+        move || (restart_at + SECONDS_PER_DAY - second_of_day(&DateTime::now())) % SECONDS_PER_DAY;
+    thread::spawn(move || {
+        // Only what's passed while the server is running counts, so a server that was just restarted
+        // isn't stopped again right away and warnings that are already late aren't sent.
+        let mut last_left = seconds_left();
+        while !server_exited.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_secs(1));
+            let left = seconds_left();
+            if left == last_left {
+                continue;
+            }
+            let (due, passed) = restart_tick(&warnings, last_left, left);
+            for message in due {
+                send(&format!("say {}", message));
+            }
+            if passed {
+                restarting_flag.store(true, Ordering::SeqCst);
+                send("say The server is restarting now.");
+                send("stop");
+                break;
+            }
+            last_left = left;
+        }
+    });
+    restarting
+}
+
+// Stops the server through `send` once `empty_since` has been set for `idle_for`, until
+// `server_exited` is set. Whoever reads the log keeps `empty_since` up to date, clearing it while
+// anyone is online. The returned flag is set when the stop was sent, so that the server isn't
+// launched again.
+pub fn schedule_idle_shutdown(
+    idle_for: Duration,
+    empty_since: Arc<Mutex<Option<Instant>>>,
+    send: fn(&str),
+    server_exited: Arc<AtomicBool>,
+) -> Arc<AtomicBool> {
+    let stopped = Arc::new(AtomicBool::new(false));
+    let stopped_flag = stopped.clone();
+    thread::spawn(move || {
+        while !server_exited.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_secs(1));
+            let idle = empty_since
+                .lock()
+                .unwrap()
+                .is_some_and(|since| since.elapsed() >= idle_for);
+            if idle {
+                stopped_flag.store(true, Ordering::SeqCst);
+                send("stop");
+                break;
+            }
+        }
+    });
+    stopped
+}
+
+// Waits out the warnings before a planned stop, sending the furthest ahead first. Returns once the
+// last lead time is over, or early with the error if one couldn't be sent.
+pub fn count_down(
+    warnings: &[(u32, String)],
+    send: &mut dyn FnMut(&str) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut warnings = warnings.to_vec();
+    warnings.sort_by_key(|(lead, _)| Reverse(*lead));
+    let longest = match warnings.first() {
+        Some((lead, _)) => *lead,
+        None => return Ok(()),
+    };
+    let started_at = Instant::now();
+    let wait_until = |lead: u32| {
+        let at = started_at + Duration::from_secs((longest - lead) as u64);
+        thread::sleep(at.saturating_duration_since(Instant::now()));
+    };
+    for (lead, message) in &warnings {
+        wait_until(*lead);
+        send(message)?;
+    }
+    wait_until(0);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_of_day_are_24_hour_with_two_digit_minutes() {
+        assert_eq!(parse_time_of_day("04:30"), Some(270));
+        assert_eq!(parse_time_of_day("4:30"), Some(270));
+        assert_eq!(parse_time_of_day(" 23:59 "), Some(1439));
+        assert_eq!(parse_time_of_day("00:00"), Some(0));
+        assert_eq!(parse_time_of_day("4:5"), None);
+        assert_eq!(parse_time_of_day("24:00"), None);
+        assert_eq!(parse_time_of_day("12:60"), None);
+        assert_eq!(parse_time_of_day("noon"), None);
+        assert_eq!(parse_time_of_day("-1:30"), None);
+        assert_eq!(parse_time_of_day(""), None);
+    }
+
+    #[test]
+    fn restart_warnings_come_from_shutdown_warnings_first() {
+        let configuration = MinecraftServerConfiguration {
+            shutdownWarnings: vec![(60, "One minute".to_string())],
+            dailyRestartWarningMinutes: Some(10),
+            ..MinecraftServerConfiguration::default()
+        };
+        assert_eq!(
+            daily_restart_warnings(&configuration),
+            vec![(60, "One minute".to_string())]
+        );
+    }
+
+    #[test]
+    fn restart_warnings_fall_back_to_the_warning_minutes() {
+        let warnings = |minutes| {
+            daily_restart_warnings(&MinecraftServerConfiguration {
+                dailyRestartWarningMinutes: minutes,
+                ..MinecraftServerConfiguration::default()
+            })
+        };
+        assert_eq!(
+            warnings(None),
+            vec![(300, "The server will restart in 5 minutes.".to_string())]
+        );
+        assert_eq!(
+            warnings(Some(1)),
+            vec![(60, "The server will restart in 1 minute.".to_string())]
+        );
+        assert_eq!(warnings(Some(0)), Vec::new());
+    }
+
+    #[test]
+    fn warnings_are_sent_once_their_lead_time_comes_up() {
+        let warnings = vec![(300, "five".to_string()), (60, "one".to_string())];
+        assert_eq!(restart_tick(&warnings, 302, 301), (Vec::new(), false));
+        assert_eq!(restart_tick(&warnings, 301, 300), (vec!["five"], false));
+        assert_eq!(restart_tick(&warnings, 300, 299), (Vec::new(), false));
+        // A busy machine that skips seconds still sends every warning it went past.
+        assert_eq!(
+            restart_tick(&warnings, 400, 30),
+            (vec!["five", "one"], false)
+        );
+    }
+
+    #[test]
+    fn the_restart_is_due_once_its_time_is_reached_or_just_passed() {
+        assert_eq!(restart_tick(&[], 1, 0), (Vec::new(), true));
+        // The second of the restart itself was missed.
+        assert_eq!(
+            restart_tick(&[], 2, SECONDS_PER_DAY - 1),
+            (Vec::new(), true)
+        );
+        assert_eq!(
+            restart_tick(&[], RESTART_WINDOW, SECONDS_PER_DAY - 5),
+            (Vec::new(), true)
+        );
+        // Further off than the window, e.g. after the clock was set back.
+        assert_eq!(
+            restart_tick(&[], RESTART_WINDOW + 1, SECONDS_PER_DAY - 5),
+            (Vec::new(), false)
+        );
+        // Launched right at the restart time, so a whole day is left.
+        assert_eq!(
+            restart_tick(&[], 0, SECONDS_PER_DAY - 1),
+            (Vec::new(), false)
+        );
+        assert_eq!(restart_tick(&[], 10, 9), (Vec::new(), false));
+    }
+
+    #[test]
+    fn count_down_sends_the_furthest_warning_first_and_stops_on_errors() {
+        let warnings = vec![(0, "now".to_string()), (1, "soon".to_string())];
+        let mut sent = Vec::new();
+        let started_at = Instant::now();
+        count_down(&warnings, &mut |message| {
+            sent.push(message.to_string());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(sent, vec!["soon", "now"]);
+        assert!(started_at.elapsed() >= Duration::from_secs(1));
+
+        let mut attempts = 0;
+        let result = count_down(&warnings, &mut |_| {
+            attempts += 1;
+            Err(io::Error::other("closed"))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+        assert!(count_down(&[], &mut |_| unreachable!()).is_ok());
+    }
+}
@@ -0,0 +1,44 @@
+use std::{
+    env, fs,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+};
+
+// The environment variable that holds the RCON password, which takes precedence over the files.
+pub const RCON_PASSWORD_VARIABLE: &str = "RCON_PASSWORD";
+
+// Where a secret comes from, so that configurations only need to hold a reference to it.
+#[derive(Debug, PartialEq, Clone)]
+pub enum SecretSource {
+    Inline(String),
+    Environment(String),
+    File(PathBuf),
+}
+
+// Reads the secret when it's needed rather than when the configuration is loaded, so it never ends
+// up saved alongside the configuration.
+pub fn resolve_secret(source: &SecretSource) -> Result<String, Error> {
+    let secret = match source {
+        SecretSource::Inline(secret) => secret.clone(),
+        SecretSource::Environment(name) => env::var(name).map_err(|_| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("The {} environment variable isn't set.", name),
+            )
+        })?,
+        // Files usually end with a newline that isn't part of the secret.
+        SecretSource::File(path) => fs::read_to_string(path)
+            .map_err(|error| {
+                Error::new(
+                    error.kind(),
+                    format!("Unable to read {}: {}", path.display(), error),
+                )
+            })?
+            .trim_end_matches(&['\r', '\n'][..])
+            .to_string(),
+    };
+    if secret.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidData, "The secret is empty."));
+    }
+    Ok(secret)
+}
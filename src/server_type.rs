@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+
+// The kind of server that the jar runs. Modded and plugin servers wrap the vanilla server with their
+// own launcher, which doesn't accept every vanilla option.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, SmartDefault)]
+pub enum ServerType {
+    #[default]
+    Vanilla,
+    Forge,
+    Fabric,
+    Paper,
+}
+
+impl ServerType {
+    pub const ALL: [ServerType; 4] = [
+        ServerType::Vanilla,
+        ServerType::Forge,
+        ServerType::Fabric,
+        ServerType::Paper,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ServerType::Vanilla => "Vanilla",
+            ServerType::Forge => "Forge",
+            ServerType::Fabric => "Fabric",
+            ServerType::Paper => "Paper",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ServerType> {
+        ServerType::ALL
+            .iter()
+            .copied()
+            .find(|server_type| server_type.name() == name)
+    }
+
+    // The options (by property) whose flags this server type's launcher is known to reject or
+    // mishandle, so they are left out of the command instead of breaking the launch.
+    pub fn unsupported_options(self) -> &'static [&'static str] {
+        match self {
+            ServerType::Vanilla => &[],
+            ServerType::Forge => &["universe", "eraseCache", "jfrProfile"],
+            ServerType::Fabric => &["jfrProfile"],
+            ServerType::Paper => &["initSettings", "jfrProfile"],
+        }
+    }
+
+    pub fn supports(self, property: &str) -> bool {
+        !self.unsupported_options().contains(&property)
+    }
+}
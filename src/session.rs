@@ -0,0 +1,242 @@
+use std::{
+    env,
+    io::{self, Error, ErrorKind},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+// A terminal multiplexer that keeps the server running after the user disconnects.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Multiplexer {
+    Screen,
+    Tmux,
+}
+
+impl Multiplexer {
+    pub fn from_name(name: &str) -> Option<Multiplexer> {
+        match name {
+            "screen" => Some(Multiplexer::Screen),
+            "tmux" => Some(Multiplexer::Tmux),
+            _ => None,
+        }
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Multiplexer::Screen => "screen",
+            Multiplexer::Tmux => "tmux",
+        }
+    }
+
+    pub fn attach_command(self, session: &str) -> String {
+        match self {
+            Multiplexer::Screen => format!("screen -r {}", session),
+            // "=" makes tmux match the whole name instead of a prefix of a longer one.
+            Multiplexer::Tmux => format!("tmux attach -t ={}", session),
+        }
+    }
+
+    fn is_installed(self) -> bool {
+        env::var_os("PATH")
+            .map(|paths| {
+                env::split_paths(&paths).any(|directory| directory.join(self.program()).is_file())
+            })
+            .unwrap_or(false)
+    }
+
+    fn session_exists(self, session: &str) -> io::Result<bool> {
+        match self {
+            Multiplexer::Tmux => Ok(Command::new("tmux")
+                .args(["has-session", "-t", &format!("={}", session)])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()?
+                .success()),
+            Multiplexer::Screen => Ok(screen_session_id(session)?.is_some()),
+        }
+    }
+
+    pub fn attach(self, session: &str) -> io::Result<()> {
+        let mut command = Command::new(self.program());
+        match self {
+            // screen matches names by their prefix, but the session's id is exact.
+            Multiplexer::Screen => {
+                let id = screen_session_id(session)?.unwrap_or_else(|| session.to_string());
+                command.args(["-r", &id])
+            }
+            Multiplexer::Tmux => command.args(["attach", "-t", &format!("={}", session)]),
+        };
+        command.status().map(|_| ())
+    }
+
+    fn start(self, session: &str, command_string: &str, directory: &Path) -> io::Result<()> {
+        let mut command = Command::new(self.program());
+        match self {
+            Multiplexer::Screen => command.args(["-dmS", session, "sh", "-c", command_string]),
+            Multiplexer::Tmux => command
+                .args(["new-session", "-d", "-s", session, "-c"])
+                .arg(directory)
+                .arg(command_string),
+        };
+        let status = command.status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(Error::other(format!(
+                "{} exited with {} while creating the session.",
+                self.program(),
+                status
+            )))
+        }
+    }
+}
+
+// `screen -ls` exits with 1 even when it found sessions, so the session is looked for in its output
+// instead, where it's listed as e.g. "12345.msc-default-1a2b3c4d\t(Detached)". Returns its id, e.g.
+// "12345.msc-default-1a2b3c4d".
+fn screen_session_id(session: &str) -> io::Result<Option<String>> {
+    let output = Command::new("screen").args(["-ls", session]).output()?;
+    Ok(find_screen_session(
+        &String::from_utf8_lossy(&output.stdout),
+        session,
+    ))
+}
+
+fn find_screen_session(listing: &str, session: &str) -> Option<String> {
+    listing.lines().find_map(|line| {
+        let id = line.trim_start().split('\t').next()?;
+        let (pid, name) = id.split_once('.')?;
+        if name == session && pid.chars().all(|character| character.is_ascii_digit()) {
+            Some(id.to_string())
+        } else {
+            None
+        }
+    })
+}
+
+// A hash that stays the same between runs and versions, unlike the standard library's.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Sessions are named after the instance and a hash of the server's directory, the profile, and the
+// instance, e.g. "msc-default-1a2b3c4d", so that servers in other directories or profiles with the
+// same instance name get their own. Only letters, digits, "-", and "_" are kept from the instance's
+// name, since "." and ":" separate the parts of a tmux target.
+pub fn session_name(directory: &Path, profile: Option<&str>, instance: &str) -> String {
+    let mut key = directory.to_string_lossy().into_owned().into_bytes();
+    for part in [profile.unwrap_or(""), instance] {
+        key.push(0);
+        key.extend_from_slice(part.as_bytes());
+    }
+    let readable: String = instance
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() || character == '-' || character == '_' {
+                character
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("msc-{}-{:08x}", readable, fnv1a(&key) as u32)
+}
+
+// The multiplexer that has the session running, if any. The server may have been started in either,
+// so both are asked when they're installed.
+pub fn find_session(session: &str) -> io::Result<Option<Multiplexer>> {
+    for multiplexer in [Multiplexer::Tmux, Multiplexer::Screen] {
+        if multiplexer.is_installed() && multiplexer.session_exists(session)? {
+            return Ok(Some(multiplexer));
+        }
+    }
+    Ok(None)
+}
+
+// What happened when the server was handed to a multiplexer.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SessionOutcome {
+    Started,
+    Attached,
+}
+
+// Attaches to the session when it's running, returning whether it was.
+pub fn attach_if_running(multiplexer: Multiplexer, session: &str) -> io::Result<bool> {
+    if !multiplexer.is_installed() || !multiplexer.session_exists(session)? {
+        return Ok(false);
+    }
+    multiplexer.attach(session)?;
+    Ok(true)
+}
+
+// Runs the server command in a detached session, or attaches to the session when it's already
+// running instead of starting a second server.
+pub fn run_in_session(
+    multiplexer: Multiplexer,
+    session: &str,
+    command_string: &str,
+) -> io::Result<SessionOutcome> {
+    if !multiplexer.is_installed() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "{} isn't installed or isn't in your PATH.",
+                multiplexer.program()
+            ),
+        ));
+    }
+    if attach_if_running(multiplexer, session)? {
+        return Ok(SessionOutcome::Attached);
+    }
+    multiplexer.start(session, command_string, &env::current_dir()?)?;
+    Ok(SessionOutcome::Started)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_differ_by_directory_profile_and_instance() {
+        let names = [
+            session_name(Path::new("/srv/a"), None, "default"),
+            session_name(Path::new("/srv/b"), None, "default"),
+            session_name(Path::new("/srv/a"), Some("modded"), "default"),
+            session_name(Path::new("/srv/a"), None, "modded"),
+            session_name(Path::new("/srv/a"), None, "a.b"),
+            session_name(Path::new("/srv/a"), None, "a:b"),
+        ];
+        for (index, name) in names.iter().enumerate() {
+            for other in &names[index + 1..] {
+                assert_ne!(name, other);
+            }
+        }
+        assert_eq!(
+            names[0],
+            session_name(Path::new("/srv/a"), None, "default"),
+            "the name has to be the same every time to find the session again"
+        );
+    }
+
+    #[test]
+    fn session_names_only_use_safe_characters() {
+        let name = session_name(Path::new("/srv"), None, "my world: 1.20");
+        assert!(name.starts_with("msc-my_world__1_20-"));
+        assert!(name
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || "-_".contains(character)));
+    }
+
+    #[test]
+    fn screen_sessions_are_matched_by_their_whole_name() {
+        let listing = "There are screens on:\n\t123.msc-a-1234abcd-x\t(Detached)\n\t456.msc-a-1234abcd\t(Detached)\n2 Sockets in /run/screen/S-user.\n";
+        assert_eq!(
+            find_screen_session(listing, "msc-a-1234abcd"),
+            Some("456.msc-a-1234abcd".to_string())
+        );
+        assert_eq!(find_screen_session(listing, "msc-a"), None);
+        assert_eq!(find_screen_session("No Sockets found.\n", "msc-a"), None);
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use crate::machine::MinecraftServerConfiguration;
+
+pub const SHAREABLE_CONFIGURATION_FILENAME: &str = "msc-config-shareable.ron";
+
+const REDACTED: &str = "<redacted>";
+
+// Words that mark a Java system property as a secret, e.g. "rcon.password" or "server-id".
+// Properties are matched case-insensitively on any part of their key.
+const SENSITIVE_PROPERTY_NAMES: &[&str] = &["password", "secret", "token", "server-id", "serverid"];
+
+pub fn is_sensitive_property(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    SENSITIVE_PROPERTY_NAMES
+        .iter()
+        .any(|sensitive| key.contains(sensitive))
+}
+
+// Absolute paths give away the user's name and directory layout, while relative ones are usually
+// just the folder name and help whoever is reading the configuration.
+fn redact_path(path: &Option<String>) -> Option<String> {
+    path.as_ref().map(|path| {
+        if Path::new(path).is_absolute() {
+            REDACTED.to_string()
+        } else {
+            path.clone()
+        }
+    })
+}
+
+// A copy of the configuration that is safe to paste into an issue or a forum post.
+pub fn sanitize_configuration(
+    configuration: &MinecraftServerConfiguration,
+) -> MinecraftServerConfiguration {
+    let mut sanitized = redact_secrets(configuration);
+    sanitized.universe = redact_path(&configuration.universe);
+    sanitized.world = redact_path(&configuration.world);
+    sanitized.javaPath = redact_path(&configuration.javaPath);
+    sanitized.rconPasswordFile = redact_path(&configuration.rconPasswordFile);
+    for (_, value) in sanitized.systemProperties.iter_mut() {
+        if Path::new(value.as_str()).is_absolute() {
+            *value = REDACTED.to_string();
+        }
+    }
+    sanitized
+}
+
+// A copy of the configuration without its secrets, which still shows the paths, e.g. for the audit
+// log that stays on the machine.
+pub fn redact_secrets(
+    configuration: &MinecraftServerConfiguration,
+) -> MinecraftServerConfiguration {
+    let mut sanitized = configuration.clone();
+    // Hooks often hold webhook URLs or tokens.
+    sanitized.preStartHook = configuration
+        .preStartHook
+        .as_ref()
+        .map(|_| REDACTED.to_string());
+    sanitized.postStartHook = configuration
+        .postStartHook
+        .as_ref()
+        .map(|_| REDACTED.to_string());
+    for (key, value) in sanitized.systemProperties.iter_mut() {
+        if is_sensitive_property(key) {
+            *value = REDACTED.to_string();
+        }
+    }
+    sanitized
+}
+
+// A server.properties value as it may be shown or recorded, e.g. without rcon.password.
+pub fn redact_property_value(key: &str, value: &str) -> String {
+    if is_sensitive_property(key) {
+        REDACTED.to_string()
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,27 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// Remembered for the next run on this machine rather than for any one server, so it's kept in the
+// platform's configuration directory instead of next to the server.
+const LAST_DIRECTORY_FILENAME: &str = "last-server-directory";
+
+// The server directory that was used last time, unless it has been removed since.
+pub fn last_server_directory(state_directory: &Path) -> Option<PathBuf> {
+    let contents = fs::read_to_string(state_directory.join(LAST_DIRECTORY_FILENAME)).ok()?;
+    let directory = PathBuf::from(contents.trim_end_matches(['\r', '\n']));
+    if directory.is_dir() {
+        Some(directory)
+    } else {
+        None
+    }
+}
+
+pub fn remember_server_directory(state_directory: &Path, directory: &Path) -> io::Result<()> {
+    fs::create_dir_all(state_directory)?;
+    fs::write(
+        state_directory.join(LAST_DIRECTORY_FILENAME),
+        format!("{}\n", directory.display()),
+    )
+}
@@ -0,0 +1,194 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use crate::memory::MemorySize;
+
+// Whether msc is itself running in a systemd unit, e.g. the exported one, which sets the limits on
+// the whole service instead.
+pub fn is_in_systemd_unit() -> bool {
+    env::var_os("INVOCATION_ID").is_some()
+}
+
+// Whether the server can be run in a transient systemd scope, which needs Linux booted with systemd
+// and systemd-run in the PATH. Inside a unit, a scope would move the server out of it, and out from
+// under its limits and `systemctl stop`.
+pub fn can_run_in_scope() -> bool {
+    cfg!(target_os = "linux")
+        && !is_in_systemd_unit()
+        && Path::new("/run/systemd/system").is_dir()
+        && env::var_os("PATH")
+            .map(|paths| {
+                env::split_paths(&paths).any(|directory| directory.join("systemd-run").is_file())
+            })
+            .unwrap_or(false)
+        && (is_root() || has_user_manager())
+}
+
+// Other users than root create scopes through their own service manager, which isn't there for e.g.
+// a login over su or a cron job. systemd-run --user finds it through the user's bus or its private
+// socket in XDG_RUNTIME_DIR.
+fn has_user_manager() -> bool {
+    let runtime_directory = match env::var_os("XDG_RUNTIME_DIR") {
+        Some(directory) => PathBuf::from(directory),
+        None => return false,
+    };
+    runtime_directory.join("bus").exists()
+        || runtime_directory.join("systemd").join("private").exists()
+}
+
+#[cfg(unix)]
+fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_root() -> bool {
+    false
+}
+
+// The systemd-run prefix that starts the server in a scope with these limits, or nothing when none
+// are set. Other users than root get a scope in their own service manager, which they're allowed to
+// create without a password.
+pub fn scope_arguments(
+    memory_max: Option<MemorySize>,
+    cpu_quota: Option<u16>,
+    niceness: Option<u16>,
+) -> Vec<String> {
+    let mut properties = Vec::new();
+    if let Some(memory_max) = memory_max {
+        properties.push(format!("MemoryMax={}", memory_max));
+    }
+    if let Some(cpu_quota) = cpu_quota {
+        properties.push(format!("CPUQuota={}%", cpu_quota));
+    }
+    if let Some(niceness) = niceness {
+        properties.push(format!("Nice={}", niceness));
+    }
+    if properties.is_empty() {
+        return vec![];
+    }
+
+    let mut arguments = vec!["systemd-run".to_string()];
+    if !is_root() {
+        arguments.push("--user".to_string());
+    }
+    arguments.push("--scope".to_string());
+    arguments.push("--quiet".to_string());
+    for property in properties {
+        arguments.push("-p".to_string());
+        arguments.push(property);
+    }
+    arguments.push("--".to_string());
+    arguments
+}
+
+// "%" starts a specifier anywhere in a unit file, so it's doubled to keep it literal.
+fn escape_specifiers(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+// Quotes an argument for a unit file's ExecStart=, where "$" also starts a variable, so it's doubled
+// too.
+fn quote_argument(argument: &str) -> String {
+    let escaped = escape_specifiers(argument).replace('$', "$$");
+    if escaped.is_empty()
+        || escaped
+            .contains(|character: char| character.is_whitespace() || "\"';".contains(character))
+    {
+        format!("\"{}\"", escaped.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        escaped
+    }
+}
+
+// A unit that runs the server as a service. `memory_max` caps the whole process, so it needs to
+// leave room for the JVM's own memory on top of the heap. The limits are set on the service since
+// msc doesn't start a scope of its own inside one.
+pub fn systemd_unit(
+    instance: &str,
+    working_directory: &Path,
+    exec_start: &[String],
+    memory_max: Option<MemorySize>,
+    cpu_quota: Option<u16>,
+    niceness: Option<u16>,
+) -> String {
+    let exec_start: Vec<String> = exec_start
+        .iter()
+        .map(|argument| quote_argument(argument))
+        .collect();
+    let mut unit = format!(
+        "[Unit]
+Description=Minecraft server ({instance})
+After=network-online.target
+Wants=network-online.target
+
+[Service]
+Type=simple
+WorkingDirectory={working_directory}
+ExecStart={exec_start}
+Restart=on-failure
+RestartSec=10
+# Java exits with 143 when systemd stops it with SIGTERM.
+SuccessExitStatus=143
+",
+        instance = instance,
+        // The whole value is the path, spaces included, and systemd doesn't remove quotes from it.
+        working_directory = escape_specifiers(&working_directory.to_string_lossy()),
+        exec_start = exec_start.join(" ")
+    );
+    if let Some(memory_max) = memory_max {
+        unit.push_str(&format!("MemoryMax={}\n", memory_max));
+    }
+    if let Some(cpu_quota) = cpu_quota {
+        unit.push_str(&format!("CPUQuota={}%\n", cpu_quota));
+    }
+    if let Some(niceness) = niceness {
+        unit.push_str(&format!("Nice={}\n", niceness));
+    }
+    unit.push_str(
+        "# Uncomment to run the server as its own user.
+#User=minecraft
+
+[Install]
+WantedBy=multi-user.target
+",
+    );
+    unit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_carry_the_configured_limits() {
+        let unit = systemd_unit(
+            "default",
+            Path::new("/srv/minecraft"),
+            &["msc".to_string(), "--start".to_string()],
+            Some(MemorySize { megabytes: 6144 }),
+            Some(200),
+            Some(10),
+        );
+        let service = unit.split("[Install]").next().unwrap();
+        for line in ["MemoryMax=6G", "CPUQuota=200%", "Nice=10"] {
+            assert!(service.lines().any(|l| l == line), "{} in {}", line, unit);
+        }
+        let unit = systemd_unit(
+            "default",
+            Path::new("/srv/My Server 100%"),
+            &[],
+            None,
+            None,
+            None,
+        );
+        assert!(unit
+            .lines()
+            .any(|line| line == "WorkingDirectory=/srv/My Server 100%%"));
+        assert!(
+            !unit.contains("CPUQuota") && !unit.contains("Nice=") && !unit.contains("MemoryMax")
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::OnceLock;
+
+use console::{Color, Style};
+use dialoguer::theme::{ColorfulTheme, SimpleTheme, Theme};
+use serde::{Deserialize, Serialize};
+
+// How prompts look, e.g. `theme: Some((colorful: true, promptColor: Some("cyan")))` in the
+// configuration file. The colors only apply to the colorful theme.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+#[allow(non_snake_case)]
+pub struct PromptTheme {
+    pub colorful: bool,
+    pub promptColor: Option<String>,
+    pub selectionColor: Option<String>,
+}
+
+static THEME: OnceLock<Box<dyn Theme + Send + Sync>> = OnceLock::new();
+
+fn parse_color(name: &str) -> Option<Color> {
+    match name.to_ascii_lowercase().as_str() {
+        "black" => Some(Color::Black),
+        "red" => Some(Color::Red),
+        "green" => Some(Color::Green),
+        "yellow" => Some(Color::Yellow),
+        "blue" => Some(Color::Blue),
+        "magenta" => Some(Color::Magenta),
+        "cyan" => Some(Color::Cyan),
+        "white" => Some(Color::White),
+        _ => None,
+    }
+}
+
+// Sets the theme used by every prompt from then on. Only the first call has an effect.
+pub fn set_theme(prompt_theme: &PromptTheme) {
+    let theme: Box<dyn Theme + Send + Sync> = if prompt_theme.colorful {
+        let mut theme = ColorfulTheme::default();
+        if let Some(color) = prompt_theme.promptColor.as_deref().and_then(parse_color) {
+            theme.prompt_style = Style::new().for_stderr().fg(color).bold();
+        }
+        if let Some(color) = prompt_theme.selectionColor.as_deref().and_then(parse_color) {
+            theme.active_item_style = Style::new().for_stderr().fg(color);
+            theme.active_item_prefix = Style::new()
+                .for_stderr()
+                .fg(color)
+                .apply_to("❯".to_string());
+        }
+        Box::new(theme)
+    } else {
+        Box::new(SimpleTheme)
+    };
+    let _ = THEME.set(theme);
+}
+
+// The theme to pass to every `dialoguer` prompt. Until a theme is set, prompts look the same as
+// `dialoguer`'s defaults.
+pub fn theme() -> &'static dyn Theme {
+    match THEME.get() {
+        Some(theme) => theme.as_ref(),
+        None => &SimpleTheme,
+    }
+}
@@ -0,0 +1,382 @@
+use std::{
+    io::{self, Error, ErrorKind},
+    net::UdpSocket,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::address::{http_request, lan_address, HttpResponse};
+
+// Where routers listen for SSDP searches, which is how UPnP devices are found on the network.
+const SSDP_ADDRESS: &str = "239.255.255.250:1900";
+
+const GATEWAY_DEVICE_TYPE: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+
+// The router services that can forward ports, in the order they're tried.
+const FORWARDING_SERVICE_TYPES: [&str; 3] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+// How long routers get to answer the search, which is also how long launching waits without one.
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Shown in the router's list of forwarded ports.
+const MAPPING_DESCRIPTION: &str = "Minecraft server";
+
+// How long the router keeps the port forwarded unless it's asked again, so that a port isn't left
+// forwarded for good when msc can't remove it, e.g. after a crash or a power cut. The mapping is
+// renewed halfway through.
+const LEASE_DURATION: Duration = Duration::from_secs(60 * 60);
+
+// A port that the router forwards to this machine until it's removed.
+pub struct PortMapping {
+    host: String,
+    port: u16,
+    control_path: String,
+    service_type: &'static str,
+    internal_client: String,
+    // In seconds, where 0 is forever for routers that only forward ports for good.
+    lease_seconds: u64,
+    pub external_port: u16,
+    // The router's public address, when it would tell.
+    pub external_address: Option<String>,
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+// Splits "http://192.168.1.1:5000/rootDesc.xml" into the host, the port, and the path.
+fn split_url(url: &str) -> Option<(String, u16, String)> {
+    let rest = url.strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (authority, 80),
+    };
+    Some((host.to_string(), port, path.to_string()))
+}
+
+// The text of the first <tag> in the XML, which is all that the router's answers need.
+fn tag_value<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    Some(xml[start..end].trim())
+}
+
+// Asks the network for a router and returns the address of its description.
+fn find_gateway() -> io::Result<String> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_read_timeout(Some(SEARCH_TIMEOUT))?;
+    let search = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+        SSDP_ADDRESS, GATEWAY_DEVICE_TYPE
+    );
+    socket.send_to(search.as_bytes(), SSDP_ADDRESS)?;
+    let mut buffer = [0; 2048];
+    loop {
+        let length = match socket.recv_from(&mut buffer) {
+            Ok((length, _)) => length,
+            Err(error)
+                if error.kind() == ErrorKind::WouldBlock || error.kind() == ErrorKind::TimedOut =>
+            {
+                return Err(Error::new(
+                    ErrorKind::NotFound,
+                    "no router on the network answered, so it may not support UPnP or have it turned off",
+                ))
+            }
+            Err(error) => return Err(error),
+        };
+        let answer = String::from_utf8_lossy(&buffer[..length]);
+        let location = answer.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            Some(value.trim().to_string()).filter(|_| name.trim().eq_ignore_ascii_case("location"))
+        });
+        if let Some(location) = location {
+            return Ok(location);
+        }
+    }
+}
+
+fn soap_request(
+    host: &str,
+    port: u16,
+    control_path: &str,
+    service_type: &str,
+    action: &str,
+    arguments: &[(&str, String)],
+) -> io::Result<HttpResponse> {
+    let arguments: String = arguments
+        .iter()
+        .map(|(name, value)| format!("<{0}>{1}</{0}>", name, value))
+        .collect();
+    let body = format!(
+        "<?xml version=\"1.0\"?>\r\n<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body><u:{action} xmlns:u=\"{service}\">{arguments}</u:{action}></s:Body></s:Envelope>\r\n",
+        action = action,
+        service = service_type,
+        arguments = arguments
+    );
+    let response = http_request(
+        host,
+        port,
+        &format!(
+            "POST {} HTTP/1.0\r\nHost: {}:{}\r\nContent-Type: text/xml; charset=\"utf-8\"\r\nContent-Length: {}\r\nSOAPAction: \"{}#{}\"\r\n\r\n{}",
+            control_path,
+            host,
+            port,
+            body.len(),
+            service_type,
+            action,
+            body
+        ),
+    )?;
+    if !response.is_ok() {
+        // e.g. "ConflictInMappingEntry" when another machine already has the port.
+        let reason = tag_value(&response.body, "errorDescription")
+            .map(str::to_string)
+            .unwrap_or_else(|| response.status_line.clone());
+        return Err(Error::other(format!("the router refused: {}", reason)));
+    }
+    Ok(response)
+}
+
+// Asks the router to forward the TCP port to this machine, as players would otherwise need it
+// forwarded by hand in the router's settings.
+pub fn try_upnp_map(port: u16) -> io::Result<PortMapping> {
+    let location = find_gateway()?;
+    let (host, description_port, path) = split_url(&location)
+        .ok_or_else(|| invalid("the router's description isn't at an http:// address"))?;
+    let description = http_request(
+        &host,
+        description_port,
+        &format!(
+            "GET {} HTTP/1.0\r\nHost: {}:{}\r\n\r\n",
+            path, host, description_port
+        ),
+    )?;
+    if !description.is_ok() {
+        return Err(invalid(&description.status_line));
+    }
+    // Each service is described in its own <service> element, with the address to control it.
+    let (service_type, control_url) = FORWARDING_SERVICE_TYPES
+        .iter()
+        .find_map(|service_type| {
+            description
+                .body
+                .split("<service>")
+                .skip(1)
+                .find_map(|service| {
+                    if tag_value(service, "serviceType")? != *service_type {
+                        return None;
+                    }
+                    Some((*service_type, tag_value(service, "controlURL")?.to_string()))
+                })
+        })
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::Unsupported,
+                "the router doesn't offer port forwarding through UPnP",
+            )
+        })?;
+    let (host, port_of_control, control_path) = match split_url(&control_url) {
+        Some(parts) => parts,
+        None if control_url.starts_with('/') => (host, description_port, control_url),
+        None => (host, description_port, format!("/{}", control_url)),
+    };
+    let internal_client = lan_address().ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            "this machine's address on the network wasn't found",
+        )
+    })?;
+
+    let mut mapping = PortMapping {
+        host,
+        port: port_of_control,
+        control_path,
+        service_type,
+        internal_client: internal_client.to_string(),
+        lease_seconds: LEASE_DURATION.as_secs(),
+        external_port: port,
+        external_address: None,
+    };
+    if let Err(error) = mapping.add() {
+        // Some routers only forward ports for good, and say so with error 725.
+        if !error.to_string().contains("OnlyPermanentLeasesSupported") {
+            return Err(error);
+        }
+        mapping.lease_seconds = 0;
+        mapping.add()?;
+    }
+    mapping.external_address = soap_request(
+        &mapping.host,
+        mapping.port,
+        &mapping.control_path,
+        mapping.service_type,
+        "GetExternalIPAddress",
+        &[],
+    )
+    .ok()
+    .and_then(|response| {
+        tag_value(&response.body, "NewExternalIPAddress")
+            .filter(|address| !address.is_empty())
+            .map(str::to_string)
+    });
+    Ok(mapping)
+}
+
+impl PortMapping {
+    // Forwarding a port that's already forwarded to this machine renews its lease.
+    fn add(&self) -> io::Result<()> {
+        soap_request(
+            &self.host,
+            self.port,
+            &self.control_path,
+            self.service_type,
+            "AddPortMapping",
+            &[
+                ("NewRemoteHost", String::new()),
+                ("NewExternalPort", self.external_port.to_string()),
+                ("NewProtocol", "TCP".to_string()),
+                ("NewInternalPort", self.external_port.to_string()),
+                ("NewInternalClient", self.internal_client.clone()),
+                ("NewEnabled", "1".to_string()),
+                ("NewPortMappingDescription", MAPPING_DESCRIPTION.to_string()),
+                ("NewLeaseDuration", self.lease_seconds.to_string()),
+            ],
+        )
+        .map(|_| ())
+    }
+    pub fn remove(&self) -> io::Result<()> {
+        soap_request(
+            &self.host,
+            self.port,
+            &self.control_path,
+            self.service_type,
+            "DeletePortMapping",
+            &[
+                ("NewRemoteHost", String::new()),
+                ("NewExternalPort", self.external_port.to_string()),
+                ("NewProtocol", "TCP".to_string()),
+            ],
+        )
+        .map(|_| ())
+    }
+}
+
+// Renews a port mapping while the server runs, and removes it when msc is interrupted or terminated
+// so that the port isn't left forwarded until its lease runs out.
+pub struct RenewedMapping {
+    mapping: Arc<PortMapping>,
+    stop: Arc<AtomicBool>,
+    renewer: JoinHandle<()>,
+}
+
+impl RenewedMapping {
+    pub fn new(mapping: PortMapping) -> Self {
+        let mapping = Arc::new(mapping);
+        let stop = Arc::new(AtomicBool::new(false));
+        signals::catch();
+        let renewer = {
+            let mapping = Arc::clone(&mapping);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                let mut renewed_at = Instant::now();
+                while !stop.load(Ordering::SeqCst) {
+                    if let Some(signal) = signals::received() {
+                        let _ = mapping.remove();
+                        signals::raise(signal);
+                    }
+                    if mapping.lease_seconds > 0 && renewed_at.elapsed() >= LEASE_DURATION / 2 {
+                        // A failed renewal is tried again a minute later, well within the lease.
+                        renewed_at = match mapping.add() {
+                            Ok(()) => Instant::now(),
+                            Err(_) => renewed_at + Duration::from_secs(60),
+                        };
+                    }
+                    thread::sleep(Duration::from_millis(250));
+                }
+            })
+        };
+        RenewedMapping {
+            mapping,
+            stop,
+            renewer,
+        }
+    }
+
+    pub fn external_port(&self) -> u16 {
+        self.mapping.external_port
+    }
+
+    // Stops renewing the mapping and removes it. A signal that arrived in the meantime is raised
+    // again once the mapping is gone, as it would have stopped msc without the mapping.
+    pub fn remove(self) -> io::Result<()> {
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = self.renewer.join();
+        let result = self.mapping.remove();
+        if let Some(signal) = signals::received() {
+            signals::raise(signal);
+        }
+        signals::restore();
+        result
+    }
+}
+
+#[cfg(unix)]
+mod signals {
+    use std::sync::atomic::{AtomicI32, Ordering};
+
+    const SIGNALS: [libc::c_int; 2] = [libc::SIGINT, libc::SIGTERM];
+
+    static RECEIVED: AtomicI32 = AtomicI32::new(0);
+
+    extern "C" fn record(signal: libc::c_int) {
+        RECEIVED.store(signal, Ordering::SeqCst);
+    }
+
+    pub fn catch() {
+        for signal in SIGNALS {
+            unsafe { libc::signal(signal, record as *const () as libc::sighandler_t) };
+        }
+    }
+
+    pub fn restore() {
+        for signal in SIGNALS {
+            unsafe { libc::signal(signal, libc::SIG_DFL) };
+        }
+    }
+
+    pub fn received() -> Option<libc::c_int> {
+        Some(RECEIVED.load(Ordering::SeqCst)).filter(|signal| *signal != 0)
+    }
+
+    // Stops msc the way the signal would have.
+    pub fn raise(signal: libc::c_int) {
+        restore();
+        unsafe { libc::raise(signal) };
+    }
+}
+
+// Elsewhere a mapping that's left behind stays only until its lease runs out.
+#[cfg(not(unix))]
+mod signals {
+    pub fn catch() {}
+
+    pub fn restore() {}
+
+    pub fn received() -> Option<i32> {
+        None
+    }
+
+    pub fn raise(_signal: i32) {}
+}
@@ -0,0 +1,364 @@
+use std::{
+    fmt, fs,
+    net::TcpListener,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    icon::{read_png_dimensions, SERVER_ICON_FILENAME, SERVER_ICON_SIZE},
+    machine::{ConfigurationOptionType, MinecraftServerConfiguration},
+    memory::MemorySize,
+    properties::{ServerProperties, CHOICE_PROPERTIES},
+    rcon::{rcon_password_weakness, rcon_port},
+    schedule::parse_time_of_day,
+    systemd::{can_run_in_scope, is_in_systemd_unit},
+    world::{check_region_files, REGION_DIRECTORIES},
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+// A problem with the configuration that was found before launching the server.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(formatter, "{}: {}", label, self.message)
+    }
+}
+
+// The port the server listens on: the configured port, then the one in server.properties, then
+// Minecraft's default.
+pub fn server_port(configuration: &MinecraftServerConfiguration) -> u16 {
+    configuration
+        .port
+        .or_else(|| {
+            ServerProperties::read(Path::new("server.properties"))
+                .ok()?
+                .get("server-port")?
+                .parse()
+                .ok()
+        })
+        .unwrap_or(25565)
+}
+
+// Where the server keeps the world: the world folder inside the universe folder, which is the
+// server's directory unless it's set. Without a world name, the server uses level-name from
+// server.properties, and "world" when that isn't set either. Options that the server type leaves out
+// of the command don't count.
+pub fn resolve_world_path(configuration: &MinecraftServerConfiguration) -> PathBuf {
+    let server_type = configuration.serverType;
+    let universe = configuration
+        .universe
+        .as_ref()
+        .filter(|_| server_type.supports("universe"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let world = configuration
+        .world
+        .clone()
+        .filter(|_| server_type.supports("world"))
+        .or_else(|| {
+            ServerProperties::read(Path::new("server.properties"))
+                .ok()?
+                .get("level-name")
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string())
+        })
+        .unwrap_or_else(|| "world".to_string());
+    universe.join(world)
+}
+
+// The worlds in a universe folder, by name. Any folder with a level.dat is a world, which skips the
+// universe's other folders like logs and mods.
+pub fn list_worlds(universe: &Path) -> Vec<String> {
+    let mut worlds: Vec<String> = match fs::read_dir(universe) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().join("level.dat").is_file())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    worlds.sort();
+    worlds
+}
+
+// The number of single-character edits that turn one name into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_character) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_character) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_character == *b_character { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// A world that doesn't exist in a universe that has other worlds, which means the server would
+// generate a fresh world next to them. An empty universe is left out, since that's how every
+// new server starts.
+pub struct MissingWorld {
+    pub name: String,
+    pub universe: PathBuf,
+    pub worlds: Vec<String>,
+}
+
+impl MissingWorld {
+    // The existing world that the name was most likely meant to be, if it's close enough to be a
+    // typo rather than a new name.
+    pub fn suggestion(&self) -> Option<&String> {
+        let name = self.name.to_lowercase();
+        self.worlds
+            .iter()
+            .map(|world| (edit_distance(&name, &world.to_lowercase()), world))
+            .filter(|(distance, _)| *distance <= 2.max(name.chars().count() / 4))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, world)| world)
+    }
+}
+
+pub fn find_missing_world(configuration: &MinecraftServerConfiguration) -> Option<MissingWorld> {
+    if configuration.universe.is_none() || !configuration.serverType.supports("universe") {
+        return None;
+    }
+    let world = resolve_world_path(configuration);
+    if world.exists() {
+        return None;
+    }
+    let universe = world.parent()?.to_path_buf();
+    let worlds = list_worlds(&universe);
+    if worlds.is_empty() {
+        return None;
+    }
+    Some(MissingWorld {
+        name: world.file_name()?.to_string_lossy().to_string(),
+        universe,
+        worlds,
+    })
+}
+
+// Checks everything that would make the server fail to start or misbehave, collecting all of the
+// problems instead of stopping at the first one.
+pub fn validate_configuration(
+    configuration: &MinecraftServerConfiguration,
+    check_port: bool,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut report =
+        |severity: Severity, message: String| diagnostics.push(Diagnostic { severity, message });
+
+    if configuration.port == Some(0) {
+        report(
+            Severity::Error,
+            "The port must be between 1 and 65535.".to_string(),
+        );
+    }
+    if configuration.backupRetention == Some(0) {
+        report(
+            Severity::Error,
+            "At least 1 backup must be kept, otherwise every backup would be removed right away."
+                .to_string(),
+        );
+    }
+    if let Some(time) = &configuration.dailyRestartTime {
+        if parse_time_of_day(time).is_none() {
+            report(
+                Severity::Error,
+                format!(
+                    "The daily restart time \"{}\" isn't a 24-hour time like 04:30.",
+                    time
+                ),
+            );
+        }
+    }
+    if let Ok(properties) = ServerProperties::read(Path::new("server.properties")) {
+        // The server quietly falls back to the default for a name it doesn't know.
+        for property in &CHOICE_PROPERTIES {
+            if let Some(value) = properties.get(property.key) {
+                if property.parse(value).is_none() {
+                    report(
+                        Severity::Warning,
+                        format!(
+                            "{}={} in server.properties isn't one the server knows, so it uses {}. Choose one of: {}.",
+                            property.key,
+                            value,
+                            property.default,
+                            property.choices.join(", ")
+                        ),
+                    );
+                }
+            }
+        }
+        if rcon_port(&properties).is_some() {
+            let password = properties.get("rcon.password").unwrap_or_default();
+            if let Some(weakness) = rcon_password_weakness(password) {
+                report(
+                    Severity::Warning,
+                    format!(
+                        "RCON is enabled, but its password is weak: {}. Anyone who can reach the RCON port gets full control of the server.",
+                        weakness
+                    ),
+                );
+            }
+        }
+    }
+    if let (Some(memory_limit), Some(max_memory)) =
+        (configuration.memoryLimit, configuration.maxMemory)
+    {
+        if memory_limit <= max_memory {
+            report(Severity::Warning, format!(
+                "The memory limit ({}) leaves no room above the maximum memory ({}) for Java itself, so the server will likely be killed once its heap fills up.",
+                memory_limit, max_memory
+            ));
+        }
+    }
+    let has_resource_limits = configuration.memoryLimit.is_some()
+        || configuration.cpuQuota.is_some()
+        || configuration.niceness.is_some();
+    // In a unit, e.g. the exported one, the limits are the unit's to set.
+    if has_resource_limits && !can_run_in_scope() && !is_in_systemd_unit() {
+        report(
+            Severity::Warning,
+            "The memory limit, CPU quota, and niceness need Linux with systemd, and a systemd user session for other users than root, so the server will start without them.".to_string(),
+        );
+    }
+    if configuration.cpuQuota == Some(0) {
+        report(
+            Severity::Error,
+            "The CPU quota must be at least 1%.".to_string(),
+        );
+    }
+    if configuration.niceness.is_some_and(|niceness| niceness > 19) {
+        report(
+            Severity::Error,
+            "The niceness must be between 0 and 19.".to_string(),
+        );
+    }
+    if let (Some(max_memory), Some(min_memory)) = (configuration.maxMemory, configuration.minMemory)
+    {
+        if min_memory > max_memory {
+            report(Severity::Error, format!(
+                "The initial memory ({}) is more than the maximum memory ({}), so Java won't start.",
+                min_memory, max_memory
+            ));
+        }
+    }
+    if let Some(max_memory) = configuration.maxMemory {
+        if max_memory < (MemorySize { megabytes: 512 }) {
+            report(
+                Severity::Warning,
+                format!(
+                    "The maximum memory ({}) is very low, so the server will likely run out of memory.",
+                    max_memory
+                ),
+            );
+        }
+    }
+
+    // The server quietly ignores an icon it can't use.
+    let icon = Path::new(SERVER_ICON_FILENAME);
+    if icon.is_file() {
+        match read_png_dimensions(icon) {
+            Ok((width, height)) if width != SERVER_ICON_SIZE || height != SERVER_ICON_SIZE => {
+                report(Severity::Warning, format!(
+                    "{} is {}×{} pixels, but the server only shows icons that are exactly {size}×{size}. Resize it in an image editor.",
+                    SERVER_ICON_FILENAME, width, height, size = SERVER_ICON_SIZE
+                ))
+            }
+            Ok(_) => {}
+            Err(error) => report(
+                Severity::Warning,
+                format!(
+                    "{} can't be used as the server's icon: {}.",
+                    SERVER_ICON_FILENAME, error
+                ),
+            ),
+        }
+    }
+
+    if configuration.checkRegionFiles {
+        let world = resolve_world_path(configuration);
+        let suspects: Vec<String> = REGION_DIRECTORIES
+            .iter()
+            .flat_map(|directory| check_region_files(&world.join(directory)))
+            .collect();
+        if !suspects.is_empty() {
+            // Long lists of files would bury the other warnings.
+            let mut listed = suspects[..suspects.len().min(5)].join(", ");
+            if suspects.len() > 5 {
+                listed += &format!(", and {} more", suspects.len() - 5);
+            }
+            report(Severity::Warning, format!(
+                "These region files look truncated, so the server may crash when it loads their chunks: {}. If it does, restore the world from a backup.",
+                listed
+            ));
+        }
+    }
+
+    if let Some(missing) = find_missing_world(configuration) {
+        let message = match missing.suggestion() {
+            Some(suggestion) => format!(
+                "There's no world named \"{}\" in {}, so the server will create a new one. Did you mean \"{}\"?",
+                missing.name,
+                missing.universe.display(),
+                suggestion
+            ),
+            None => format!(
+                "There's no world named \"{}\" in {}, so the server will create a new one. The worlds there are: {}.",
+                missing.name,
+                missing.universe.display(),
+                missing.worlds.join(", ")
+            ),
+        };
+        report(Severity::Warning, message);
+    }
+
+    for property in configuration.serverType.unsupported_options() {
+        let is_set = match configuration.get(property.to_string()) {
+            ConfigurationOptionType::Bool(value) => value,
+            ConfigurationOptionType::OptionU16(value) => value.is_some(),
+            ConfigurationOptionType::OptionString(value) => value.is_some(),
+            ConfigurationOptionType::OptionMemory(value) => value.is_some(),
+            ConfigurationOptionType::KeyValues(values) => !values.is_empty(),
+            ConfigurationOptionType::ServerType(_) | ConfigurationOptionType::Choice(_) => false,
+        };
+        if is_set {
+            report(
+                Severity::Warning,
+                format!(
+                    "{} servers don't support --{}, so it's left out of the command.",
+                    configuration.serverType.name(),
+                    property
+                ),
+            );
+        }
+    }
+
+    // Binding the port ourselves is the quickest way to tell whether another server is already
+    // using it, rather than finding out after Java has booted.
+    let port = server_port(configuration);
+    if check_port && port != 0 && TcpListener::bind(("0.0.0.0", port)).is_err() {
+        report(Severity::Error, format!(
+            "Port {} is already in use, most likely by another server. Stop it, choose another port, or pass --skip-port-check.",
+            port
+        ));
+    }
+
+    diagnostics
+}
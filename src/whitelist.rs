@@ -0,0 +1,40 @@
+use std::{fs, io, path::Path};
+
+use crate::properties::ServerProperties;
+
+pub const WHITELIST_FILENAME: &str = "whitelist.json";
+
+// `white-list` in server.properties decides whether the whitelist is used at all, while whitelist.json
+// lists the players that it lets in. The server only fills in whitelist.json through its
+// `whitelist add <player>` command, since entries need the player's UUID.
+pub fn is_whitelist_enabled() -> bool {
+    ServerProperties::read(Path::new("server.properties"))
+        .map(|properties| properties.get("white-list").map(str::trim) == Some("true"))
+        .unwrap_or(false)
+}
+
+// Turns the whitelist on or off in server.properties. `enforce-whitelist` is changed along with it
+// so that players who are removed from the list while the server runs are kicked as well. Enabling
+// it also creates an empty whitelist.json, which the server would otherwise only create once it's
+// running.
+pub fn set_whitelist_enabled(enabled: bool) -> io::Result<()> {
+    let path = Path::new("server.properties");
+    let mut properties = ServerProperties::read_or_default(path)?;
+    let value = if enabled { "true" } else { "false" };
+    properties.set("white-list", value);
+    properties.set("enforce-whitelist", value);
+    properties.write(path)?;
+
+    if enabled && !Path::new(WHITELIST_FILENAME).exists() {
+        fs::write(WHITELIST_FILENAME, "[]\n")?;
+    }
+    Ok(())
+}
+
+// Counts the players in whitelist.json, where every entry has a "name". Reading the file this
+// loosely is enough to tell whether anyone can join.
+pub fn count_whitelisted_players() -> usize {
+    fs::read_to_string(WHITELIST_FILENAME)
+        .map(|whitelist| whitelist.matches("\"name\"").count())
+        .unwrap_or(0)
+}
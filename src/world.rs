@@ -0,0 +1,281 @@
+use std::{
+    fs,
+    io::{Error, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::properties::ServerProperties;
+
+// The server holds a lock on this file inside the world for as long as it's running.
+const SESSION_LOCK_FILENAME: &str = "session.lock";
+
+// Java locks session.lock with fcntl, so asking who would block a write lock tells whether a server
+// is using the world. The file itself stays behind after the server stops.
+#[cfg(unix)]
+fn is_session_locked(session_lock: &Path) -> bool {
+    use std::os::unix::io::AsRawFd;
+
+    let file = match fs::File::open(session_lock) {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as _;
+    lock.l_whence = libc::SEEK_SET as _;
+    let result = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETLK, &mut lock) };
+    result == 0 && lock.l_type != libc::F_UNLCK as _
+}
+
+// Windows won't let a locked file be opened for writing.
+#[cfg(not(unix))]
+fn is_session_locked(session_lock: &Path) -> bool {
+    session_lock.is_file()
+        && fs::OpenOptions::new()
+            .append(true)
+            .open(session_lock)
+            .is_err()
+}
+
+// Where Bukkit-based servers like Paper load their plugins from.
+const PLUGINS_DIRECTORY: &str = "plugins";
+
+// The sorted names of the directory's entries that `keep` accepts. A missing directory has none.
+fn list_entries(directory: &Path, keep: impl Fn(&Path) -> bool) -> Vec<String> {
+    let mut names: Vec<String> = match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| keep(&entry.path()))
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    names.sort_by_key(|name| name.to_lowercase());
+    names
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|found| found.eq_ignore_ascii_case(extension))
+}
+
+// The plugin jars in the server's plugins folder. Plugins keep their settings in folders next to
+// their jars, so only the jars are listed.
+pub fn list_plugins() -> Vec<String> {
+    plugin_jars(Path::new(PLUGINS_DIRECTORY))
+}
+
+fn plugin_jars(directory: &Path) -> Vec<String> {
+    list_entries(directory, |path| {
+        path.is_file() && has_extension(path, "jar")
+    })
+}
+
+// The datapacks in the world, which are either folders or zip files.
+pub fn list_datapacks(world: &Path) -> Vec<String> {
+    list_entries(&world.join("datapacks"), |path| {
+        path.is_dir() || (path.is_file() && has_extension(path, "zip"))
+    })
+}
+
+// Region files are made of 4 KiB sectors, starting with two sectors of header: where each chunk is,
+// then when each was saved.
+const REGION_SECTOR_SIZE: u64 = 4096;
+const REGION_HEADER_SIZE: u64 = 2 * REGION_SECTOR_SIZE;
+
+// The folders that hold the overworld's, the Nether's, and the End's region files.
+pub const REGION_DIRECTORIES: [&str; 3] = ["region", "DIM-1/region", "DIM1/region"];
+
+// Whether the region file looks cut short: a size that isn't whole sectors, or a chunk that's
+// supposed to be past its end. Empty files are fine, since the server creates them before it saves
+// anything to them.
+fn is_region_file_suspect(path: &Path) -> bool {
+    let size = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return true,
+    };
+    if size == 0 {
+        return false;
+    }
+    if size < REGION_HEADER_SIZE || size % REGION_SECTOR_SIZE != 0 {
+        return true;
+    }
+    let mut locations = [0u8; REGION_SECTOR_SIZE as usize];
+    if fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut locations))
+        .is_err()
+    {
+        return true;
+    }
+    // Each chunk's location is a 3-byte offset and a 1-byte length, both counted in sectors.
+    locations.chunks_exact(4).any(|location| {
+        let offset = u64::from_be_bytes([0, 0, 0, 0, 0, location[0], location[1], location[2]]);
+        let sectors = location[3] as u64;
+        offset != 0 && (offset + sectors) * REGION_SECTOR_SIZE > size
+    })
+}
+
+// The region files in the folder that look truncated or corrupted, e.g. after the server was killed
+// while saving. Only their sizes and headers are read, so that large worlds are checked quickly.
+pub fn check_region_files(dir: &Path) -> Vec<String> {
+    let mut suspects: Vec<String> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| has_extension(path, "mca") && is_region_file_suspect(path))
+            .map(|path| path.display().to_string())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    suspects.sort();
+    suspects
+}
+
+pub fn is_world_in_use(world: &Path) -> bool {
+    is_session_locked(&world.join(SESSION_LOCK_FILENAME))
+}
+
+// A world that was moved to its new name, which can be put back, e.g. when the configuration that
+// points at the new name couldn't be saved.
+pub struct RenamedWorld {
+    from: PathBuf,
+    to: PathBuf,
+    properties_path: PathBuf,
+    // The server's properties from before the rename, when they exist.
+    properties: Option<ServerProperties>,
+}
+
+impl RenamedWorld {
+    // Moves the folder back and restores level-name.
+    pub fn undo(self) -> Result<(), Error> {
+        fs::rename(&self.to, &self.from)?;
+        match self.properties {
+            Some(properties) => properties.write(&self.properties_path),
+            None => Ok(()),
+        }
+    }
+}
+
+// Moves the world's folder next to itself under the new name, and points level-name in
+// server.properties at it when the server's properties exist. When level-name can't be changed,
+// the folder is moved back, so that the server still finds the world.
+pub fn rename_world(world: &Path, new_name: &str) -> Result<RenamedWorld, Error> {
+    rename_world_with_properties(world, new_name, Path::new("server.properties"))
+}
+
+fn rename_world_with_properties(
+    world: &Path,
+    new_name: &str,
+    properties_path: &Path,
+) -> Result<RenamedWorld, Error> {
+    let renamed = world.with_file_name(new_name);
+    fs::rename(world, &renamed)?;
+    let properties = ServerProperties::read(properties_path).ok();
+    if let Some(properties) = &properties {
+        let mut changed = properties.clone();
+        changed.set("level-name", new_name);
+        if let Err(error) = changed.write(properties_path) {
+            fs::rename(&renamed, world)?;
+            return Err(error);
+        }
+    }
+    Ok(RenamedWorld {
+        from: world.to_path_buf(),
+        to: renamed,
+        properties_path: properties_path.to_path_buf(),
+        properties,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renaming_a_world_can_be_undone() {
+        let directory =
+            std::env::temp_dir().join(format!("msc-test-rename-world-{}", std::process::id()));
+        let world = directory.join("world");
+        fs::create_dir_all(&world).unwrap();
+        fs::write(world.join("level.dat"), "level").unwrap();
+        let properties_path = directory.join("server.properties");
+        fs::write(&properties_path, "motd=Hi\nlevel-name=world\n").unwrap();
+
+        let renamed = rename_world_with_properties(&world, "survival", &properties_path).unwrap();
+        assert!(directory.join("survival").join("level.dat").is_file());
+        assert!(!world.exists());
+        let level_name = |path: &Path| {
+            ServerProperties::read(path)
+                .unwrap()
+                .get("level-name")
+                .map(str::to_string)
+        };
+        assert_eq!(level_name(&properties_path).as_deref(), Some("survival"));
+
+        renamed.undo().unwrap();
+        assert!(world.join("level.dat").is_file());
+        assert!(!directory.join("survival").exists());
+        assert_eq!(level_name(&properties_path).as_deref(), Some("world"));
+        assert_eq!(
+            fs::read_to_string(&properties_path).unwrap(),
+            "motd=Hi\nlevel-name=world\n"
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn plugins_and_datapacks_are_listed_by_name() {
+        let directory =
+            std::env::temp_dir().join(format!("msc-test-add-ons-{}", std::process::id()));
+        let plugins = directory.join("plugins");
+        let world = directory.join("world");
+        fs::create_dir_all(plugins.join("WorldEdit")).unwrap();
+        fs::create_dir_all(world.join("datapacks").join("Custom Recipes")).unwrap();
+        for file in ["WorldEdit.jar", "essentialsx.JAR", "notes.txt"] {
+            fs::write(plugins.join(file), "").unwrap();
+        }
+        for file in ["vanilla-tweaks.zip", "readme.txt"] {
+            fs::write(world.join("datapacks").join(file), "").unwrap();
+        }
+
+        assert_eq!(plugin_jars(&plugins), ["essentialsx.JAR", "WorldEdit.jar"]);
+        assert_eq!(
+            list_datapacks(&world),
+            ["Custom Recipes", "vanilla-tweaks.zip"]
+        );
+        assert!(plugin_jars(&directory.join("missing")).is_empty());
+        assert!(list_datapacks(&directory.join("missing")).is_empty());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn truncated_region_files_are_suspects() {
+        let directory =
+            std::env::temp_dir().join(format!("msc-test-region-files-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        // A header whose first chunk starts right after it and takes up one sector.
+        let mut header = vec![0u8; REGION_HEADER_SIZE as usize];
+        header[..4].copy_from_slice(&[0, 0, 2, 1]);
+        let mut whole = header.clone();
+        whole.resize(3 * REGION_SECTOR_SIZE as usize, 0);
+        let files: [(&str, &[u8]); 6] = [
+            ("r.0.0.mca", &[]),
+            ("r.0.1.mca", &whole),
+            ("r.1.0.mca", &whole[..5000]),
+            ("r.1.1.mca", &header),
+            ("r.2.0.mca", &header[..REGION_SECTOR_SIZE as usize]),
+            ("r.2.1.mcc", &whole[..5000]),
+        ];
+        for (name, contents) in files {
+            fs::write(directory.join(name), contents).unwrap();
+        }
+
+        let suspects = check_region_files(&directory);
+        let expected: Vec<String> = ["r.1.0.mca", "r.1.1.mca", "r.2.0.mca"]
+            .iter()
+            .map(|name| directory.join(name).display().to_string())
+            .collect();
+        assert_eq!(suspects, expected);
+        assert!(check_region_files(&directory.join("missing")).is_empty());
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
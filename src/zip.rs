@@ -0,0 +1,582 @@
+use std::{
+    fs::File,
+    io::{Error, ErrorKind, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+// Just enough of the zip format to read a single file out of a jar: no zip64, encryption, or
+// compression methods other than stored and deflate, which is all that jars use.
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> usize {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]]) as usize
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> usize {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ]) as usize
+}
+
+fn read_at(file: &mut File, offset: usize, length: usize) -> Result<Vec<u8>, Error> {
+    let mut bytes = vec![0u8; length];
+    file.seek(SeekFrom::Start(offset as u64))?;
+    file.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+// Reads the file at `name` (e.g. "version.json") from the archive, or None if there's no such file.
+pub fn read_zip_entry(path: &Path, name: &str) -> Result<Option<Vec<u8>>, Error> {
+    let mut file = File::open(path)?;
+    let length = file.metadata()?.len() as usize;
+    // The end of central directory record is 22 bytes plus a comment of up to 65535 bytes.
+    let tail_length = length.min(22 + 65535);
+    let tail = read_at(&mut file, length - tail_length, tail_length)?;
+    let end = (0..tail_length.saturating_sub(21))
+        .rev()
+        .find(|&offset| tail[offset..offset + 4] == [0x50, 0x4b, 0x05, 0x06])
+        .ok_or_else(|| invalid("This isn't a zip file."))?;
+    let directory_size = u32_at(&tail, end + 12);
+    let directory_offset = u32_at(&tail, end + 16);
+    if directory_offset
+        .checked_add(directory_size)
+        .is_none_or(|directory_end| directory_end > length)
+    {
+        return Err(invalid("The zip file's directory is out of bounds."));
+    }
+    let directory = read_at(&mut file, directory_offset, directory_size)?;
+
+    let mut offset = 0;
+    while offset + 46 <= directory.len()
+        && directory[offset..offset + 4] == [0x50, 0x4b, 0x01, 0x02]
+    {
+        let method = u16_at(&directory, offset + 10);
+        let compressed_size = u32_at(&directory, offset + 20);
+        let name_length = u16_at(&directory, offset + 28);
+        let extra_length = u16_at(&directory, offset + 30);
+        let comment_length = u16_at(&directory, offset + 32);
+        let header_offset = u32_at(&directory, offset + 42);
+        let entry_name = directory.get(offset + 46..offset + 46 + name_length);
+        offset += 46 + name_length + extra_length + comment_length;
+        if entry_name != Some(name.as_bytes()) {
+            continue;
+        }
+
+        // The local header repeats the name and can have a different extra field.
+        if header_offset + 30 > length {
+            return Err(invalid("The zip file's entry is out of bounds."));
+        }
+        let header = read_at(&mut file, header_offset, 30)?;
+        let data_offset = header_offset + 30 + u16_at(&header, 26) + u16_at(&header, 28);
+        // Checked before reading so that a corrupt size can't make it allocate gigabytes.
+        if data_offset + compressed_size > length {
+            return Err(invalid("The zip file's entry is out of bounds."));
+        }
+        let data = read_at(&mut file, data_offset, compressed_size)?;
+        return match method {
+            0 => Ok(Some(data)),
+            8 => inflate(&data).map(Some),
+            _ => Err(invalid("The file uses an unsupported compression method.")),
+        };
+    }
+    Ok(None)
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl BitReader<'_> {
+    // Deflate packs bits starting from the least significant one.
+    fn bits(&mut self, needed: u32) -> Result<u32, Error> {
+        while self.count < needed {
+            let byte = *self
+                .data
+                .get(self.position)
+                .ok_or_else(|| invalid("The compressed data ended early."))?;
+            self.position += 1;
+            self.buffer |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1u64 << needed) - 1) as u32;
+        self.buffer = self.buffer.checked_shr(needed).unwrap_or(0);
+        self.count -= needed;
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+// A canonical Huffman code, stored as how many codes there are of each length and the symbols in
+// code order.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, Error> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - count < first {
+                return self
+                    .symbols
+                    .get((index + code - first) as usize)
+                    .copied()
+                    .ok_or_else(|| invalid("The compressed data is corrupt."));
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("The compressed data is corrupt."))
+    }
+}
+
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// The order that the lengths of the code length code are sent in.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), Error> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        if symbol < 256 {
+            output.push(symbol as u8);
+        } else if symbol == 256 {
+            return Ok(());
+        } else {
+            let index = symbol - 257;
+            if index >= LENGTH_BASES.len() {
+                return Err(invalid("The compressed data is corrupt."));
+            }
+            let length = LENGTH_BASES[index] as usize
+                + reader.bits(LENGTH_EXTRA_BITS[index] as u32)? as usize;
+            let index = distances.decode(reader)? as usize;
+            if index >= DISTANCE_BASES.len() {
+                return Err(invalid("The compressed data is corrupt."));
+            }
+            let distance = DISTANCE_BASES[index] as usize
+                + reader.bits(DISTANCE_EXTRA_BITS[index] as u32)? as usize;
+            if distance > output.len() {
+                return Err(invalid("The compressed data is corrupt."));
+            }
+            // Copies byte by byte since the match may overlap what it's producing.
+            let start = output.len() - distance;
+            for offset in 0..length {
+                output.push(output[start + offset]);
+            }
+        }
+    }
+}
+
+fn read_dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), Error> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    let mut code_lengths = [0u8; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_length_code.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => (
+                *lengths
+                    .last()
+                    .ok_or_else(|| invalid("The compressed data is corrupt."))?,
+                3 + reader.bits(2)?,
+            ),
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err(invalid("The compressed data is corrupt."));
+    }
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+// Decompresses raw deflate data (RFC 1951), which is how zip files compress their entries.
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut reader = BitReader {
+        data,
+        position: 0,
+        buffer: 0,
+        count: 0,
+    };
+    let mut output = Vec::new();
+    loop {
+        let is_last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align_to_byte();
+                let header = reader
+                    .data
+                    .get(reader.position..reader.position.saturating_add(4))
+                    .ok_or_else(|| invalid("The compressed data ended early."))?;
+                let length = u16_at(header, 0);
+                // The length is followed by its ones' complement.
+                if u16_at(header, 2) != !length & 0xffff {
+                    return Err(invalid("The compressed data is corrupt."));
+                }
+                reader.position += 4;
+                let stored = reader
+                    .data
+                    .get(reader.position..reader.position.saturating_add(length))
+                    .ok_or_else(|| invalid("The compressed data ended early."))?;
+                output.extend_from_slice(stored);
+                reader.position += length;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths);
+                let distances = Huffman::new(&[5u8; 30]);
+                inflate_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            _ => return Err(invalid("The compressed data is corrupt.")),
+        }
+        if is_last {
+            return Ok(output);
+        }
+    }
+}
+
+// Decompresses a gzip file (RFC 1952), which is deflate data behind a header. Only the first member
+// is read, and the checksum at the end isn't checked.
+pub fn gunzip(data: &[u8]) -> Result<Vec<u8>, Error> {
+    const TEXT: u8 = 1 << 0;
+    const HEADER_CRC: u8 = 1 << 1;
+    const EXTRA: u8 = 1 << 2;
+    const NAME: u8 = 1 << 3;
+    const COMMENT: u8 = 1 << 4;
+
+    if data.len() < 10 || data[0..2] != [0x1f, 0x8b] {
+        return Err(invalid("This isn't a gzip file."));
+    }
+    if data[2] != 8 {
+        return Err(invalid("The gzip file isn't compressed with deflate."));
+    }
+    let flags = data[3] & !TEXT;
+    let mut position = 10;
+    if flags & EXTRA != 0 {
+        let length = data
+            .get(position..position + 2)
+            .map(|bytes| u16_at(bytes, 0))
+            .ok_or_else(|| invalid("The gzip header ended early."))?;
+        position += 2 + length;
+    }
+    for field in [NAME, COMMENT] {
+        if flags & field != 0 {
+            let end = data
+                .get(position..)
+                .and_then(|rest| rest.iter().position(|byte| *byte == 0))
+                .ok_or_else(|| invalid("The gzip header ended early."))?;
+            position += end + 1;
+        }
+    }
+    if flags & HEADER_CRC != 0 {
+        position += 2;
+    }
+    inflate(
+        data.get(position..)
+            .ok_or_else(|| invalid("The gzip header ended early."))?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Made with Python's zlib at levels 0 and 9, which picks a fixed Huffman code for short input.
+    const STORED: [u8; 25] = [
+        0x01, 0x14, 0x00, 0xeb, 0xff, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x2c, 0x20, 0x48, 0x65, 0x6c,
+        0x6c, 0x6f, 0x2c, 0x20, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x21,
+    ];
+    const FIXED_HUFFMAN: [u8; 12] = [
+        0xf3, 0x48, 0xcd, 0xc9, 0xc9, 0xd7, 0x51, 0xf0, 0x40, 0xa2, 0x14, 0x01,
+    ];
+    // version_lines() at level 9, long enough for zlib to send its own Huffman code.
+    const DYNAMIC_HUFFMAN: [u8; 155] = [
+        0x85, 0xd2, 0xbb, 0x0a, 0xc2, 0x40, 0x10, 0x85, 0xe1, 0xde, 0xa7, 0x08, 0x5b, 0x4b, 0xd8,
+        0xd9, 0xfb, 0xfa, 0x32, 0x12, 0xc9, 0x16, 0x81, 0x98, 0xc0, 0x26, 0x6a, 0x11, 0x7c, 0x77,
+        0x63, 0xa7, 0x4c, 0x0e, 0xd3, 0x9e, 0x19, 0xbe, 0xea, 0xdf, 0xd4, 0xd0, 0xab, 0x4b, 0xa3,
+        0xa8, 0x35, 0xba, 0xd5, 0xea, 0xdc, 0xa8, 0xa9, 0xbb, 0x97, 0xff, 0xe5, 0x35, 0xd7, 0xb1,
+        0xbf, 0x3e, 0x4b, 0x5d, 0x86, 0x79, 0xda, 0x4f, 0x36, 0x6a, 0xbd, 0xcf, 0xcb, 0xda, 0xdd,
+        0xc6, 0xef, 0xeb, 0x5a, 0x1f, 0xe5, 0x7d, 0xda, 0x7e, 0x25, 0x62, 0x12, 0x01, 0x89, 0x04,
+        0xc9, 0x30, 0xc9, 0x00, 0xc9, 0x08, 0x92, 0x65, 0x92, 0x05, 0x92, 0x15, 0x24, 0xc7, 0x24,
+        0x07, 0x24, 0x27, 0x48, 0x9e, 0x49, 0x1e, 0x48, 0x5e, 0x90, 0x02, 0x93, 0x02, 0x90, 0x82,
+        0x20, 0x45, 0x26, 0x45, 0x20, 0x45, 0x41, 0x4a, 0x4c, 0x4a, 0x40, 0x4a, 0x82, 0x94, 0x99,
+        0x94, 0x81, 0x94, 0xa5, 0x32, 0x79, 0xe4, 0x04, 0x2a, 0x27, 0xb1, 0xf2, 0x83, 0xcc, 0x41,
+        0xe7, 0x74, 0xd0, 0xf9, 0x07,
+    ];
+    // Made with Python's zipfile: a stored META-INF/MANIFEST.MF and a deflated version.json.
+    const JAR: [u8; 307] = [
+        0x50, 0x4b, 0x03, 0x04, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x00, 0xa9,
+        0x52, 0x66, 0xcd, 0x17, 0x00, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00,
+        0x4d, 0x45, 0x54, 0x41, 0x2d, 0x49, 0x4e, 0x46, 0x2f, 0x4d, 0x41, 0x4e, 0x49, 0x46, 0x45,
+        0x53, 0x54, 0x2e, 0x4d, 0x46, 0x4d, 0x61, 0x6e, 0x69, 0x66, 0x65, 0x73, 0x74, 0x2d, 0x56,
+        0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3a, 0x20, 0x31, 0x2e, 0x30, 0x0d, 0x0a, 0x50, 0x4b,
+        0x03, 0x04, 0x14, 0x00, 0x00, 0x00, 0x08, 0x00, 0xc0, 0x6b, 0x4e, 0x5d, 0x8f, 0x7c, 0x79,
+        0xb6, 0x2e, 0x00, 0x00, 0x00, 0x39, 0x00, 0x00, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x76, 0x65,
+        0x72, 0x73, 0x69, 0x6f, 0x6e, 0x2e, 0x6a, 0x73, 0x6f, 0x6e, 0xab, 0x56, 0xca, 0x4c, 0x51,
+        0xb2, 0x52, 0x50, 0x32, 0xd4, 0x33, 0x32, 0xd0, 0x33, 0x51, 0xd2, 0x51, 0x50, 0xca, 0x4b,
+        0xcc, 0x4d, 0x45, 0x15, 0x29, 0xcf, 0x2f, 0xca, 0x49, 0x89, 0x2f, 0x4b, 0x2d, 0x2a, 0xce,
+        0xcc, 0xcf, 0x03, 0x4a, 0x19, 0x9b, 0x1b, 0x18, 0xd4, 0x02, 0x00, 0x50, 0x4b, 0x01, 0x02,
+        0x14, 0x03, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x00, 0xa9, 0x52, 0x66,
+        0xcd, 0x17, 0x00, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x01, 0x00, 0x00, 0x00, 0x00, 0x4d, 0x45, 0x54,
+        0x41, 0x2d, 0x49, 0x4e, 0x46, 0x2f, 0x4d, 0x41, 0x4e, 0x49, 0x46, 0x45, 0x53, 0x54, 0x2e,
+        0x4d, 0x46, 0x50, 0x4b, 0x01, 0x02, 0x14, 0x03, 0x14, 0x00, 0x00, 0x00, 0x08, 0x00, 0xc0,
+        0x6b, 0x4e, 0x5d, 0x8f, 0x7c, 0x79, 0xb6, 0x2e, 0x00, 0x00, 0x00, 0x39, 0x00, 0x00, 0x00,
+        0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x01, 0x49,
+        0x00, 0x00, 0x00, 0x76, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x2e, 0x6a, 0x73, 0x6f, 0x6e,
+        0x50, 0x4b, 0x05, 0x06, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x7c, 0x00, 0x00,
+        0x00, 0xa1, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    fn version_lines() -> Vec<u8> {
+        (0..12)
+            .map(|index| {
+                format!(
+                    "{{\"id\": \"1.20.{0}\", \"name\": \"1.20.{0}\", \"world_version\": {1}, \"stable\": true}}\n",
+                    index,
+                    3700 + index
+                )
+            })
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    #[test]
+    fn inflates_stored_blocks() {
+        assert_eq!(STORED[0] >> 1 & 3, 0);
+        assert_eq!(inflate(&STORED).unwrap(), b"Hello, Hello, Hello!");
+    }
+
+    #[test]
+    fn inflates_fixed_huffman_blocks() {
+        assert_eq!(FIXED_HUFFMAN[0] >> 1 & 3, 1);
+        assert_eq!(inflate(&FIXED_HUFFMAN).unwrap(), b"Hello, Hello, Hello!");
+    }
+
+    #[test]
+    fn inflates_dynamic_huffman_blocks() {
+        assert_eq!(DYNAMIC_HUFFMAN[0] >> 1 & 3, 2);
+        assert_eq!(inflate(&DYNAMIC_HUFFMAN).unwrap(), version_lines());
+    }
+
+    #[test]
+    fn rejects_corrupt_data() {
+        // Block type 3 is reserved.
+        assert!(inflate(&[0x07]).is_err());
+        assert!(inflate(&[]).is_err());
+        // A stored block whose length doesn't match its complement.
+        let mut stored = STORED;
+        stored[3] ^= 0xff;
+        assert!(inflate(&stored).is_err());
+        for data in [&STORED[..], &FIXED_HUFFMAN[..], &DYNAMIC_HUFFMAN[..]] {
+            assert!(inflate(&data[..data.len() - 1]).is_err());
+        }
+        // A match that reaches back before the start of the output: a fixed block whose first
+        // symbol is length 3 (257, code 0000001) at distance 1 (code 00000).
+        assert!(inflate(&[0x03, 0x02, 0x00]).is_err());
+    }
+
+    #[test]
+    fn flipped_bits_never_panic() {
+        for index in 0..DYNAMIC_HUFFMAN.len() {
+            for bit in 0..8 {
+                let mut data = DYNAMIC_HUFFMAN;
+                data[index] ^= 1 << bit;
+                let _ = inflate(&data);
+            }
+        }
+    }
+
+    #[test]
+    fn gunzips_a_gzip_file_with_a_name() {
+        let mut data = vec![0x1f, 0x8b, 8, 1 << 3, 0, 0, 0, 0, 0, 3];
+        data.extend_from_slice(b"hello.txt\0");
+        data.extend_from_slice(&FIXED_HUFFMAN);
+        data.extend_from_slice(&[0; 8]);
+        assert_eq!(gunzip(&data).unwrap(), b"Hello, Hello, Hello!");
+        assert!(gunzip(&FIXED_HUFFMAN).is_err());
+    }
+
+    #[test]
+    fn reads_stored_and_deflated_entries_from_a_jar() {
+        let path = std::env::temp_dir().join(format!("msc-test-zip-{}.jar", std::process::id()));
+        std::fs::write(&path, JAR).unwrap();
+        let manifest = read_zip_entry(&path, "META-INF/MANIFEST.MF");
+        let version = read_zip_entry(&path, "version.json");
+        let missing = read_zip_entry(&path, "missing.json");
+        std::fs::write(&path, b"not a zip file").unwrap();
+        let not_a_zip = read_zip_entry(&path, "version.json");
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(manifest.unwrap().unwrap(), b"Manifest-Version: 1.0\r\n");
+        assert_eq!(
+            version.unwrap().unwrap(),
+            &b"{\"id\": \"1.20.4\", \"name\": \"1.20.4\", \"world_version\": 3700}"[..]
+        );
+        assert!(missing.unwrap().is_none());
+        assert!(not_a_zip.is_err());
+    }
+
+    // Runs read_zip_entry on each of `jars`, reusing one file for all of them.
+    fn read_jars(
+        name: &str,
+        jars: impl Iterator<Item = Vec<u8>>,
+    ) -> Vec<Result<Option<Vec<u8>>, Error>> {
+        let path =
+            std::env::temp_dir().join(format!("msc-test-{}-{}.jar", name, std::process::id()));
+        let results = jars
+            .map(|jar| {
+                std::fs::write(&path, jar).unwrap();
+                read_zip_entry(&path, "version.json")
+            })
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+        results
+    }
+
+    #[test]
+    fn rejects_truncated_jars() {
+        // Every prefix is missing at least part of the end of central directory record.
+        let results = read_jars(
+            "zip-truncated",
+            (0..JAR.len()).map(|length| JAR[..length].to_vec()),
+        );
+        assert!(results.iter().all(Result::is_err));
+    }
+
+    #[test]
+    fn rejects_entries_that_point_outside_the_jar() {
+        // The central directory starts at 0xa1, and version.json's entry is the second one in it.
+        let entry = 0xa1 + 46 + "META-INF/MANIFEST.MF".len();
+        let mut huge_size = JAR;
+        huge_size[entry + 20..entry + 24].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut huge_offset = JAR;
+        huge_offset[entry + 42..entry + 46].copy_from_slice(&0xffff_ff00u32.to_le_bytes());
+        let mut huge_directory = JAR;
+        let end = JAR.len() - 22;
+        huge_directory[end + 12..end + 16].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let results = read_jars(
+            "zip-out-of-bounds",
+            [huge_size, huge_offset, huge_directory]
+                .iter()
+                .map(|jar| jar.to_vec()),
+        );
+        assert!(results.iter().all(Result::is_err));
+    }
+
+    #[test]
+    fn flipped_bits_in_a_jar_never_panic() {
+        let jars = (0..JAR.len() * 8).map(|bit| {
+            let mut jar = JAR.to_vec();
+            jar[bit / 8] ^= 1 << (bit % 8);
+            jar
+        });
+        read_jars("zip-flipped", jars);
+    }
+
+    #[test]
+    fn rejects_truncated_gzip_headers() {
+        let mut data = vec![
+            0x1f,
+            0x8b,
+            8,
+            (1 << 2) | (1 << 3) | (1 << 4) | (1 << 1),
+            0,
+            0,
+            0,
+            0,
+            0,
+            3,
+        ];
+        data.extend_from_slice(&[4, 0, b'x', b'y', b'z', b'w']);
+        data.extend_from_slice(b"hello.txt\0a comment\0");
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&FIXED_HUFFMAN);
+        assert_eq!(gunzip(&data).unwrap(), b"Hello, Hello, Hello!");
+        for length in 0..data.len() {
+            assert!(gunzip(&data[..length]).is_err());
+        }
+        // An extra field that claims to be longer than the file.
+        let mut long_extra = data.clone();
+        long_extra[10..12].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(gunzip(&long_extra).is_err());
+    }
+}